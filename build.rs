@@ -0,0 +1,27 @@
+use std::{env, path::Path, process::Command};
+
+// the version `krab --version` and the about popup show, with the git hash of
+// the build if it is made from a git checkout
+fn main() {
+    let version = env::var("CARGO_PKG_VERSION").unwrap();
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty());
+
+    match hash {
+        Some(hash) => println!("cargo:rustc-env=KRAB_VERSION={} ({})", version, hash),
+        None => println!("cargo:rustc-env=KRAB_VERSION={}", version),
+    }
+    // a new commit changes the hash, without git the script runs only once
+    for path in [".git/HEAD", ".git/refs"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
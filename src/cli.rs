@@ -0,0 +1,548 @@
+use clap::{Parser, Subcommand};
+use std::{
+    env, fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+use zeroize::Zeroizing;
+
+use crate::{
+    backup,
+    crypto::{
+        check_user, delete_user,
+        generator::{
+            generate_password, validate_symbols, PasswordPolicy, DEFAULT_PWD_LENGTH,
+            MAX_PWD_LENGTH, MIN_PWD_LENGTH,
+        },
+        strength::{check_record_strength, min_record_bits},
+        user::{display_name, RecordOperationConfig, User},
+    },
+    db::write_private_file,
+    doctor::{data_dir_checks, env_checks, report, terminal_checks},
+    restore,
+};
+
+#[derive(Debug, Parser)]
+#[command(version = crate::VERSION, about = "Crabby keeps your data safe.")]
+pub struct Cli {
+    /// Run a single command instead of starting the TUI
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Absolute path of the data directory, overrides KRAB_DATA_DIR
+    #[arg(long, global = true)]
+    pub data_dir: Option<PathBuf>,
+    /// Read the master password from the first line of this file instead of prompting.
+    /// Preferred over KRAB_MASTER_PASSWORD, which other users may see in process listings
+    #[arg(long, global = true)]
+    pub master_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum Command {
+    /// Print the password stored for a domain
+    Get {
+        username: String,
+        domain: String,
+        /// Label of the record, for a domain with several records
+        #[arg(long, default_value = "")]
+        label: String,
+        /// Print {"domain": ..., "password": ...} (and "label"), errors as {"error": ...}
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print all domains of a user, one per line, a labeled record as `domain (label)`
+    List {
+        username: String,
+        /// Print a JSON array of the domains, errors as {"error": ...}
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add a new record, the password is prompted for (or read from stdin if it is piped)
+    Add {
+        username: String,
+        domain: String,
+        /// Tells the record apart from others of the same domain
+        #[arg(long, default_value = "")]
+        label: String,
+    },
+    /// Write all records of a user to a CSV file, passwords are stored in plaintext
+    Export {
+        username: String,
+        out: PathBuf,
+        /// Overwrite the file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Write every record to <out>/<domain>.txt like pass does, passwords are stored in plaintext
+    ExportPass {
+        username: String,
+        out: PathBuf,
+        /// Overwrite entries that already exist
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add the logins of an unencrypted Bitwarden JSON export
+    ImportBitwarden {
+        username: String,
+        file: PathBuf,
+        /// Only report what would be imported, nothing is written
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check that every record of a user decrypts, without printing any of them
+    Verify { username: String },
+    /// Print a generated password, nothing is stored and no login is needed
+    Gen {
+        /// Number of characters
+        #[arg(long, default_value_t = DEFAULT_PWD_LENGTH, value_parser = pwd_length)]
+        length: usize,
+        /// Leave out uppercase letters
+        #[arg(long)]
+        no_upper: bool,
+        /// Leave out digits
+        #[arg(long)]
+        no_digits: bool,
+        /// Leave out symbols
+        #[arg(long)]
+        no_symbols: bool,
+        /// Use only these symbols, overrides KRAB_PWD_SYMBOLS
+        #[arg(long, value_parser = validate_symbols)]
+        symbols: Option<String>,
+    },
+    /// Archive all user files (still encrypted) into a single tar file
+    Backup { out: PathBuf },
+    /// Delete a user, the file is overwritten with random data first (best-effort on SSDs)
+    Delete {
+        username: String,
+        /// How many times the file is overwritten
+        #[arg(long, default_value_t = 3)]
+        passes: u32,
+    },
+    /// Restore user files from an archive created by backup
+    Restore {
+        archive: PathBuf,
+        /// Overwrite user files that already exist
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check the data directory, the KRAB_* variables and the terminal
+    Doctor,
+}
+
+// a password length the generator accepts
+fn pwd_length(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(length) if (MIN_PWD_LENGTH..=MAX_PWD_LENGTH).contains(&length) => Ok(length),
+        _ => Err(format!(
+            "must be a number from {} to {}",
+            MIN_PWD_LENGTH, MAX_PWD_LENGTH
+        )),
+    }
+}
+
+impl Command {
+    /// Whether the command prints JSON, errors included
+    pub fn json(&self) -> bool {
+        match self {
+            Command::Get { json, .. } | Command::List { json, .. } => *json,
+            _ => false,
+        }
+    }
+}
+
+/// Error message of a command as it is written to stderr
+///
+/// # Arguments
+///
+/// * `e` - The error message
+/// * `json` - Whether the command prints JSON, see `Command::json`
+///
+/// # Returns
+/// * `String` - `{"error": ...}` for JSON output, `Error: ...` otherwise
+pub fn error_output(e: &str, json: bool) -> String {
+    if json {
+        serde_json::json!({ "error": e }).to_string()
+    } else {
+        format!("Error: {}", e)
+    }
+}
+
+/// Read a single line from stdin without the trailing newline
+///
+/// # Arguments
+///
+/// * `prompt` - Text written to stderr before reading, so stdout stays clean for scripts
+///
+/// # Returns
+/// * `Result<String, String>` - The line or an error message
+fn read_line(prompt: &str) -> Result<String, String> {
+    eprint!("{}", prompt);
+    let _ = io::stderr().flush();
+
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+        Err(_) => Err("Could not read from stdin".to_string()),
+    }
+}
+
+// prompts for a password without echoing it on a terminal, piped input is read
+// as a line
+fn read_password(prompt: &str) -> Result<Zeroizing<String>, String> {
+    if io::stdin().is_terminal() {
+        match rpassword::prompt_password(prompt) {
+            Ok(pwd) => Ok(Zeroizing::new(pwd)),
+            Err(_) => Err("Could not read the password".to_string()),
+        }
+    } else {
+        read_line(prompt).map(Zeroizing::new)
+    }
+}
+
+/// Get the master password without echoing it
+///
+/// It is read from the first line of `master_file` if given, then from
+/// `KRAB_MASTER_PASSWORD`, and prompted for otherwise. The environment of a process
+/// can be read by other users on some systems (e.g. in process listings), so the
+/// file (readable only by its owner) is the safer choice for scripts. Without a
+/// terminal the password is read from stdin.
+///
+/// # Arguments
+///
+/// * `master_file` - File holding the master password on its first line
+///
+/// # Returns
+/// * `Result<Zeroizing<String>, String>` - The master password or an error message
+fn master_password(master_file: Option<&Path>) -> Result<Zeroizing<String>, String> {
+    if let Some(master_file) = master_file {
+        let content = match fs::read_to_string(master_file) {
+            Ok(content) => Zeroizing::new(content),
+            Err(_) => return Err("Could not read the master password file".to_string()),
+        };
+        let first_line = content.lines().next().unwrap_or_default();
+        return Ok(Zeroizing::new(first_line.to_string()));
+    }
+    if let Ok(master_pwd) = env::var("KRAB_MASTER_PASSWORD") {
+        return Ok(Zeroizing::new(master_pwd));
+    }
+
+    read_password("Master password: ").map_err(|_| "Could not read the master password".to_string())
+}
+
+fn login(
+    db_path: &Path,
+    username: &str,
+    master_file: Option<&Path>,
+) -> Result<(User, Zeroizing<String>), String> {
+    if !check_user(username, db_path) {
+        return Err("User not found".to_string());
+    }
+
+    let master_pwd = master_password(master_file)?;
+    // the error tells a wrong master password from a file that can not be read
+    let user = User::from(db_path, username, &master_pwd)?;
+    if user.unreadable() > 0 {
+        eprintln!(
+            "{} record(s) could not be read and are skipped, see krab verify",
+            user.unreadable()
+        );
+    }
+
+    Ok((user, master_pwd))
+}
+
+/// Run a non-interactive command
+///
+/// # Arguments
+///
+/// * `command` - The command to run
+/// * `db_path` - Path to the directory where the users data is stored
+/// * `master_file` - File holding the master password, see `master_password`
+///
+/// # Returns
+/// * `Result<(), String>` - Nothing or an error message
+pub fn run(command: Command, db_path: &Path, master_file: Option<&Path>) -> Result<(), String> {
+    match command {
+        Command::Get {
+            username,
+            domain,
+            label,
+            json,
+        } => {
+            let (user, _) = login(db_path, &username, master_file)?;
+            let secret = user
+                .records()
+                .iter()
+                .filter(|r| r.label() == label)
+                .map(|r| r.secret())
+                .find(|(d, _)| *d == domain);
+            match secret {
+                Some((domain, pwd)) if json => {
+                    let pwd = Zeroizing::new(pwd);
+                    let mut output =
+                        serde_json::json!({ "domain": domain, "password": pwd.as_str() });
+                    if !label.is_empty() {
+                        output["label"] = serde_json::json!(label);
+                    }
+                    let output = Zeroizing::new(output.to_string());
+                    println!("{}", output.as_str());
+                    Ok(())
+                }
+                Some((_, pwd)) => {
+                    println!("{}", pwd);
+                    Ok(())
+                }
+                None => Err("Record not found".to_string()),
+            }
+        }
+        Command::List { username, json } => {
+            let (user, _) = login(db_path, &username, master_file)?;
+            let domains: Vec<String> = user
+                .read_only_records()
+                .sorted_by_domain()
+                .records()
+                .iter()
+                .map(|record| display_name(&record.secret().0, record.label()))
+                .collect();
+            if json {
+                println!("{}", serde_json::json!(domains));
+            } else {
+                for domain in domains {
+                    println!("{}", domain);
+                }
+            }
+            Ok(())
+        }
+        Command::Add {
+            username,
+            domain,
+            label,
+        } => {
+            let (mut user, master_pwd) = login(db_path, &username, master_file)?;
+            let pwd = read_password("Password: ")?;
+            check_record_strength(&pwd, min_record_bits())
+                .map_err(|e| format!("{}, `krab gen` generates one", e))?;
+            let config = RecordOperationConfig::builder()
+                .username(&username)
+                .master_pwd(&master_pwd)
+                .domain(&domain)
+                .label(&label)
+                .pwd(&pwd)
+                .path(db_path)
+                .build()?;
+            user.add_record(config)
+        }
+        Command::Export {
+            username,
+            out,
+            force,
+        } => {
+            let (user, master_pwd) = login(db_path, &username, master_file)?;
+            let csv = Zeroizing::new(user.export_csv(&master_pwd)?);
+            match write_private_file(&out, csv.as_bytes(), force) {
+                Ok(_) => Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(format!(
+                    "{} already exists, pass --force to overwrite it",
+                    out.display()
+                )),
+                Err(_) => Err("Could not write to file.".to_string()),
+            }
+        }
+        Command::ExportPass {
+            username,
+            out,
+            force,
+        } => {
+            let (user, master_pwd) = login(db_path, &username, master_file)?;
+            let exported = user.export_pass(&master_pwd, &out, force).map_err(|e| {
+                if e.ends_with("already exists") {
+                    format!("{}, pass --force to overwrite it", e)
+                } else {
+                    e
+                }
+            })?;
+            println!("Exported {} records", exported);
+            Ok(())
+        }
+        Command::ImportBitwarden {
+            username,
+            file,
+            dry_run,
+        } => {
+            let json = match fs::read_to_string(file) {
+                Ok(json) => json,
+                Err(_) => return Err("Could not read file.".to_string()),
+            };
+            let (mut user, master_pwd) = login(db_path, &username, master_file)?;
+            if dry_run {
+                let report =
+                    user.validate_bitwarden_import(&json, &username, &master_pwd, db_path)?;
+                println!("Would import {} records", report.added.len());
+                for duplicate in report.duplicates {
+                    println!("Duplicate {}", duplicate);
+                }
+                for (item, name) in report.malformed {
+                    println!("Malformed item {}: {}", item, name);
+                }
+                return Ok(());
+            }
+            let summary = user.import_bitwarden(&json, &username, &master_pwd, db_path)?;
+            println!("Imported {} records", summary.imported.len());
+            for skipped in summary.skipped {
+                println!("Skipped {}", skipped);
+            }
+            Ok(())
+        }
+        Command::Delete { username, passes } => {
+            login(db_path, &username, master_file)?;
+            delete_user(db_path, &username, passes)
+        }
+        Command::Verify { username } => {
+            if !check_user(&username, db_path) {
+                return Err("User not found".to_string());
+            }
+            let master_pwd = master_password(master_file)?;
+            let report = User::verify(db_path, &username, &master_pwd)?;
+            if report.failed.is_empty() {
+                println!("OK: {} records", report.records);
+                return Ok(());
+            }
+            let offsets: Vec<String> = report.failed.iter().map(u32::to_string).collect();
+            Err(format!(
+                "{} of {} records could not be read, at byte offsets {}",
+                report.failed.len(),
+                report.records,
+                offsets.join(", ")
+            ))
+        }
+        Command::Gen {
+            length,
+            no_upper,
+            no_digits,
+            no_symbols,
+            symbols,
+        } => {
+            let policy = PasswordPolicy::from_env();
+            let pwd = Zeroizing::new(generate_password(&PasswordPolicy {
+                length,
+                upper: !no_upper,
+                digits: !no_digits,
+                symbols: !no_symbols,
+                custom_special: symbols.or(policy.custom_special),
+            }));
+            println!("{}", pwd.as_str());
+            Ok(())
+        }
+        Command::Backup { out } => backup(db_path, &out).map_err(|e| e.to_string()),
+        Command::Restore { archive, force } => {
+            restore(&archive, db_path, force).map_err(|e| e.to_string())
+        }
+        Command::Doctor => doctor(Some(db_path)),
+    }
+}
+
+/// Print the report of `krab doctor`
+///
+/// # Arguments
+///
+/// * `data_dir` - Data directory given with `--data-dir`
+///
+/// # Returns
+/// * `Result<(), String>` - Nothing or an error message if a blocking problem was found
+pub fn doctor(data_dir: Option<&Path>) -> Result<(), String> {
+    let mut checks = vec![];
+    checks.extend(data_dir_checks(data_dir));
+    checks.extend(env_checks(dotenv::dotenv().ok(), |name| {
+        env::var(name).ok()
+    }));
+    checks.extend(terminal_checks());
+    println!("{}", report(&checks));
+
+    match checks.iter().filter(|check| check.blocking).count() {
+        0 => Ok(()),
+        1 => Err("Found a blocking problem".to_string()),
+        count => Err(format!("Found {} blocking problems", count)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gen_args() {
+        let cli = Cli::try_parse_from(["krab", "gen", "--length", "12", "--no-symbols"]).unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(Command::Gen {
+                length: 12,
+                no_upper: false,
+                no_digits: false,
+                no_symbols: true,
+                symbols: None,
+            })
+        );
+        assert!(matches!(
+            Cli::try_parse_from(["krab", "gen", "--symbols=-_."]).unwrap().command,
+            Some(Command::Gen { symbols: Some(symbols), .. }) if symbols == "-_."
+        ));
+        assert!(Cli::try_parse_from(["krab", "gen", "--symbols", "ab"]).is_err());
+        assert!(matches!(
+            Cli::try_parse_from(["krab", "gen"]).unwrap().command,
+            Some(Command::Gen {
+                length: DEFAULT_PWD_LENGTH,
+                ..
+            })
+        ));
+        assert!(Cli::try_parse_from(["krab", "gen", "--length", "4"]).is_err());
+        assert!(Cli::try_parse_from(["krab", "gen", "--length", "65"]).is_err());
+    }
+
+    #[test]
+    fn test_error_output() {
+        assert_eq!(
+            error_output("User not found", false),
+            "Error: User not found"
+        );
+        assert_eq!(
+            error_output("Wrong \"master\" password", true),
+            r#"{"error":"Wrong \"master\" password"}"#
+        );
+    }
+
+    #[test]
+    fn test_master_password_from_file() {
+        let path = env::temp_dir().join(format!("krab-master-{}", std::process::id()));
+        fs::write(&path, "master password\nignored\n").unwrap();
+
+        let master_pwd = master_password(Some(&path));
+
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(master_pwd.unwrap().as_str(), "master password");
+        assert!(master_password(Some(Path::new("/nonexistent/master"))).is_err());
+    }
+
+    #[test]
+    fn test_login_reports_unreadable_file() {
+        let dir = env::temp_dir().join(format!("krab-login-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let master_file = dir.join("master");
+        fs::write(&master_file, "master").unwrap();
+        let config = RecordOperationConfig::new("crab", "master", "example.com", "password", &dir);
+        User::new(&config).unwrap();
+        let file = crate::crypto::user_path("crab", &dir);
+        let bytes = fs::read(&file).unwrap();
+        fs::write(&file, &bytes[..bytes.len() - 1]).unwrap();
+
+        let res = login(&dir, "crab", Some(&master_file));
+        let expected = User::from(&dir, "crab", "master");
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let e = res.err().unwrap();
+        assert_ne!(e, "Wrong master password");
+        assert_eq!(Err(e), expected);
+    }
+}
@@ -1,15 +1,61 @@
 use sha2::{Digest, Sha256};
-use std::{path::PathBuf, str};
+use std::{
+    path::{Path, PathBuf},
+    str,
+};
 
+use crate::db::secure_remove_file;
+
+pub mod bitwarden;
+pub mod generator;
+pub mod hibp;
 mod models;
+pub mod strength;
 pub mod user;
 
-pub fn check_user(username: &str, path: PathBuf) -> bool {
-    let hashed_username = hash(username.to_string());
-    match path.join(hashed_username).exists() {
-        true => true,
-        false => false,
+pub fn check_user(username: &str, path: &Path) -> bool {
+    user_path(username, path).exists()
+}
+
+/// Trims and lowercases a username, so `Alice` and `alice` are the same user
+pub fn normalize_username(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+/// Name of the file the users data is stored in
+///
+/// The name is the hash of the normalized username. The normalization is one-way:
+/// a file created before it existed (from a mixed case username) is still found
+/// under the hash of the original username, until the user is created again.
+///
+/// # Arguments
+///
+/// * `username` - The username of the user
+/// * `path` - Path to the directory where the users data is stored
+///
+/// # Returns
+/// * `String` - The file name
+pub fn user_file_name(username: &str, path: &Path) -> String {
+    let normalized = hash(normalize_username(username));
+    let original = hash(username.to_string());
+    if !path.join(&normalized).exists() && path.join(&original).exists() {
+        return original;
     }
+
+    normalized
+}
+
+/// Path of the file the users data is stored in, see `user_file_name`
+///
+/// # Arguments
+///
+/// * `username` - The username of the user
+/// * `dir` - Path to the directory where the users data is stored
+///
+/// # Returns
+/// * `PathBuf` - The path of the file, it may not exist
+pub fn user_path(username: &str, dir: &Path) -> PathBuf {
+    dir.join(user_file_name(username, dir))
 }
 
 pub fn hash(data: String) -> String {
@@ -18,3 +64,25 @@ pub fn hash(data: String) -> String {
     let result = hasher.finalize();
     format!("{:x}", result)
 }
+
+/// Delete a user, overwriting the users file with random data first
+///
+/// This is best-effort: on SSDs (wear-leveling) the old data may still be recoverable.
+///
+/// # Arguments
+///
+/// * `path` - Path to the directory where the users data is stored
+/// * `username` - The username of the user
+/// * `passes` - How many times the file is overwritten before it is removed
+///
+/// # Returns
+/// * `Result<(), String>` - Nothing or an error message
+pub fn delete_user(path: &Path, username: &str, passes: u32) -> Result<(), String> {
+    if !check_user(username, path) {
+        return Err("User not found".to_string());
+    }
+    match secure_remove_file(&user_path(username, path), passes) {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Could not delete user".to_string()),
+    }
+}
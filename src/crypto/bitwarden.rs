@@ -0,0 +1,191 @@
+use serde::Deserialize;
+use zeroize::Zeroize;
+
+#[derive(Debug, Deserialize)]
+struct Export {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    name: String,
+    login: Option<Login>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Login {
+    username: Option<String>,
+    password: Option<String>,
+    uris: Option<Vec<Uri>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Uri {
+    uri: Option<String>,
+}
+
+/// A login read from a Bitwarden export
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitwardenLogin {
+    /// Position of the item in the export, counted from 1
+    pub item: usize,
+    /// Name of the item
+    pub name: String,
+    pub domain: String,
+    /// Username of the login, empty if it has none
+    pub username: String,
+    pub password: String,
+}
+
+impl Drop for BitwardenLogin {
+    fn drop(&mut self) {
+        self.password.zeroize();
+    }
+}
+
+/// Logins read from a Bitwarden export
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BitwardenLogins {
+    /// Logins with a password, they are checked when they are turned into records
+    pub logins: Vec<BitwardenLogin>,
+    /// Position (counted from 1) and name of the items without a login or a password
+    pub skipped: Vec<(usize, String)>,
+}
+
+// strips the scheme and everything after the host, `https://a.com/login` -> `a.com`
+fn domain_from_uri(uri: &str) -> String {
+    let without_scheme = match uri.find("://") {
+        Some(i) => &uri[i + 3..],
+        None => uri,
+    };
+    without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Parse an unencrypted Bitwarden JSON export
+///
+/// The domain is taken from the first URI of a login (falling back to the
+/// item name). Items without a login or a password are skipped, the logins are
+/// not checked otherwise.
+///
+/// # Arguments
+///
+/// * `json` - Content of the export file
+///
+/// # Returns
+/// * `Result<BitwardenLogins, String>` - The logins or an error message
+pub fn parse(json: &str) -> Result<BitwardenLogins, String> {
+    let export: Export = match serde_json::from_str(json) {
+        Ok(export) => export,
+        Err(_) => return Err("Could not parse Bitwarden export".to_string()),
+    };
+
+    let mut result = BitwardenLogins::default();
+    for (i, item) in export.items.into_iter().enumerate() {
+        let login = match item.login {
+            Some(login) => login,
+            None => {
+                result.skipped.push((i + 1, item.name));
+                continue;
+            }
+        };
+        let password = match login.password {
+            Some(password) if !password.is_empty() => password,
+            _ => {
+                result.skipped.push((i + 1, item.name));
+                continue;
+            }
+        };
+        let domain = login
+            .uris
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|u| u.uri)
+            .map(|uri| domain_from_uri(&uri))
+            .unwrap_or(item.name.clone());
+
+        result.logins.push(BitwardenLogin {
+            item: i + 1,
+            name: item.name,
+            domain,
+            username: login.username.unwrap_or_default(),
+            password,
+        });
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_from_uri() {
+        assert_eq!(domain_from_uri("https://example.com/login"), "example.com");
+        assert_eq!(domain_from_uri("example.com"), "example.com");
+        assert_eq!(domain_from_uri("http://a.example.com?x=1"), "a.example.com");
+    }
+
+    #[test]
+    fn test_parse_success() {
+        let json = r#"{
+            "items": [
+                {
+                    "name": "Example",
+                    "login": {
+                        "username": "crab",
+                        "password": "password",
+                        "uris": [{ "uri": "https://example.com/login" }]
+                    }
+                },
+                {
+                    "name": "example2.com",
+                    "login": { "password": "password2", "uris": [] }
+                },
+                { "name": "Secure note", "notes": "text" },
+                {
+                    "name": "No password",
+                    "login": { "uris": [{ "uri": "https://example3.com" }] }
+                }
+            ]
+        }"#;
+
+        let result = parse(json).unwrap();
+
+        assert_eq!(
+            result.logins,
+            vec![
+                BitwardenLogin {
+                    item: 1,
+                    name: "Example".to_string(),
+                    domain: "example.com".to_string(),
+                    username: "crab".to_string(),
+                    password: "password".to_string(),
+                },
+                BitwardenLogin {
+                    item: 2,
+                    name: "example2.com".to_string(),
+                    domain: "example2.com".to_string(),
+                    username: String::new(),
+                    password: "password2".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            result.skipped,
+            vec![
+                (3, "Secure note".to_string()),
+                (4, "No password".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_fail_invalid_json() {
+        assert!(parse("{ \"items\": 1 }").is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+use std::env;
+
+/// Length of a generated password
+pub const DEFAULT_PWD_LENGTH: usize = 20;
+/// Shortest and longest password the policy can be set to
+pub const MIN_PWD_LENGTH: usize = 8;
+pub const MAX_PWD_LENGTH: usize = 64;
+// no whitespace, it is easily lost when a password is copied or typed
+const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+pub(crate) const SYMBOLS: &[u8] = b"!#$%&*+-.:;=?@^_~";
+
+/// Characters a generated password is made of
+///
+/// Lowercase letters are always used, uppercase letters, digits and symbols can
+/// be left out for sites that reject them. A generated password has at least
+/// one character of every class it uses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPolicy {
+    pub length: usize,
+    pub upper: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Symbols used instead of the default ones, for sites that only allow some
+    pub custom_special: Option<String>,
+}
+
+/// Check a set of symbols for `PasswordPolicy::custom_special`
+///
+/// # Arguments
+///
+/// * `symbols` - The symbols, printable ASCII characters other than letters and digits
+///
+/// # Returns
+/// * `Result<String, String>` - The symbols without duplicates or an error message
+pub fn validate_symbols(symbols: &str) -> Result<String, String> {
+    if symbols.is_empty() {
+        return Err("The symbols can not be empty".to_string());
+    }
+    // no whitespace, it is easily lost when a password is copied or typed
+    if !symbols.chars().all(|c| c.is_ascii_punctuation()) {
+        return Err("The symbols must be printable ASCII, not letters or digits".to_string());
+    }
+    let mut unique = String::new();
+    for c in symbols.chars() {
+        if !unique.contains(c) {
+            unique.push(c);
+        }
+    }
+
+    Ok(unique)
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            length: DEFAULT_PWD_LENGTH,
+            upper: true,
+            digits: true,
+            symbols: true,
+            custom_special: None,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// The default policy with the symbols set with `KRAB_PWD_SYMBOLS`, invalid
+    /// symbols are ignored
+    pub fn from_env() -> Self {
+        PasswordPolicy {
+            custom_special: env::var("KRAB_PWD_SYMBOLS")
+                .ok()
+                .and_then(|symbols| validate_symbols(&symbols).ok()),
+            ..PasswordPolicy::default()
+        }
+    }
+
+    // the character classes in use, lowercase letters first
+    fn classes(&self) -> Vec<&[u8]> {
+        let symbols = match &self.custom_special {
+            Some(symbols) => symbols.as_bytes(),
+            None => SYMBOLS,
+        };
+        [
+            (LOWER, true),
+            (UPPER, self.upper),
+            (DIGITS, self.digits),
+            (symbols, self.symbols),
+        ]
+        .into_iter()
+        .filter(|(_, used)| *used)
+        .map(|(class, _)| class)
+        .collect()
+    }
+
+    /// Make generated passwords one character longer, up to `MAX_PWD_LENGTH`
+    pub fn longer(&mut self) {
+        self.length = (self.length + 1).min(MAX_PWD_LENGTH);
+    }
+
+    /// Make generated passwords one character shorter, down to `MIN_PWD_LENGTH`
+    pub fn shorter(&mut self) {
+        self.length = self.length.saturating_sub(1).max(MIN_PWD_LENGTH);
+    }
+
+    /// Whether a password has the length of the policy, only characters of the
+    /// classes it uses and at least one character of each of them
+    pub fn matches(&self, pwd: &str) -> bool {
+        let classes = self.classes();
+        pwd.len() == self.length
+            && pwd
+                .bytes()
+                .all(|c| classes.iter().any(|class| class.contains(&c)))
+            && classes
+                .iter()
+                .all(|class| pwd.bytes().any(|c| class.contains(&c)))
+    }
+
+    /// Entropy of a generated password in bits, `length * log2(charset size)`
+    pub fn entropy_bits(&self) -> f64 {
+        self.length as f64 * (self.classes().concat().len() as f64).log2()
+    }
+
+    /// Short description like `20 chars, a-z A-Z 0-9 !#$`
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} chars, a-z", self.length);
+        if self.upper {
+            summary.push_str(" A-Z");
+        }
+        if self.digits {
+            summary.push_str(" 0-9");
+        }
+        match &self.custom_special {
+            Some(symbols) if self.symbols => summary.push_str(&format!(" {}", symbols)),
+            _ if self.symbols => summary.push_str(" !#$"),
+            _ => {}
+        }
+
+        summary
+    }
+}
+
+/// Generate a random password that matches a policy
+///
+/// # Arguments
+///
+/// * `policy` - Length and characters of the password
+///
+/// # Returns
+/// * `String` - The password
+pub fn generate_password(policy: &PasswordPolicy) -> String {
+    generate_password_with_rng(policy, &mut OsRng)
+}
+
+/// Generate a random password with the given random number generator
+///
+/// `generate_password` passes the operating system generator, a seeded one makes
+/// the output repeatable for tests. Passwords are drawn until one has a character
+/// of every class the policy uses.
+///
+/// # Arguments
+///
+/// * `policy` - Length and characters of the password
+/// * `rng` - Source of randomness
+///
+/// # Returns
+/// * `String` - The password
+pub fn generate_password_with_rng<R: Rng + ?Sized>(policy: &PasswordPolicy, rng: &mut R) -> String {
+    let charset = policy.classes().concat();
+    loop {
+        let pwd: String = (0..policy.length)
+            .map(|_| *charset.choose(rng).unwrap() as char)
+            .collect();
+        if policy.matches(&pwd) {
+            return pwd;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_generate_password() {
+        let policy = PasswordPolicy::default();
+        let pwd = generate_password(&policy);
+
+        assert_eq!(pwd.len(), DEFAULT_PWD_LENGTH);
+        assert!(policy.matches(&pwd));
+        assert_ne!(pwd, generate_password(&policy));
+    }
+
+    #[test]
+    fn test_generate_password_with_rng() {
+        let policy = PasswordPolicy::default();
+        let pwd = generate_password_with_rng(&policy, &mut StdRng::seed_from_u64(7));
+
+        assert!(policy.matches(&pwd));
+        assert_eq!(
+            pwd,
+            generate_password_with_rng(&policy, &mut StdRng::seed_from_u64(7))
+        );
+        assert_ne!(
+            pwd,
+            generate_password_with_rng(&policy, &mut StdRng::seed_from_u64(8))
+        );
+    }
+
+    #[test]
+    fn test_generate_password_policy() {
+        let policy = PasswordPolicy {
+            length: MIN_PWD_LENGTH,
+            upper: true,
+            digits: true,
+            symbols: false,
+            custom_special: None,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let pwd = generate_password_with_rng(&policy, &mut rng);
+            assert_eq!(pwd.len(), MIN_PWD_LENGTH);
+            assert!(pwd.bytes().all(|c| c.is_ascii_alphanumeric()));
+            assert!(pwd.bytes().any(|c| c.is_ascii_uppercase()));
+            assert!(pwd.bytes().any(|c| c.is_ascii_digit()));
+        }
+    }
+
+    #[test]
+    fn test_custom_special() {
+        let policy = PasswordPolicy {
+            custom_special: Some(validate_symbols("-_.").unwrap()),
+            ..PasswordPolicy::default()
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let pwd = generate_password_with_rng(&policy, &mut rng);
+            assert!(policy.matches(&pwd));
+            assert!(pwd
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)));
+        }
+        assert!(!policy.matches("Abcdefghijklmnopq1!"));
+        assert_eq!(policy.summary(), "20 chars, a-z A-Z 0-9 -_.");
+        assert_eq!(policy.entropy_bits(), 20.0 * 65f64.log2());
+
+        assert_eq!(validate_symbols("-_-."), Ok("-_.".to_string()));
+        assert!(validate_symbols("").is_err());
+        assert!(validate_symbols("- _").is_err());
+        assert!(validate_symbols("-a").is_err());
+        assert!(validate_symbols("-€").is_err());
+    }
+
+    #[test]
+    fn test_password_policy() {
+        let mut policy = PasswordPolicy {
+            length: 4,
+            upper: false,
+            digits: true,
+            symbols: true,
+            custom_special: None,
+        };
+
+        assert!(policy.matches("a1b!"));
+        assert!(!policy.matches("a1bc"));
+        assert!(!policy.matches("A1b!"));
+        assert!(!policy.matches("a1b!c"));
+        assert_eq!(policy.summary(), "4 chars, a-z 0-9 !#$");
+        assert_eq!(policy.entropy_bits(), 4.0 * 53f64.log2());
+
+        policy.shorter();
+        assert_eq!(policy.length, MIN_PWD_LENGTH);
+        policy.length = MAX_PWD_LENGTH;
+        policy.longer();
+        assert_eq!(policy.length, MAX_PWD_LENGTH);
+    }
+}
@@ -0,0 +1,127 @@
+use sha1::{Digest, Sha1};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+// the range API is queried with the first 5 characters of the hash
+const PREFIX_LEN: usize = 5;
+
+/// Uppercase hex SHA-1 of a password, the format HaveIBeenPwned uses
+pub fn sha1_hash(pwd: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(pwd);
+    let result = hasher.finalize();
+    format!("{:X}", result)
+}
+
+// count of a `HASH:COUNT` line if the hash matches, lines of a range file only
+// contain the suffix of the hash, lines of the full list contain all of it
+fn count_in_line(line: &str, hash: &str) -> Option<u64> {
+    let (line_hash, count) = line.trim().split_once(':')?;
+    let matches = if line_hash.len() == hash.len() {
+        line_hash.eq_ignore_ascii_case(hash)
+    } else {
+        line_hash.eq_ignore_ascii_case(&hash[PREFIX_LEN..])
+    };
+    if matches {
+        count.trim().parse().ok()
+    } else {
+        None
+    }
+}
+
+fn count_in_lines(lines: impl Iterator<Item = String>, hash: &str) -> u64 {
+    lines
+        .filter_map(|line| count_in_line(&line, hash))
+        .next()
+        .unwrap_or(0)
+}
+
+/// How often a password appears in known breaches, checked against a local file
+///
+/// The password never leaves the machine. The file is either the response of
+/// the range endpoint for the prefix of the password (`SUFFIX:COUNT` lines) or
+/// the downloaded list of all hashes (`HASH:COUNT` lines).
+///
+/// # Arguments
+///
+/// * `pwd` - The password to check
+/// * `file` - Path to the range file or the full list
+///
+/// # Returns
+/// * `Result<u64, String>` - The breach count (zero if not found) or an error message
+pub fn breach_count_offline(pwd: &str, file: &Path) -> Result<u64, String> {
+    let file = match File::open(file) {
+        Ok(file) => file,
+        Err(_) => return Err("Could not read file.".to_string()),
+    };
+    let lines = BufReader::new(file).lines().map_while(Result::ok);
+
+    Ok(count_in_lines(lines, &sha1_hash(pwd)))
+}
+
+/// How often a password appears in known breaches, checked with the HaveIBeenPwned range API
+///
+/// Only the first 5 characters of the SHA-1 of the password are sent (k-anonymity).
+///
+/// # Arguments
+///
+/// * `pwd` - The password to check
+///
+/// # Returns
+/// * `Result<u64, String>` - The breach count (zero if not found) or an error message
+#[cfg(feature = "hibp-online")]
+pub fn breach_count_online(pwd: &str) -> Result<u64, String> {
+    let hash = sha1_hash(pwd);
+    let url = format!(
+        "https://api.pwnedpasswords.com/range/{}",
+        &hash[..PREFIX_LEN]
+    );
+    let response = match ureq::get(&url).call() {
+        Ok(response) => response,
+        Err(_) => return Err("Could not reach HaveIBeenPwned".to_string()),
+    };
+    let body = match response.into_string() {
+        Ok(body) => body,
+        Err(_) => return Err("Could not read HaveIBeenPwned response".to_string()),
+    };
+
+    Ok(count_in_lines(body.lines().map(str::to_string), &hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha1_hash() {
+        assert_eq!(
+            sha1_hash("password"),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+
+    #[test]
+    fn test_count_in_lines() {
+        let hash = sha1_hash("password");
+        let range = "003D68EB55068C33ACE09247EE4C639306B:3\n\
+                     1E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824\n";
+        assert_eq!(
+            count_in_lines(range.lines().map(str::to_string), &hash),
+            9545824
+        );
+
+        let full = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824";
+        assert_eq!(
+            count_in_lines(full.lines().map(str::to_string), &hash),
+            9545824
+        );
+
+        assert_eq!(
+            count_in_lines(range.lines().map(str::to_string), &sha1_hash("a")),
+            0
+        );
+    }
+}
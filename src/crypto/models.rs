@@ -1,12 +1,36 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Name of a record as it is listed, `domain (label)` if it has a label
+///
+/// A domain can have several records, told apart by their labels (like `login`
+/// and `pin` of a bank).
+///
+/// # Arguments
+///
+/// * `domain` - The domain of the record
+/// * `label` - The label of the record, empty for none
+///
+/// # Returns
+/// * `String` - The domain, followed by the label in parentheses if there is one
+pub fn display_name(domain: &str, label: &str) -> String {
+    if label.is_empty() {
+        domain.to_string()
+    } else {
+        format!("{} ({})", domain, label)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct RecordOperationConfig {
     pub username: String,
     pub master_pwd: String,
     pub domain: String,
+    /// Tells the record apart from others of the same domain, empty for none
+    pub label: String,
     pub pwd: String,
     pub path: PathBuf,
+    /// Tags of the record, without whitespace or commas
+    pub tags: Vec<String>,
 }
 
 impl RecordOperationConfig {
@@ -15,14 +39,227 @@ impl RecordOperationConfig {
         master_pwd: &str,
         domain: &str,
         pwd: &str,
-        path: &PathBuf,
+        path: &Path,
     ) -> RecordOperationConfig {
         RecordOperationConfig {
             username: username.to_string(),
             master_pwd: master_pwd.to_string(),
             domain: domain.to_string(),
+            label: String::new(),
             pwd: pwd.to_string(),
-            path: path.clone(),
+            path: path.to_path_buf(),
+            tags: vec![],
         }
     }
+
+    pub fn builder() -> RecordOperationConfigBuilder {
+        RecordOperationConfigBuilder::default()
+    }
+
+    /// Check the config before it is used to create or change a user
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Nothing or an error message for the first invalid field
+    pub fn validate(&self) -> Result<(), String> {
+        if self.username.trim().is_empty() {
+            return Err("Username can not be empty".to_string());
+        }
+        if self.master_pwd.trim().is_empty() {
+            return Err("Master password can not be empty".to_string());
+        }
+        if self.domain.is_empty() {
+            return Err("Domain can not be empty".to_string());
+        }
+        // the domain is shown and looked up as a single word
+        if self.domain.contains(char::is_whitespace) {
+            return Err("Domain can not contain whitespace".to_string());
+        }
+        // tags are shown and entered comma separated
+        if self
+            .tags
+            .iter()
+            .any(|tag| tag.is_empty() || tag.contains(|c: char| c == ',' || c.is_whitespace()))
+        {
+            return Err("Tags can not be empty or contain commas or whitespace".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Builds a `RecordOperationConfig` with named fields, see `RecordOperationConfig::builder`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RecordOperationConfigBuilder {
+    username: String,
+    master_pwd: String,
+    domain: String,
+    label: String,
+    pwd: String,
+    path: Option<PathBuf>,
+    tags: Vec<String>,
+}
+
+impl RecordOperationConfigBuilder {
+    pub fn username(mut self, username: &str) -> Self {
+        self.username = username.to_string();
+        self
+    }
+
+    pub fn master_pwd(mut self, master_pwd: &str) -> Self {
+        self.master_pwd = master_pwd.to_string();
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = domain.to_string();
+        self
+    }
+
+    /// Tells the record apart from others of the same domain
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = label.to_string();
+        self
+    }
+
+    pub fn pwd(mut self, pwd: &str) -> Self {
+        self.pwd = pwd.to_string();
+        self
+    }
+
+    pub fn path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn tags(mut self, tags: &[&str]) -> Self {
+        self.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        self
+    }
+
+    /// # Returns
+    /// * `Result<RecordOperationConfig, String>` - The validated config or an error message
+    pub fn build(self) -> Result<RecordOperationConfig, String> {
+        let path = match self.path {
+            Some(path) => path,
+            None => return Err("Path is not set".to_string()),
+        };
+        let config = RecordOperationConfig {
+            username: self.username,
+            master_pwd: self.master_pwd,
+            domain: self.domain,
+            label: self.label,
+            pwd: self.pwd,
+            path,
+            tags: self.tags,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Outcome of importing records from another password manager
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ImportSummary {
+    /// Domains of the records that were added
+    pub imported: Vec<String>,
+    /// Entries that were not added (unsupported or invalid entries or already existing domains)
+    pub skipped: Vec<String>,
+}
+
+/// What an import would do, made without writing anything
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ImportReport {
+    /// Domains of the records that would be added
+    pub added: Vec<String>,
+    /// Domains that already exist (or repeat in the import) and would be skipped
+    pub duplicates: Vec<String>,
+    /// Position (counted from 1) and name of the entries that can not be stored
+    pub malformed: Vec<(usize, String)>,
+}
+
+/// Result of checking that every record of a user decrypts
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VerifyReport {
+    /// Number of records found in the users file
+    pub records: usize,
+    /// Byte offsets of the records that could not be read or decrypted
+    pub failed: Vec<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_success() {
+        let config = RecordOperationConfig::builder()
+            .username("crab")
+            .master_pwd("master")
+            .domain("example.com")
+            .pwd("password")
+            .path(Path::new("/tmp"))
+            .build();
+
+        assert_eq!(
+            config,
+            Ok(RecordOperationConfig::new(
+                "crab",
+                "master",
+                "example.com",
+                "password",
+                Path::new("/tmp")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(display_name("bank.com", "pin"), "bank.com (pin)");
+        assert_eq!(display_name("bank.com", ""), "bank.com");
+
+        let config = RecordOperationConfig::builder()
+            .username("crab")
+            .master_pwd("master")
+            .domain("bank.com")
+            .label("pin")
+            .pwd("1234")
+            .path(Path::new("/tmp"))
+            .build()
+            .unwrap();
+        assert_eq!(config.domain, "bank.com");
+        assert_eq!(config.label, "pin");
+
+        // a domain may hold a `#`, like a URL with a fragment
+        let config = RecordOperationConfig::builder()
+            .username("crab")
+            .master_pwd("master")
+            .domain("example.com/#login")
+            .pwd("password")
+            .path(Path::new("/tmp"))
+            .build()
+            .unwrap();
+        assert_eq!(config.domain, "example.com/#login");
+        assert!(config.label.is_empty());
+    }
+
+    #[test]
+    fn test_builder_fail_validation() {
+        let builder = RecordOperationConfig::builder()
+            .username("crab")
+            .master_pwd("master")
+            .domain("example.com")
+            .path(Path::new("/tmp"));
+
+        assert!(builder.clone().username(" ").build().is_err());
+        assert!(builder.clone().master_pwd("  ").build().is_err());
+        assert!(builder.clone().domain("").build().is_err());
+        assert!(builder.clone().domain("example .com").build().is_err());
+        assert!(builder.clone().tags(&["work,home"]).build().is_err());
+        assert!(builder.clone().tags(&[""]).build().is_err());
+        assert!(RecordOperationConfig::builder()
+            .username("crab")
+            .master_pwd("master")
+            .domain("example.com")
+            .build()
+            .is_err());
+    }
 }
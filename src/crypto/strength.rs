@@ -0,0 +1,185 @@
+use std::env;
+
+use super::generator::SYMBOLS;
+
+// printable ASCII characters that are neither letters nor digits, space included
+const PRINTABLE_SYMBOLS: usize = 33;
+
+/// Rough strength of a password, from its length and the character classes it uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strength {
+    Weak,
+    Fair,
+    Strong,
+}
+
+// number of character classes (lowercase, uppercase, digits, others) in a password
+fn character_classes(pwd: &str) -> usize {
+    let classes: [fn(&char) -> bool; 4] = [
+        char::is_ascii_lowercase,
+        char::is_ascii_uppercase,
+        char::is_ascii_digit,
+        |c| !c.is_ascii_alphanumeric(),
+    ];
+    classes
+        .iter()
+        .filter(|class| pwd.chars().any(|c| class(&c)))
+        .count()
+}
+
+/// Estimate the entropy of a password in bits
+///
+/// Every character is counted as drawn at random from the character classes the
+/// password uses, so a password generated with a policy gets the entropy of the
+/// policy. Other characters than letters and digits count as the symbols the
+/// generator uses if they all are, else as any printable ASCII symbol.
+///
+/// # Arguments
+///
+/// * `pwd` - The password
+///
+/// # Returns
+/// * `f64` - The estimated entropy
+pub fn password_entropy_bits(pwd: &str) -> f64 {
+    let mut pool = 0;
+    if pwd.chars().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if pwd.chars().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if pwd.chars().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    let mut others = pwd
+        .chars()
+        .filter(|c| !c.is_ascii_alphanumeric())
+        .peekable();
+    if others.peek().is_some() {
+        pool += if others.all(|c| c.is_ascii() && SYMBOLS.contains(&(c as u8))) {
+            SYMBOLS.len()
+        } else {
+            PRINTABLE_SYMBOLS
+        };
+    }
+    if pool == 0 {
+        return 0.0;
+    }
+
+    pwd.chars().count() as f64 * (pool as f64).log2()
+}
+
+/// Estimate the strength of a password
+///
+/// Passwords shorter than 8 characters or from a single character class are weak,
+/// passwords of at least 12 characters from three or more classes are strong.
+///
+/// # Arguments
+///
+/// * `pwd` - The password
+///
+/// # Returns
+/// * `Strength` - The estimated strength
+pub fn password_strength(pwd: &str) -> Strength {
+    let length = pwd.chars().count();
+    let classes = character_classes(pwd);
+    if length < 8 || classes <= 1 {
+        Strength::Weak
+    } else if length >= 12 && classes >= 3 {
+        Strength::Strong
+    } else {
+        Strength::Fair
+    }
+}
+
+/// Entropy in bits a record password needs before it is stored, set with
+/// `KRAB_ENFORCE_RECORD_STRENGTH` (unset or 0 stores any password)
+pub fn min_record_bits() -> Option<u32> {
+    min_record_bits_from(env::var("KRAB_ENFORCE_RECORD_STRENGTH").ok().as_deref())
+}
+
+fn min_record_bits_from(value: Option<&str>) -> Option<u32> {
+    match value.and_then(|v| v.trim().parse().ok()) {
+        Some(0) | None => None,
+        Some(bits) => Some(bits),
+    }
+}
+
+/// Check that a record password has at least the given entropy
+///
+/// # Arguments
+///
+/// * `pwd` - The password
+/// * `min_bits` - The entropy needed, `None` accepts any password
+///
+/// # Returns
+/// * `Result<(), String>` - Nothing or an error message
+pub fn check_record_strength(pwd: &str, min_bits: Option<u32>) -> Result<(), String> {
+    let min_bits = match min_bits {
+        Some(min_bits) => min_bits,
+        None => return Ok(()),
+    };
+    let bits = password_entropy_bits(pwd);
+    if bits < min_bits as f64 {
+        return Err(format!(
+            "The password is too weak, ~{} bits of the {} needed",
+            bits.round(),
+            min_bits
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::generator::{generate_password, PasswordPolicy};
+
+    #[test]
+    fn test_password_strength() {
+        assert_eq!(password_strength("Ab1!"), Strength::Weak);
+        assert_eq!(password_strength("abcdefghijklmnop"), Strength::Weak);
+        assert_eq!(password_strength("abcdefg1"), Strength::Fair);
+        assert_eq!(password_strength("Abcdefghijk1"), Strength::Strong);
+    }
+
+    #[test]
+    fn test_password_entropy_bits() {
+        let lower = password_entropy_bits("abcdefgh");
+        let full = password_entropy_bits("Abcdefgh1234!#$%");
+
+        assert_eq!(password_entropy_bits(""), 0.0);
+        assert_eq!(lower, 8.0 * 26f64.log2());
+        assert_eq!(lower.round(), 38.0);
+        assert_eq!(full, 16.0 * 79f64.log2());
+        assert_eq!(full.round(), 101.0);
+        assert_eq!(password_entropy_bits("abc d"), 5.0 * 59f64.log2());
+
+        let policy = PasswordPolicy::default();
+        assert_eq!(
+            password_entropy_bits(&generate_password(&policy)),
+            policy.entropy_bits()
+        );
+    }
+
+    #[test]
+    fn test_check_record_strength() {
+        assert_eq!(min_record_bits_from(None), None);
+        assert_eq!(min_record_bits_from(Some("0")), None);
+        assert_eq!(min_record_bits_from(Some("strong")), None);
+        assert_eq!(min_record_bits_from(Some(" 60 ")), Some(60));
+
+        // not enforced, anything is stored
+        assert!(check_record_strength("abc", None).is_ok());
+        assert!(check_record_strength("Abcdefghijk1!", Some(60)).is_ok());
+        assert!(
+            check_record_strength(&generate_password(&PasswordPolicy::default()), Some(100))
+                .is_ok()
+        );
+        assert_eq!(
+            check_record_strength("abcdefgh", Some(60)),
+            Err("The password is too weak, ~38 bits of the 60 needed".to_string())
+        );
+    }
+}
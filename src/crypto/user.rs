@@ -1,51 +1,184 @@
 use aes_gcm_siv::{
-    aead::{self, consts::U12, generic_array::GenericArray, Aead, KeyInit, OsRng},
+    aead::{self, consts::U12, generic_array::GenericArray, Aead, KeyInit, OsRng, Payload},
     AeadCore, Aes128GcmSiv, Key,
 };
+use argon2::Argon2;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use scrypt::{password_hash::SaltString, scrypt, Params};
-use std::{fs, mem::size_of, path::PathBuf, str};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    str,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, error, instrument, warn};
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::{
-    clear_file_content, create_file,
-    db::{append_to_file, write_to_file},
+    create_file,
+    crypto::{
+        bitwarden::{self, BitwardenLogins},
+        normalize_username,
+        strength::{password_strength, Strength},
+        user_path,
+    },
+    db::{
+        append_to_file, is_read_only, is_read_only_error, replace_file, write_private_file,
+        write_to_file,
+    },
     hash,
 };
 
-pub use super::models::RecordOperationConfig;
+pub use super::models::{
+    display_name, ImportReport, ImportSummary, RecordOperationConfig, RecordOperationConfigBuilder,
+    VerifyReport,
+};
+
+// first byte of a record that starts with a header, records written before
+// the header existed start with their (base64) salt and are always scrypt
+const RECORD_VERSION_NO_AAD: u8 = 1;
+// records of this version are encrypted with the hashed username as associated
+// data, so they only decrypt in the file of the user they belong to
+// (2 is taken by the file header)
+const RECORD_VERSION: u8 = 3;
+// version byte and kdf id
+const RECORD_HEADER_LEN: usize = 2;
+// first byte of a file that starts with a shared salt header
+const FILE_HEADER_SHARED_SALT: u8 = 2;
+// version byte, kdf id and salt
+const FILE_HEADER_SHARED_SALT_LEN: usize = 2 + 22;
+// first byte of a file that starts with a header holding a password verifier
+const FILE_HEADER_VERIFIER: u8 = 4;
+// version byte and flags, followed by the verifier record
+const FILE_HEADER_VERIFIER_LEN: usize = 2;
+// set in the flags if all records are encrypted with the salt of the verifier
+const FLAG_SHARED_SALT: u8 = 1;
+// known plaintext of the verifier
+const VERIFIER_PLAINTEXT: &str = "keeper-crabby";
+// log2(N) of scrypt records without a cost in their kdf id
+const DEFAULT_SCRYPT_COST: u8 = 14;
+// log2(N) that can be set with `KRAB_SCRYPT_COST`
+const SCRYPT_COSTS: std::ops::RangeInclusive<u8> = 10..=20;
+// set in the kdf id of scrypt records with another cost, the cost is in the low bits
+const KDF_ID_SCRYPT_COST: u8 = 0x80;
+
+#[cfg(test)]
+thread_local! {
+    // number of key derivations done by the current (test) thread
+    static DERIVATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    // number of users files read by the current (test) thread
+    static FILE_READS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Key derivation function used for a record
+///
+/// The scrypt cost is stored in the kdf id of the record, so records written with
+/// another cost still decrypt after it is changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kdf {
+    /// scrypt with the default cost (log2(N) = 14, r = 8, p = 1)
+    Scrypt,
+    /// scrypt with log2(N) set to the given cost, r = 8, p = 1
+    ScryptCost(u8),
+    Argon2id,
+}
+
+impl Kdf {
+    /// Kdf used for new records, set with `KRAB_KDF=argon2id|scrypt` (defaults to scrypt)
+    ///
+    /// The cost of scrypt is set with `KRAB_SCRYPT_COST` as log2(N) between 10 and 20,
+    /// the default (14) is used if it is not set or out of range.
+    pub fn from_env() -> Self {
+        match env::var("KRAB_KDF") {
+            Ok(kdf) if kdf.eq_ignore_ascii_case("argon2id") => Kdf::Argon2id,
+            _ => Kdf::scrypt_with_cost(env::var("KRAB_SCRYPT_COST").ok().as_deref()),
+        }
+    }
+
+    // scrypt with the given cost if it is valid, with the default cost otherwise
+    fn scrypt_with_cost(cost: Option<&str>) -> Self {
+        match cost.and_then(|cost| cost.trim().parse::<u8>().ok()) {
+            Some(DEFAULT_SCRYPT_COST) | None => Kdf::Scrypt,
+            Some(cost) if SCRYPT_COSTS.contains(&cost) => Kdf::ScryptCost(cost),
+            Some(cost) => {
+                warn!(cost, "scrypt cost out of range, using the default");
+                Kdf::Scrypt
+            }
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Kdf::Scrypt => 0,
+            Kdf::ScryptCost(cost) => KDF_ID_SCRYPT_COST | cost,
+            Kdf::Argon2id => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Kdf::Scrypt),
+            1 => Some(Kdf::Argon2id),
+            id if id & KDF_ID_SCRYPT_COST != 0
+                && SCRYPT_COSTS.contains(&(id & !KDF_ID_SCRYPT_COST)) =>
+            {
+                Some(Kdf::ScryptCost(id & !KDF_ID_SCRYPT_COST))
+            }
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 struct CipherConfig {
+    pub kdf: Kdf,
     pub key: Key<Aes128GcmSiv>,
     pub salt: Vec<u8>,                // 22 bytes
     pub nonce: GenericArray<u8, U12>, // 12 bytes
     pub ciphertext: Vec<u8>,
+    pub aad: Vec<u8>, // not stored
 }
 
 impl CipherConfig {
     fn new(
+        kdf: Kdf,
         key: Key<Aes128GcmSiv>,
         salt: Vec<u8>,
         nonce: GenericArray<u8, U12>,
         ciphertext: Vec<u8>,
+        aad: Vec<u8>,
     ) -> Self {
         CipherConfig {
+            kdf,
             key,
             salt,
             nonce,
             ciphertext,
+            aad,
         }
     }
 
+    #[cfg(test)]
     fn len(&self) -> usize {
-        self.salt.len() + self.nonce.len() + size_of::<u32>() + self.ciphertext.len()
+        RECORD_HEADER_LEN
+            + self.salt.len()
+            + self.nonce.len()
+            + size_of::<u32>()
+            + self.ciphertext.len()
     }
 
     fn write(&self, buffer: &mut Vec<u8>) {
         // this is needed to get the length of the ciphertext
         // so that we can read it back from the file
         let ciphertext_len: u32 = self.ciphertext.len() as u32;
-        let mut data: Vec<u8> = self.salt.clone();
+        let mut data: Vec<u8> = vec![RECORD_VERSION, self.kdf.id()];
 
+        data.append(&mut self.salt.clone());
         data.append(&mut self.nonce.to_vec());
         data.append(&mut ciphertext_len.to_be_bytes().to_vec());
         data.append(&mut self.ciphertext.clone());
@@ -53,54 +186,355 @@ impl CipherConfig {
         buffer.append(&mut data);
     }
 
-    fn encrypt_data(data: &str, master_pwd: &str) -> Result<Self, aead::Error> {
-        let derived_key = DerivedKey::derive_key(master_pwd, None);
-        let salt = derived_key.salt;
-        let key = Key::<Aes128GcmSiv>::clone_from_slice(&derived_key.key);
+    fn encrypt_data(data: &str, master_pwd: &str, aad: &[u8]) -> Result<Self, aead::Error> {
+        CipherConfig::encrypt_data_with_kdf(data, master_pwd, Kdf::from_env(), aad)
+    }
+
+    fn encrypt_data_with_kdf(
+        data: &str,
+        master_pwd: &str,
+        kdf: Kdf,
+        aad: &[u8],
+    ) -> Result<Self, aead::Error> {
+        let derived_key = DerivedKey::derive_key(master_pwd, None, kdf);
+        CipherConfig::encrypt_data_with_key(data, &derived_key, kdf, aad)
+    }
+
+    fn encrypt_data_with_key(
+        data: &str,
+        derived_key: &DerivedKey,
+        kdf: Kdf,
+        aad: &[u8],
+    ) -> Result<Self, aead::Error> {
+        let key = Key::<Aes128GcmSiv>::clone_from_slice(derived_key.key.as_slice());
+        let salt = derived_key.salt.clone();
         let cipher = Aes128GcmSiv::new(&key);
         let nonce = Aes128GcmSiv::generate_nonce(&mut OsRng);
-        let ciphertext = cipher.encrypt(&nonce, data.as_bytes())?;
-        Ok(CipherConfig::new(key, salt, nonce, ciphertext))
+        let payload = Payload {
+            msg: data.as_bytes(),
+            aad,
+        };
+        let ciphertext = cipher.encrypt(&nonce, payload)?;
+        Ok(CipherConfig::new(
+            kdf,
+            key,
+            salt,
+            nonce,
+            ciphertext,
+            aad.to_vec(),
+        ))
     }
 
     fn decrypt_data(&self) -> Result<String, aead::Error> {
         let cipher = Aes128GcmSiv::new(&self.key);
-        let plaintext = cipher.decrypt(&self.nonce, self.ciphertext.as_ref())?;
+        let payload = Payload {
+            msg: self.ciphertext.as_ref(),
+            aad: &self.aad,
+        };
+        let plaintext = cipher.decrypt(&self.nonce, payload)?;
         let result = String::from_utf8(plaintext).unwrap();
         Ok(result)
     }
 }
 
+impl Drop for CipherConfig {
+    fn drop(&mut self) {
+        self.key.as_mut_slice().zeroize();
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 struct DerivedKey {
-    pub key: [u8; 16],
+    pub key: Zeroizing<[u8; 16]>,
     pub salt: Vec<u8>,
 }
 
 impl DerivedKey {
-    fn new(key: [u8; 16], salt: Vec<u8>) -> Self {
+    fn new(key: Zeroizing<[u8; 16]>, salt: Vec<u8>) -> Self {
         DerivedKey { key, salt }
     }
 
-    fn derive_key(data: &str, salt: Option<Vec<u8>>) -> Self {
+    fn derive_key(data: &str, salt: Option<Vec<u8>>, kdf: Kdf) -> Self {
+        #[cfg(test)]
+        DERIVATIONS.with(|d| d.set(d.get() + 1));
+
         let salt = match salt {
             Some(salt) => salt,
-            None => SaltString::generate(&mut OsRng)
-                .as_str()
-                .as_bytes()
-                .to_vec(),
+            None => DerivedKey::generate_salt(),
         };
         let salt_copy = salt.clone();
-        let mut derived_key = [0u8; 16];
-        scrypt(
-            &data.as_bytes(),
-            &salt,
-            &Params::new(14 as u8, 8 as u32, 1 as u32, 16 as usize).unwrap(),
-            &mut derived_key,
-        )
-        .unwrap();
+        let mut derived_key = Zeroizing::new([0u8; 16]);
+        match kdf {
+            Kdf::Scrypt | Kdf::ScryptCost(_) => {
+                let cost = match kdf {
+                    Kdf::ScryptCost(cost) => cost,
+                    _ => DEFAULT_SCRYPT_COST,
+                };
+                scrypt(
+                    data.as_bytes(),
+                    &salt,
+                    &Params::new(cost, 8, 1, 16).unwrap(),
+                    derived_key.as_mut_slice(),
+                )
+                .unwrap()
+            }
+            Kdf::Argon2id => Argon2::default()
+                .hash_password_into(data.as_bytes(), &salt, derived_key.as_mut_slice())
+                .unwrap(),
+        }
         DerivedKey::new(derived_key, salt_copy)
     }
+
+    fn generate_salt() -> Vec<u8> {
+        SaltString::generate(&mut OsRng)
+            .as_str()
+            .as_bytes()
+            .to_vec()
+    }
+}
+
+/// Keys derived during a single operation, keyed by kdf and salt
+///
+/// Only lives as long as the operation (a read pass, or an integrity check and
+/// the write that follows it), so no key material outlives the call.
+#[derive(Debug, Default)]
+struct KeyCache(HashMap<(Kdf, Vec<u8>), DerivedKey>);
+
+impl KeyCache {
+    fn derive_key(&mut self, data: &str, salt: Vec<u8>, kdf: Kdf) -> &DerivedKey {
+        self.0.entry((kdf, salt)).or_insert_with_key(|(kdf, salt)| {
+            DerivedKey::derive_key(data, Some(salt.clone()), *kdf)
+        })
+    }
+
+    // derives the keys of all salts that are not cached yet, in parallel with the
+    // `rayon` feature, so reading a file with a salt per record is not sequential
+    fn prefetch(&mut self, data: &str, salts: Vec<(Kdf, Vec<u8>)>) {
+        let missing: HashSet<(Kdf, Vec<u8>)> = salts
+            .into_iter()
+            .filter(|salt| !self.0.contains_key(salt))
+            .collect();
+
+        #[cfg(feature = "rayon")]
+        let missing = missing.into_par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let missing = missing.into_iter();
+        let derived: Vec<_> = missing
+            .map(|(kdf, salt)| {
+                let key = DerivedKey::derive_key(data, Some(salt.clone()), kdf);
+                ((kdf, salt), key)
+            })
+            .collect();
+
+        // the keys were derived on other threads
+        #[cfg(all(test, feature = "rayon"))]
+        DERIVATIONS.with(|d| d.set(d.get() + derived.len()));
+
+        self.0.extend(derived);
+    }
+}
+
+// a record in the users file format before its key is derived
+struct RawRecord {
+    kdf: Kdf,
+    salt: Vec<u8>,
+    nonce: GenericArray<u8, U12>,
+    ciphertext: Vec<u8>,
+    aad: Vec<u8>,
+    len: usize, // bytes the record takes up in the file
+}
+
+impl RawRecord {
+    // a truncated or corrupt record is an error
+    fn parse(bytes: &[u8], aad: &[u8]) -> Result<Self, aead::Error> {
+        RawRecord::read(&mut &bytes[..], aad)?.ok_or(aead::Error)
+    }
+
+    // reads the next record and exactly its bytes, `None` at the end of the input
+    fn read<R: BufRead>(reader: &mut R, aad: &[u8]) -> Result<Option<Self>, aead::Error> {
+        let first = match reader.fill_buf() {
+            Ok([]) => return Ok(None),
+            Ok(buffer) => buffer[0],
+            Err(_) => return Err(aead::Error),
+        };
+        let (kdf, header_len, aad) = match first {
+            RECORD_VERSION | RECORD_VERSION_NO_AAD => {
+                let mut header = [0u8; RECORD_HEADER_LEN];
+                reader.read_exact(&mut header).map_err(|_| aead::Error)?;
+                let aad = match first {
+                    RECORD_VERSION => aad.to_vec(),
+                    _ => vec![],
+                };
+                match Kdf::from_id(header[1]) {
+                    Some(kdf) => (kdf, RECORD_HEADER_LEN, aad),
+                    None => return Err(aead::Error),
+                }
+            }
+            _ => (Kdf::Scrypt, 0, vec![]),
+        };
+        // salt, nonce and ciphertext length
+        let mut fields = [0u8; 38];
+        reader.read_exact(&mut fields).map_err(|_| aead::Error)?;
+        let ciphertext_len = u32::from_be_bytes(fields[34..38].try_into().unwrap()) as usize;
+        // a corrupt length can not allocate more than there is to read
+        let mut ciphertext = vec![];
+        reader
+            .by_ref()
+            .take(ciphertext_len as u64)
+            .read_to_end(&mut ciphertext)
+            .map_err(|_| aead::Error)?;
+        if ciphertext.len() != ciphertext_len {
+            return Err(aead::Error);
+        }
+
+        Ok(Some(RawRecord {
+            kdf,
+            salt: fields[0..22].to_vec(),
+            nonce: GenericArray::clone_from_slice(&fields[22..34]),
+            ciphertext,
+            aad,
+            len: header_len + fields.len() + ciphertext_len,
+        }))
+    }
+
+    fn into_record(self, master_pwd: &str, offset: u32, keys: &mut KeyCache) -> Record {
+        let derived_key = keys.derive_key(master_pwd, self.salt.clone(), self.kdf);
+        let key = Key::<Aes128GcmSiv>::clone_from_slice(derived_key.key.as_slice());
+        let cipher_config = CipherConfig::new(
+            self.kdf,
+            key,
+            self.salt,
+            self.nonce,
+            self.ciphertext,
+            self.aad,
+        );
+        Record::new(cipher_config, offset, None, None, vec![])
+    }
+}
+
+/// Optional header at the start of a users file
+///
+/// New files start with a verifier, a known constant encrypted with the master
+/// key, so a wrong master password is detected before any record is read. When
+/// the salt is shared all records are encrypted with the salt of the verifier,
+/// so the key has to be derived only once when reading the file. Shared salt
+/// headers written before the verifier existed have none.
+#[derive(Debug, Clone, PartialEq)]
+struct FileHeader {
+    pub kdf: Kdf,
+    pub salt: Vec<u8>,
+    pub shared_salt: bool,
+    pub verifier: Option<Vec<u8>>, // a record in the users file format
+}
+
+impl FileHeader {
+    fn new(
+        kdf: Kdf,
+        shared_salt: bool,
+        master_pwd: &str,
+        aad: &[u8],
+        keys: &mut KeyCache,
+    ) -> Result<Self, aead::Error> {
+        let salt = DerivedKey::generate_salt();
+        let derived_key = keys.derive_key(master_pwd, salt.clone(), kdf);
+        let cipher =
+            CipherConfig::encrypt_data_with_key(VERIFIER_PLAINTEXT, derived_key, kdf, aad)?;
+        let mut verifier = vec![];
+        cipher.write(&mut verifier);
+
+        Ok(FileHeader {
+            kdf,
+            salt,
+            shared_salt,
+            verifier: Some(verifier),
+        })
+    }
+
+    /// Whether all records of new users share a salt, set with `KRAB_SHARED_SALT=1|true`
+    fn shared_salt_from_env() -> bool {
+        match env::var("KRAB_SHARED_SALT") {
+            Ok(v) => v == "1" || v.eq_ignore_ascii_case("true"),
+            Err(_) => false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match &self.verifier {
+            Some(verifier) => FILE_HEADER_VERIFIER_LEN + verifier.len(),
+            None => FILE_HEADER_SHARED_SALT_LEN,
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        match &self.verifier {
+            Some(verifier) => {
+                let flags = if self.shared_salt {
+                    FLAG_SHARED_SALT
+                } else {
+                    0
+                };
+                buffer.push(FILE_HEADER_VERIFIER);
+                buffer.push(flags);
+                buffer.extend_from_slice(verifier);
+            }
+            None => {
+                buffer.push(FILE_HEADER_SHARED_SALT);
+                buffer.push(self.kdf.id());
+                buffer.extend_from_slice(&self.salt);
+            }
+        }
+    }
+
+    fn read_from_bytes(
+        bytes: &[u8],
+        master_pwd: &str,
+        aad: &[u8],
+        keys: &mut KeyCache,
+    ) -> Result<Option<Self>, String> {
+        match bytes.first() {
+            Some(&FILE_HEADER_SHARED_SALT) => {
+                if bytes.len() < FILE_HEADER_SHARED_SALT_LEN {
+                    return Err("Could not read user".to_string());
+                }
+                match Kdf::from_id(bytes[1]) {
+                    Some(kdf) => Ok(Some(FileHeader {
+                        kdf,
+                        salt: bytes[2..FILE_HEADER_SHARED_SALT_LEN].to_vec(),
+                        shared_salt: true,
+                        verifier: None,
+                    })),
+                    None => Err("Could not read user".to_string()),
+                }
+            }
+            Some(&FILE_HEADER_VERIFIER) => {
+                if bytes.len() < FILE_HEADER_VERIFIER_LEN {
+                    return Err("Could not read user".to_string());
+                }
+                let body = bytes[FILE_HEADER_VERIFIER_LEN..].to_vec();
+                let (verifier, remaining, _) =
+                    match Record::read_from_bytes(body, master_pwd, 0, keys, aad) {
+                        Ok(res) => res,
+                        Err(_) => return Err("Could not read user".to_string()),
+                    };
+                match verifier.cypher.decrypt_data() {
+                    Ok(plaintext) if plaintext == VERIFIER_PLAINTEXT => {}
+                    _ => return Err("Wrong master password".to_string()),
+                }
+                let verifier_len = bytes.len() - FILE_HEADER_VERIFIER_LEN - remaining.len();
+                let verifier_bytes = bytes
+                    [FILE_HEADER_VERIFIER_LEN..FILE_HEADER_VERIFIER_LEN + verifier_len]
+                    .to_vec();
+
+                Ok(Some(FileHeader {
+                    kdf: verifier.cypher.kdf,
+                    salt: verifier.cypher.salt.clone(),
+                    shared_salt: bytes[1] & FLAG_SHARED_SALT != 0,
+                    verifier: Some(verifier_bytes),
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -108,19 +542,50 @@ pub struct Record {
     cypher: CipherConfig,
     offset: u32,
     domain: Option<String>,
+    // tells the record apart from others of the same domain, empty for none
+    label: String,
     pwd: Option<String>,
+    tags: Vec<String>,
+    // seconds since the unix epoch the password was last set, records written
+    // before it was stored have none
+    modified: Option<u64>,
 }
 
 impl Record {
-    fn new(cypher: CipherConfig, offset: u32, domain: Option<String>, pwd: Option<String>) -> Self {
+    fn new(
+        cypher: CipherConfig,
+        offset: u32,
+        domain: Option<String>,
+        pwd: Option<String>,
+        tags: Vec<String>,
+    ) -> Self {
         Record {
             cypher,
             offset,
             domain,
+            label: String::new(),
             pwd,
+            tags,
+            modified: None,
         }
     }
 
+    fn with_label(mut self, label: String) -> Self {
+        self.label = label;
+        self
+    }
+
+    fn with_modified(mut self, modified: Option<u64>) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    /// When the password was last set, `None` for records written before it was stored
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
     fn set_domain(&mut self, domain: String) {
         self.domain = Some(domain);
     }
@@ -129,6 +594,26 @@ impl Record {
         self.pwd = Some(pwd);
     }
 
+    fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    /// Tags of the record, empty if it has none or is not decrypted
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Label that tells the record apart from others of its domain, empty for none
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    // whether this is the record of `domain` with `label`
+    fn is(&self, domain: &str, label: &str) -> bool {
+        self.domain.as_deref() == Some(domain) && self.label == label
+    }
+
+    /// Domain and password of the record, the caller is responsible for wiping them
     pub fn secret(&self) -> (String, String) {
         assert!(self.domain.is_some() && self.pwd.is_some());
         (self.domain.clone().unwrap(), self.pwd.clone().unwrap())
@@ -138,19 +623,15 @@ impl Record {
         bytes: Vec<u8>,
         master_pwd: &str,
         offset: u32,
+        keys: &mut KeyCache,
+        aad: &[u8],
     ) -> Result<(Self, Vec<u8>, u32), aead::Error> {
-        let salt = bytes[0..22].to_vec();
-        let nonce = GenericArray::clone_from_slice(&bytes[22..34]);
-        let ciphertext_len = u32::from_be_bytes(bytes[34..38].try_into().unwrap());
-        let ciphertext = bytes[38..(38 + ciphertext_len as usize)].to_vec();
-        let derived_key = DerivedKey::derive_key(master_pwd, Some(salt.clone()));
-        let key = Key::<Aes128GcmSiv>::clone_from_slice(&derived_key.key);
-        let cipher_config = CipherConfig::new(key, salt, nonce, ciphertext);
-        let current_offset = 38 + ciphertext_len as usize + offset as usize;
+        let raw = RawRecord::parse(&bytes, aad)?;
+        let len = raw.len;
         Ok((
-            Record::new(cipher_config, offset, None, None),
-            bytes[(38 + ciphertext_len as usize)..].to_vec(),
-            current_offset as u32,
+            raw.into_record(master_pwd, offset, keys),
+            bytes[len..].to_vec(),
+            offset + len as u32,
         ))
     }
 
@@ -161,758 +642,3764 @@ impl Record {
     /// * `p` - Path to the directory where the file (users data) is stored
     /// * `username` - The username of the user
     /// * `master_pwd` - The master password of the user
+    /// * `keys` - Keys already derived during the current operation
+    ///
+    /// # Returns
+    /// * `Result<(Option<FileHeader>, Vec<Self>), String>` - The file header and a vector of
+    ///   records or an error message
+    fn read_user(
+        p: &Path,
+        username: &str,
+        master_pwd: &str,
+        keys: &mut KeyCache,
+    ) -> Result<(Option<FileHeader>, Vec<Self>), String> {
+        let file_path = user_path(username, p);
+        Record::read_file(&file_path, master_pwd, keys)
+    }
+
+    /// Read user data from the users file
+    ///
+    /// The name of the file (the hashed username) is the associated data the
+    /// records are encrypted with.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - Path to the file where the users data is stored
+    /// * `master_pwd` - The master password of the user
+    /// * `keys` - Keys already derived during the current operation
     ///
     /// # Returns
-    /// * `Result<Vec<Self>, String>` - A vector of records or an error message
-    fn read_user(p: &PathBuf, username: &str, master_pwd: &str) -> Result<Vec<Self>, String> {
-        let hash = hash(username.to_string());
-        let file_path = p.join(hash.as_str());
+    /// * `Result<(Option<FileHeader>, Vec<Self>), String>` - The file header and a vector of
+    ///   records or an error message
+    #[instrument(skip_all)]
+    fn read_file(
+        file_path: &Path,
+        master_pwd: &str,
+        keys: &mut KeyCache,
+    ) -> Result<(Option<FileHeader>, Vec<Self>), String> {
         let mut data: Vec<Record> = Vec::new();
-        let mut offset = 0;
+        let header;
         if file_path.exists() {
-            let mut bytes = fs::read(file_path).unwrap();
-            let mut run = true;
-            while run {
-                let res = Record::read_from_bytes(bytes, master_pwd, offset);
-                if res.is_err() {
-                    return Err("Could not read user".to_string());
-                }
-                let (cipher, remaining, next_offset) = res.unwrap();
-                data.push(cipher);
-                bytes = remaining;
-                if bytes.len() == 0 {
-                    run = false;
-                }
-
-                offset = next_offset;
+            let (mut reader, h, aad) = Record::open_file(file_path, master_pwd, keys)?;
+            header = h;
+            let mut offset = header.as_ref().map_or(0, |header| header.len() as u32);
+            // the records are parsed first so all keys can be derived at once, one
+            // record at a time so the file is never held twice
+            let mut raw_records = vec![];
+            let mut start = 0;
+            loop {
+                let raw = match RawRecord::read(&mut reader, &aad) {
+                    Ok(Some(raw)) => raw,
+                    Ok(None) => break,
+                    Err(_) => {
+                        warn!(offset = offset as usize + start, "could not parse record");
+                        return Err("Could not read user".to_string());
+                    }
+                };
+                start += raw.len;
+                raw_records.push(raw);
+            }
+            keys.prefetch(
+                master_pwd,
+                raw_records
+                    .iter()
+                    .map(|r| (r.kdf, r.salt.clone()))
+                    .collect(),
+            );
+            for raw in raw_records {
+                let len = raw.len as u32;
+                data.push(raw.into_record(master_pwd, offset, keys));
+                offset += len;
             }
         } else {
+            debug!("users file not found");
             return Err("User not found".to_string());
         }
-        Ok(data)
+        debug!(records = data.len(), "read users file");
+        Ok((header, data))
     }
-}
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct User(Vec<Record>, PathBuf);
+    // opens a users file and reads its header, the reader is left at the first
+    // record and returned with the header and the associated data of the records
+    fn open_file(
+        file_path: &Path,
+        master_pwd: &str,
+        keys: &mut KeyCache,
+    ) -> Result<OpenedFile, String> {
+        #[cfg(test)]
+        FILE_READS.with(|r| r.set(r.get() + 1));
+
+        let mut reader = match File::open(file_path) {
+            Ok(file) => BufReader::new(file),
+            Err(_) => return Err("Could not read user".to_string()),
+        };
+        let aad = file_aad(file_path);
+        // the header is short enough to be in the first buffer, it fails fast on
+        // a wrong master password if the file has a verifier
+        let header = match reader.fill_buf() {
+            Ok(buffer) => FileHeader::read_from_bytes(buffer, master_pwd, &aad, keys)?,
+            Err(_) => return Err("Could not read user".to_string()),
+        };
+        if let Some(header) = &header {
+            reader.consume(header.len());
+        }
 
-impl User {
-    pub fn from(path: &PathBuf, username: &str, master_pwd: &str) -> Result<Self, String> {
-        let records = Record::read_user(path, username, master_pwd);
-        let mut new_records = vec![];
+        Ok((reader, header, aad))
+    }
 
-        match records {
-            Ok(r) => {
-                for record in r.iter() {
-                    let decrypted = record.cypher.decrypt_data();
-                    match decrypted {
-                        Ok(decrypted) => {
-                            let parts: Vec<&str> = decrypted.split_whitespace().collect();
-                            let mut new_record = record.clone();
-                            new_record.set_domain(parts[0].to_string());
-                            new_record.set_pwd(parts[1].to_string());
-                            new_records.push(new_record);
-                        }
-                        Err(_) => return Err("Could not decrypt data".to_string()),
-                    }
-                }
-            }
-            Err(e) => return Err(e),
+    // checks the master password against the verifier in the header of a users
+    // file, or against its first record if it has no verifier, only one key is
+    // derived and no other record is read
+    fn verify_master_pwd(
+        file_path: &Path,
+        master_pwd: &str,
+        keys: &mut KeyCache,
+    ) -> Result<(), String> {
+        // a wrong master password already fails on the verifier here
+        let (mut reader, header, aad) = Record::open_file(file_path, master_pwd, keys)?;
+        if header
+            .as_ref()
+            .is_some_and(|header| header.verifier.is_some())
+        {
+            return Ok(());
         }
+        let offset = header.map_or(0, |header| header.len() as u32);
+        match RawRecord::read(&mut reader, &aad) {
+            Ok(Some(raw)) => match raw
+                .into_record(master_pwd, offset, keys)
+                .cypher
+                .decrypt_data()
+            {
+                Ok(_) => Ok(()),
+                Err(_) => Err("could not decrypt the first record".to_string()),
+            },
+            Ok(None) => Err("no verifier and no records to check against".to_string()),
+            Err(_) => Err("could not read the first record".to_string()),
+        }
+    }
+}
 
-        let path = path.join(hash(username.to_string()));
-
-        Ok(User(new_records, path))
+impl Drop for Record {
+    fn drop(&mut self) {
+        self.domain.zeroize();
+        self.label.zeroize();
+        self.pwd.zeroize();
+        self.tags.zeroize();
     }
+}
 
-    pub fn new(user: &RecordOperationConfig) -> Result<(), String> {
-        let hashed_username = hash(user.username.to_string());
-        let res = create_file(&user.path, hashed_username.as_str());
-        let file_path = match res {
-            Ok(path) => path,
-            Err(_) => return Err("Could not create file.".to_string()),
-        };
-        let data = format!("{} {}", user.domain, user.pwd);
+type OpenedFile = (BufReader<File>, Option<FileHeader>, Vec<u8>);
 
-        let cipher = CipherConfig::encrypt_data(&data, &user.master_pwd);
-        let cipher = match cipher {
-            Ok(cipher) => cipher,
-            Err(_) => return Err("Could not encrypt data.".to_string()),
-        };
-        let mut buffer = vec![];
-        cipher.write(&mut buffer);
-        match write_to_file(&file_path, buffer) {
-            Ok(_) => Ok(()),
-            Err(_) => Err("Could not write to file.".to_string()),
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ExportedRecord {
+    domain: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    label: String,
+    password: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+// path of the `pass` entry of a domain, the parts of the domain between slashes are
+// directories and may not leave `out`
+fn pass_entry_path(out: &Path, domain: &str) -> Result<PathBuf, String> {
+    let mut path = out.to_path_buf();
+    let parts: Vec<&str> = domain.split('/').collect();
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() || *part == "." || *part == ".." || part.contains('\\') {
+            return Err(format!("Can not export the domain {} as a file", domain));
+        }
+        if i + 1 == parts.len() {
+            path.push(format!("{}.txt", part));
+        } else {
+            path.push(part);
         }
     }
 
+    Ok(path)
+}
+
+/// A read only list of records, sorted or filtered for presentation
+///
+/// The records are cloned out of the user, so the list does not change when the user does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOnlyRecords(Vec<Record>);
+
+impl ReadOnlyRecords {
     pub fn records(&self) -> Vec<Record> {
         self.0.clone()
     }
 
-    pub fn add_record(&mut self, record: RecordOperationConfig) -> Result<(), String> {
-        let integrity = self.check_integrity(&record.username, &record.master_pwd, &record.path);
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
 
-        if !integrity {
-            return Err("Integrity check failed".to_string());
-        }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 
-        let data = format!("{} {}", record.domain, record.pwd);
-        let cipher = CipherConfig::encrypt_data(&data, &record.master_pwd);
-        let cipher = match cipher {
-            Ok(cipher) => cipher,
-            Err(_) => return Err("Could not create user.".to_string()),
-        };
-        let offset = self.last_offset();
-        let record = Record::new(
-            cipher,
-            offset,
-            Some(record.domain.to_string()),
-            Some(record.pwd.to_string()),
-        );
-        let mut buffer = vec![];
-        record.cypher.write(&mut buffer);
-        append_to_file(&self.path(), buffer).unwrap();
-        self.0.push(record);
+    /// The records sorted by domain, records with the same domain keep their order
+    ///
+    /// Records that are not decrypted are sorted last.
+    pub fn sorted_by_domain(&self) -> ReadOnlyRecords {
+        let mut records = self.0.clone();
+        records.sort_by(|a, b| match (&a.domain, &b.domain) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+
+        ReadOnlyRecords(records)
+    }
 
-        Ok(())
+    /// The records `predicate` returns true for, in the same order
+    pub fn filter<P>(&self, predicate: P) -> ReadOnlyRecords
+    where
+        P: Fn(&Record) -> bool,
+    {
+        ReadOnlyRecords(self.0.iter().filter(|r| predicate(r)).cloned().collect())
     }
+}
 
-    pub fn remove_record(&mut self, record: RecordOperationConfig) -> Result<(), String> {
-        let integrity = self.check_integrity(&record.username, &record.master_pwd, &record.path);
+impl Drop for ExportedRecord {
+    fn drop(&mut self) {
+        self.domain.zeroize();
+        self.password.zeroize();
+        self.tags.zeroize();
+    }
+}
 
-        if !integrity {
-            return Err("Integrity check failed".to_string());
-        }
+// plaintext of a record as it is written, a JSON object so the password may
+// contain anything
+#[derive(Serialize)]
+struct PlaintextRef<'a> {
+    domain: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    label: &'a str,
+    pwd: &'a str,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<u64>,
+}
 
-        if self
-            .domains()
-            .iter()
-            .find(|d| d.as_str() == record.domain)
-            .is_none()
-        {
-            return Err("Record not found".to_string());
-        }
+// plaintext of a record as it is read, wiped when dropped
+#[derive(Deserialize)]
+struct Plaintext {
+    domain: String,
+    #[serde(default)]
+    label: String,
+    pwd: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    modified: Option<u64>,
+}
 
-        let mut new_records = vec![];
-        for r in self.0.iter() {
-            if r.domain != Some(record.domain.to_string()) {
-                new_records.push(r.clone());
-            }
-        }
+impl Drop for Plaintext {
+    fn drop(&mut self) {
+        self.domain.zeroize();
+        self.label.zeroize();
+        self.pwd.zeroize();
+        self.tags.zeroize();
+    }
+}
 
-        // TODO: calibrate offsets or remove them
+// plaintext of a record, `{"domain":...,"label":...,"pwd":...,"tags":[...],"modified":...}`
+// without the label, the tags or the time if the record has none
+fn marshal(
+    domain: &str,
+    label: &str,
+    pwd: &str,
+    tags: &[String],
+    modified: Option<u64>,
+) -> Zeroizing<String> {
+    let plaintext = PlaintextRef {
+        domain,
+        label,
+        pwd,
+        tags,
+        modified,
+    };
+    // serializing strings into a string can not fail
+    Zeroizing::new(serde_json::to_string(&plaintext).unwrap_or_default())
+}
 
-        self.remove_records_from_file();
-        let path = self.path();
-        let mut buffer = vec![];
+// splits the plaintext of a record into domain, password and tags, see `unmarshal_record`
+fn unmarshal(plaintext: &str) -> Result<(String, String, Vec<String>), String> {
+    unmarshal_record(plaintext).map(|(fields, _, _)| fields)
+}
 
-        for record in new_records.iter() {
-            record.cypher.write(&mut buffer);
+// splits the plaintext of a record into domain, password and tags, its label and
+// the time the password was set, records written before the JSON format are read
+// as `"domain password tags"` without a label or a time, a record without a
+// domain is an error
+fn unmarshal_record(plaintext: &str) -> Result<TimedRecordFields, String> {
+    if plaintext.starts_with('{') {
+        if let Ok(mut record) = serde_json::from_str::<Plaintext>(plaintext) {
+            if record.domain.is_empty() {
+                return Err("Could not read record".to_string());
+            }
+            let fields = (
+                std::mem::take(&mut record.domain),
+                std::mem::take(&mut record.pwd),
+                std::mem::take(&mut record.tags),
+            );
+            return Ok((fields, std::mem::take(&mut record.label), record.modified));
         }
+    }
+    unmarshal_legacy(plaintext).map(|fields| (fields, String::new(), None))
+}
 
-        write_to_file(&path, buffer).unwrap();
-        self.0 = new_records;
+// seconds since the unix epoch, stored as the time a password was set
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
 
-        Ok(())
+// `"domain password"` followed by the comma separated tags, a missing password
+// (stored as `"domain "` or as only the domain) is read as an empty one, a
+// password with spaces can not be told apart from the tags and is an error
+fn unmarshal_legacy(plaintext: &str) -> Result<(String, String, Vec<String>), String> {
+    let mut parts = plaintext.split(' ');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(domain), pwd, tags, None) if !domain.is_empty() => Ok((
+            domain.to_string(),
+            pwd.unwrap_or_default().to_string(),
+            tags.unwrap_or_default()
+                .split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+        )),
+        _ => Err("Could not read record".to_string()),
     }
+}
 
-    pub fn modify_record(&mut self, record: RecordOperationConfig) -> Result<(), String> {
-        let integrity = self.check_integrity(&record.username, &record.master_pwd, &record.path);
+// domain, password and tags of a record
+type RecordFields = (String, String, Vec<String>);
+
+// fields of a record, its label and the time its password was set
+type TimedRecordFields = (RecordFields, String, Option<u64>);
+
+// decrypts the records into domain, password, tags, label and the time the password
+// was set in file order, in parallel with the `rayon` feature, a record that decrypts
+// but can not be read is returned as an error of its own
+fn decrypt_records(records: &[Record]) -> Result<Vec<Result<TimedRecordFields, String>>, String> {
+    #[cfg(feature = "rayon")]
+    let records = records.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let records = records.iter();
+    records
+        .map(|record| match record.cypher.decrypt_data() {
+            Ok(decrypted) => Ok(unmarshal_record(&Zeroizing::new(decrypted))),
+            Err(_) => {
+                error!(offset = record.offset, "could not decrypt record");
+                Err("Could not decrypt data".to_string())
+            }
+        })
+        .collect()
+}
 
-        if !integrity {
-            return Err("Integrity check failed".to_string());
-        }
+// associated data of the records in a users file, the file name is the hashed username
+fn file_aad(file_path: &Path) -> Vec<u8> {
+    match file_path.file_name() {
+        Some(name) => name.as_encoded_bytes().to_vec(),
+        None => vec![],
+    }
+}
 
-        let mut new_records = vec![];
-        let mut found = false;
-        for r in self.0.iter() {
-            if r.domain != Some(record.domain.to_string()) {
-                new_records.push(r.clone());
-            } else {
-                found = true;
-            }
+/// Count the records of a user without the master password
+///
+/// Only the headers and lengths of the records are read, no key is derived and
+/// nothing is decrypted, so it is cheap enough for the login screen. The verifier
+/// of the file header is not counted. A file that can not be parsed to its end is
+/// reported as invalid data.
+///
+/// # Arguments
+///
+/// * `path` - Path to the directory where the users data is stored
+/// * `username` - The username of the user
+///
+/// # Returns
+/// * `io::Result<usize>` - The number of records
+pub fn count_records(path: &Path, username: &str) -> io::Result<usize> {
+    let file_path = user_path(username, path);
+    let mut reader = BufReader::new(File::open(&file_path)?);
+    let aad = file_aad(&file_path);
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Could not read user");
+    match reader.fill_buf()?.first() {
+        Some(&FILE_HEADER_SHARED_SALT) => {
+            let mut header = [0u8; FILE_HEADER_SHARED_SALT_LEN];
+            reader.read_exact(&mut header)?;
         }
-
-        if !found {
-            return Err("Record not found".to_string());
+        Some(&FILE_HEADER_VERIFIER) => {
+            let mut header = [0u8; FILE_HEADER_VERIFIER_LEN];
+            reader.read_exact(&mut header)?;
+            RawRecord::read(&mut reader, &aad)
+                .map_err(|_| invalid())?
+                .ok_or_else(invalid)?;
         }
+        _ => {}
+    }
 
-        let data = format!("{} {}", record.domain, record.pwd);
-        let cipher = CipherConfig::encrypt_data(&data, &record.master_pwd);
-        let cipher = match cipher {
-            Ok(cipher) => cipher,
-            Err(_) => return Err("Could not create user.".to_string()),
-        };
+    let mut count = 0;
+    while RawRecord::read(&mut reader, &aad)
+        .map_err(|_| invalid())?
+        .is_some()
+    {
+        count += 1;
+    }
 
-        let record = Record::new(
-            cipher,
-            self.last_offset(),
+    Ok(count)
+}
+
+// message for a failed write, a read-only vault is named as such
+fn write_error(e: io::Error, message: &str) -> String {
+    if is_read_only_error(&e) {
+        warn!("the vault is read-only");
+        return "The vault is read-only".to_string();
+    }
+    message.to_string()
+}
+
+// quotes a CSV field if it contains a separator, a quote or a line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// When changes to the records are written to the users file
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WriteMode {
+    /// Every change is written right away
+    #[default]
+    Immediate,
+    /// Changes are kept in memory until `User::flush`, `dirty` is set while there are any
+    Deferred { dirty: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    records: Vec<Record>,
+    // users file of the user
+    path: PathBuf,
+    header: Option<FileHeader>,
+    // normalized username
+    username: String,
+    write_mode: WriteMode,
+    // records that decrypt but can not be read, they are written back unchanged
+    // so saving the vault does not lose them
+    unreadable: Vec<Record>,
+}
+
+impl User {
+    #[instrument(skip_all)]
+    pub fn from(path: &Path, username: &str, master_pwd: &str) -> Result<Self, String> {
+        let records = Record::read_user(path, username, master_pwd, &mut KeyCache::default());
+        let mut new_records = vec![];
+        let mut unreadable = vec![];
+        let header;
+
+        match records {
+            Ok((h, r)) => {
+                header = h;
+                for (record, fields) in r.iter().zip(decrypt_records(&r)?) {
+                    let ((domain, pwd, tags), label, modified) = match fields {
+                        Ok(fields) => fields,
+                        Err(_) => {
+                            warn!(offset = record.offset, "could not read record");
+                            unreadable.push(record.clone());
+                            continue;
+                        }
+                    };
+                    let mut new_record = record.clone();
+                    new_record.set_domain(domain);
+                    new_record.set_pwd(pwd);
+                    new_record.set_tags(tags);
+                    new_record.label = label;
+                    new_record.modified = modified;
+                    // a domain and label stored more than once (a bug or a manual
+                    // edit) keep only their last record
+                    new_records.retain(|r: &Record| {
+                        r.domain != new_record.domain || r.label != new_record.label
+                    });
+                    new_records.push(new_record);
+                }
+            }
+            Err(e) => {
+                warn!("could not read user: {}", e);
+                return Err(e);
+            }
+        }
+
+        let path = user_path(username, path);
+
+        Ok(User {
+            records: new_records,
+            path,
+            header,
+            username: normalize_username(username),
+            write_mode: WriteMode::default(),
+            unreadable,
+        })
+    }
+
+    pub fn new(user: &RecordOperationConfig) -> Result<Self, String> {
+        User::new_with_shared_salt(user, FileHeader::shared_salt_from_env())
+    }
+
+    fn new_with_shared_salt(
+        user: &RecordOperationConfig,
+        shared_salt: bool,
+    ) -> Result<Self, String> {
+        let hashed_username = hash(normalize_username(&user.username));
+        let res = create_file(&user.path, hashed_username.as_str());
+        let file_path = match res {
+            Ok(path) => path,
+            Err(e) => return Err(write_error(e, "Could not create file.")),
+        };
+        let modified = Some(now_secs());
+        let data = marshal(&user.domain, &user.label, &user.pwd, &user.tags, modified);
+
+        let mut keys = KeyCache::default();
+        let header = FileHeader::new(
+            Kdf::from_env(),
+            shared_salt,
+            &user.master_pwd,
+            &file_aad(&file_path),
+            &mut keys,
+        );
+        let header = match header {
+            Ok(header) => header,
+            Err(_) => return Err("Could not encrypt data.".to_string()),
+        };
+        let offset = header.len() as u32;
+        let mut new_user = User {
+            records: vec![],
+            path: file_path.clone(),
+            header: Some(header),
+            username: normalize_username(&user.username),
+            write_mode: WriteMode::default(),
+            unreadable: vec![],
+        };
+        let cipher = new_user.encrypt(&data, &user.master_pwd, &mut keys);
+        let cipher = match cipher {
+            Ok(cipher) => cipher,
+            Err(_) => return Err("Could not encrypt data.".to_string()),
+        };
+        let record = Record::new(
+            cipher,
+            offset,
+            Some(user.domain.to_string()),
+            Some(user.pwd.to_string()),
+            user.tags.clone(),
+        )
+        .with_label(user.label.clone())
+        .with_modified(modified);
+        let buffer = new_user.serialize(std::slice::from_ref(&record));
+        match write_to_file(&file_path, buffer) {
+            Ok(_) => {
+                new_user.records.push(record);
+                Ok(new_user)
+            }
+            Err(e) => Err(write_error(e, "Could not write to file.")),
+        }
+    }
+
+    /// The (normalized) username of the user
+    pub fn username(&self) -> String {
+        self.username.clone()
+    }
+
+    /// Whether changes can not be written because the vault is read-only
+    pub fn is_read_only(&self) -> bool {
+        is_read_only(&self.path())
+    }
+
+    /// Keep changes in memory until `flush` instead of writing each one right away
+    pub fn defer_writes(&mut self) {
+        if self.write_mode == WriteMode::Immediate {
+            self.write_mode = WriteMode::Deferred { dirty: false };
+        }
+    }
+
+    /// Whether there are changes that are not written to the users file yet
+    pub fn is_dirty(&self) -> bool {
+        self.write_mode == WriteMode::Deferred { dirty: true }
+    }
+
+    /// Write all records to the users file at once
+    ///
+    /// The file is replaced atomically, so it holds either all or none of the changes.
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Nothing or an error message
+    pub fn flush(&mut self) -> Result<(), String> {
+        if !self.is_dirty() {
+            return Ok(());
+        }
+        if let Err(e) = replace_file(&self.path(), self.serialize(&self.records)) {
+            return Err(write_error(e, "Could not write to file."));
+        }
+        self.write_mode = WriteMode::Deferred { dirty: false };
+        debug!(records = self.records.len(), "flushed records");
+
+        Ok(())
+    }
+
+    // writes `records` as the whole file, or only marks the user dirty if writes are deferred
+    fn write_records(&mut self, records: &[Record]) -> Result<(), String> {
+        match self.write_mode {
+            WriteMode::Immediate => match write_to_file(&self.path(), self.serialize(records)) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(write_error(e, "Could not write to file.")),
+            },
+            WriteMode::Deferred { .. } => {
+                self.write_mode = WriteMode::Deferred { dirty: true };
+                Ok(())
+            }
+        }
+    }
+
+    // appends serialized records to the file, or only marks the user dirty if writes are deferred
+    fn append_records_to_file(&mut self, buffer: Vec<u8>) -> Result<(), String> {
+        match self.write_mode {
+            WriteMode::Immediate => match append_to_file(&self.path(), buffer) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(write_error(e, "Could not write to file.")),
+            },
+            WriteMode::Deferred { .. } => {
+                self.write_mode = WriteMode::Deferred { dirty: true };
+                Ok(())
+            }
+        }
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.records.clone()
+    }
+
+    /// Drop the decrypted records, each record is wiped when it is dropped
+    pub fn wipe(&mut self) {
+        self.records.clear();
+        self.unreadable.clear();
+    }
+
+    /// Read the records again from the users file, after they were wiped
+    ///
+    /// The write mode is kept, changes that were not flushed are lost.
+    ///
+    /// # Arguments
+    ///
+    /// * `master_pwd` - The master password of the user
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Nothing or an error message
+    pub fn reload(&mut self, master_pwd: &str) -> Result<(), String> {
+        let dir = self
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let mut user = User::from(&dir, &self.username(), master_pwd)?;
+        if let WriteMode::Deferred { .. } = self.write_mode {
+            user.write_mode = WriteMode::Deferred { dirty: false };
+        }
+        *self = user;
+
+        Ok(())
+    }
+
+    /// Number of records that decrypt but can not be read
+    ///
+    /// They are not listed, but are kept in the users file when it is written.
+    pub fn unreadable(&self) -> usize {
+        self.unreadable.len()
+    }
+
+    /// Number of records of the user
+    pub fn record_count(&self) -> usize {
+        self.records.len()
+    }
+
+    /// The records of the user in file order, to be sorted or filtered for presentation
+    pub fn read_only_records(&self) -> ReadOnlyRecords {
+        ReadOnlyRecords(self.records.clone())
+    }
+
+    /// Borrowed domain and password of each decrypted record, in file order
+    ///
+    /// Unlike `records` nothing is cloned, records that are not decrypted are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.records
+            .iter()
+            .filter_map(|r| Some((r.domain.as_deref()?, r.pwd.as_deref()?)))
+    }
+
+    /// Records tagged with `tag`, in file order
+    pub fn records_with_tag(&self, tag: &str) -> Vec<Record> {
+        self.read_only_records()
+            .filter(|r| r.tags.iter().any(|t| t == tag))
+            .records()
+    }
+
+    /// All tags used by the records, sorted and without duplicates
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.records.iter().flat_map(|r| r.tags.clone()).collect();
+        tags.sort();
+        tags.dedup();
+
+        tags
+    }
+
+    pub fn add_record(&mut self, record: RecordOperationConfig) -> Result<(), String> {
+        self.add_record_with_keys(record, &mut KeyCache::default())
+    }
+
+    // keys derived by the integrity check are reused by later calls with the same cache
+    #[instrument(skip_all)]
+    fn add_record_with_keys(
+        &mut self,
+        record: RecordOperationConfig,
+        keys: &mut KeyCache,
+    ) -> Result<(), String> {
+        let integrity =
+            self.check_integrity(&record.username, &record.master_pwd, &record.path, keys);
+
+        if !integrity {
+            return Err("Integrity check failed".to_string());
+        }
+
+        // records of one domain with different labels are not duplicates
+        if self
+            .records
+            .iter()
+            .any(|r| r.is(&record.domain, &record.label))
+        {
+            debug!("record already exists");
+            return Err("Record already exists".to_string());
+        }
+
+        let modified = Some(now_secs());
+        let data = marshal(
+            &record.domain,
+            &record.label,
+            &record.pwd,
+            &record.tags,
+            modified,
+        );
+        let cipher = self.encrypt(&data, &record.master_pwd, keys);
+        let cipher = match cipher {
+            Ok(cipher) => cipher,
+            Err(_) => {
+                error!("could not encrypt record");
+                return Err("Could not create user.".to_string());
+            }
+        };
+        let offset = self.last_offset();
+        let record = Record::new(
+            cipher,
+            offset,
             Some(record.domain.to_string()),
             Some(record.pwd.to_string()),
+            record.tags.clone(),
+        )
+        .with_label(record.label.clone())
+        .with_modified(modified);
+        let mut buffer = vec![];
+        record.cypher.write(&mut buffer);
+        self.append_records_to_file(buffer)?;
+        self.records.push(record);
+        debug!(offset, "added record");
+
+        Ok(())
+    }
+
+    /// Add many records with a single integrity check and a single write
+    ///
+    /// The username, master password and path of the first record are used for
+    /// the integrity check. Records whose domain and label already exist (or
+    /// appear earlier in the batch) are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `records` - The records to add
+    ///
+    /// # Returns
+    /// * `Result<ImportSummary, String>` - Added and skipped domains or an error message
+    pub fn add_records(
+        &mut self,
+        records: Vec<RecordOperationConfig>,
+    ) -> Result<ImportSummary, String> {
+        let mut keys = KeyCache::default();
+        let first = match records.first() {
+            Some(first) => first,
+            None => return Ok(ImportSummary::default()),
+        };
+        if !self.check_integrity(&first.username, &first.master_pwd, &first.path, &mut keys) {
+            return Err("Integrity check failed".to_string());
+        }
+
+        self.append_records(records, &mut keys)
+    }
+
+    // appends the records in a single write, the integrity has to be checked before
+    fn append_records(
+        &mut self,
+        records: Vec<RecordOperationConfig>,
+        keys: &mut KeyCache,
+    ) -> Result<ImportSummary, String> {
+        let mut summary = ImportSummary::default();
+        let mut names = self.names();
+        let mut new_records = vec![];
+        // the records are appended, so the first one starts at the end of the file
+        let file_len = self.serialize(&self.records).len();
+        let mut buffer = vec![];
+        let modified = Some(now_secs());
+        for record in records {
+            let name = display_name(&record.domain, &record.label);
+            if let Err(e) = record.validate() {
+                debug!("skipped invalid record: {}", e);
+                summary.skipped.push(name);
+                continue;
+            }
+            let key = (record.domain.clone(), record.label.clone());
+            if names.contains(&key) {
+                summary.skipped.push(name);
+                continue;
+            }
+
+            let data = marshal(
+                &record.domain,
+                &record.label,
+                &record.pwd,
+                &record.tags,
+                modified,
+            );
+            let cipher = match self.encrypt(&data, &record.master_pwd, keys) {
+                Ok(cipher) => cipher,
+                Err(_) => return Err("Could not encrypt data.".to_string()),
+            };
+            let offset = (file_len + buffer.len()) as u32;
+            cipher.write(&mut buffer);
+            new_records.push(
+                Record::new(
+                    cipher,
+                    offset,
+                    Some(record.domain.to_string()),
+                    Some(record.pwd.to_string()),
+                    record.tags.clone(),
+                )
+                .with_label(record.label.clone())
+                .with_modified(modified),
+            );
+            names.push(key);
+            summary.imported.push(name);
+        }
+
+        if !buffer.is_empty() {
+            self.append_records_to_file(buffer)?;
+            self.records.extend(new_records);
+        }
+
+        Ok(summary)
+    }
+
+    #[instrument(skip_all)]
+    pub fn remove_record(&mut self, record: RecordOperationConfig) -> Result<(), String> {
+        let mut keys = KeyCache::default();
+        let integrity = self.check_integrity(
+            &record.username,
+            &record.master_pwd,
+            &record.path,
+            &mut keys,
+        );
+
+        if !integrity {
+            return Err("Integrity check failed".to_string());
+        }
+
+        if !self
+            .records
+            .iter()
+            .any(|r| r.is(&record.domain, &record.label))
+        {
+            debug!("record not found");
+            return Err("Record not found".to_string());
+        }
+
+        let mut new_records = vec![];
+        for r in self.records.iter() {
+            if !r.is(&record.domain, &record.label) {
+                new_records.push(r.clone());
+            }
+        }
+
+        // TODO: calibrate offsets or remove them
+
+        // a file without a verifier is checked against its first record, so it
+        // gets one before its last record is removed
+        let header = self.header.clone();
+        if new_records.is_empty()
+            && self.unreadable.is_empty()
+            && header
+                .as_ref()
+                .is_none_or(|header| header.verifier.is_none())
+        {
+            let kdf = header.as_ref().map_or(Kdf::from_env(), |header| header.kdf);
+            let shared_salt = header.as_ref().is_some_and(|header| header.shared_salt);
+            match FileHeader::new(
+                kdf,
+                shared_salt,
+                &record.master_pwd,
+                &file_aad(&self.path()),
+                &mut keys,
+            ) {
+                Ok(verifier) => self.header = Some(verifier),
+                Err(_) => return Err("Could not encrypt data.".to_string()),
+            }
+        }
+        if let Err(e) = self.write_records(&new_records) {
+            self.header = header;
+            return Err(e);
+        }
+        self.records = new_records;
+        debug!(records = self.records.len(), "removed record");
+
+        Ok(())
+    }
+
+    pub fn modify_record(&mut self, record: RecordOperationConfig) -> Result<(), String> {
+        let mut keys = KeyCache::default();
+        let integrity = self.check_integrity(
+            &record.username,
+            &record.master_pwd,
+            &record.path,
+            &mut keys,
+        );
+
+        if !integrity {
+            return Err("Integrity check failed".to_string());
+        }
+
+        let mut new_records = vec![];
+        let mut old = None;
+        for r in self.records.iter() {
+            if !r.is(&record.domain, &record.label) {
+                new_records.push(r.clone());
+            } else {
+                old = Some(r);
+            }
+        }
+
+        let old = match old {
+            Some(old) => old,
+            None => return Err("Record not found".to_string()),
+        };
+        // only a new password counts as a change, not new tags
+        let modified = if old.pwd.as_deref() == Some(record.pwd.as_str()) {
+            old.modified
+        } else {
+            Some(now_secs())
+        };
+
+        let data = marshal(
+            &record.domain,
+            &record.label,
+            &record.pwd,
+            &record.tags,
+            modified,
         );
+        let cipher = self.encrypt(&data, &record.master_pwd, &mut keys);
+        let cipher = match cipher {
+            Ok(cipher) => cipher,
+            Err(_) => return Err("Could not create user.".to_string()),
+        };
+
+        let record = Record::new(
+            cipher,
+            self.last_offset(),
+            Some(record.domain.to_string()),
+            Some(record.pwd.to_string()),
+            record.tags.clone(),
+        )
+        .with_label(record.label.clone())
+        .with_modified(modified);
+
+        new_records.push(record);
+
+        self.write_records(&new_records)?;
+        self.records = new_records;
+
+        Ok(())
+    }
+
+    /// Rename the domain of a record, keeping its password and its place in the file
+    ///
+    /// # Arguments
+    ///
+    /// * `old` - The current domain of the record
+    /// * `label` - The label of the record, it is kept
+    /// * `new` - The new domain, it must not have a record with the same label
+    /// * `master_pwd` - The master password of the user, checked against the file
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Nothing or an error message
+    pub fn rename_domain(
+        &mut self,
+        old: &str,
+        label: &str,
+        new: &str,
+        master_pwd: &str,
+    ) -> Result<(), String> {
+        let mut keys = KeyCache::default();
+        let dir = self
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        if !self.check_integrity(&self.username(), master_pwd, &dir, &mut keys) {
+            return Err("Integrity check failed".to_string());
+        }
+
+        if new.is_empty() {
+            return Err("Domain can not be empty".to_string());
+        }
+        // the domain is shown and looked up as a single word
+        if new.contains(char::is_whitespace) {
+            return Err("Domain can not contain whitespace".to_string());
+        }
+        let index = match self.records.iter().position(|r| r.is(old, label)) {
+            Some(index) => index,
+            None => return Err("Record not found".to_string()),
+        };
+        if self.records.iter().any(|r| r.is(new, label)) {
+            return Err("Record already exists".to_string());
+        }
+
+        let pwd = self.records[index].pwd.clone().unwrap_or_default();
+        let tags = self.records[index].tags.clone();
+        let modified = self.records[index].modified;
+        let data = marshal(new, label, &pwd, &tags, modified);
+        let cipher = match self.encrypt(&data, master_pwd, &mut keys) {
+            Ok(cipher) => cipher,
+            Err(_) => return Err("Could not encrypt data.".to_string()),
+        };
+        let mut new_records = self.records.clone();
+        new_records[index] = Record::new(
+            cipher,
+            self.records[index].offset,
+            Some(new.to_string()),
+            Some(pwd),
+            tags,
+        )
+        .with_label(label.to_string())
+        .with_modified(modified);
+
+        self.write_records(&new_records)?;
+        self.records = new_records;
+
+        Ok(())
+    }
+
+    /// Move a record to another place in the file
+    ///
+    /// The records keep their encryption, only their order and offsets change.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain of the record
+    /// * `label` - The label of the record
+    /// * `new_index` - The place of the record in the file after the move
+    /// * `master_pwd` - The master password of the user, checked against the file
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Nothing or an error message
+    pub fn move_record(
+        &mut self,
+        domain: &str,
+        label: &str,
+        new_index: usize,
+        master_pwd: &str,
+    ) -> Result<(), String> {
+        let mut keys = KeyCache::default();
+        let dir = self
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        if !self.check_integrity(&self.username(), master_pwd, &dir, &mut keys) {
+            return Err("Integrity check failed".to_string());
+        }
+
+        let index = match self.records.iter().position(|r| r.is(domain, label)) {
+            Some(index) => index,
+            None => return Err("Record not found".to_string()),
+        };
+        if new_index >= self.records.len() {
+            return Err("Invalid position".to_string());
+        }
+
+        let mut new_records = self.records.clone();
+        let record = new_records.remove(index);
+        new_records.insert(new_index, record);
+        let mut offset = self.header.as_ref().map_or(0, |header| header.len());
+        for record in new_records.iter_mut() {
+            record.offset = offset as u32;
+            let mut buffer = vec![];
+            record.cypher.write(&mut buffer);
+            offset += buffer.len();
+        }
+
+        self.write_records(&new_records)?;
+        self.records = new_records;
+        debug!(from = index, to = new_index, "moved record");
+
+        Ok(())
+    }
+
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    // encrypts with the shared salt of the file if it has one
+    fn encrypt(
+        &self,
+        data: &str,
+        master_pwd: &str,
+        keys: &mut KeyCache,
+    ) -> Result<CipherConfig, aead::Error> {
+        match &self.header {
+            Some(header) if header.shared_salt => {
+                let derived_key = keys.derive_key(master_pwd, header.salt.clone(), header.kdf);
+                CipherConfig::encrypt_data_with_key(
+                    data,
+                    derived_key,
+                    header.kdf,
+                    &file_aad(&self.path),
+                )
+            }
+            _ => CipherConfig::encrypt_data(data, master_pwd, &file_aad(&self.path)),
+        }
+    }
+
+    // file content for the given records, including the file header
+    fn serialize(&self, records: &[Record]) -> Vec<u8> {
+        let mut buffer = vec![];
+        if let Some(header) = &self.header {
+            header.write(&mut buffer);
+        }
+        for record in records.iter().chain(self.unreadable.iter()) {
+            record.cypher.write(&mut buffer);
+        }
+        buffer
+    }
+
+    fn last_offset(&self) -> u32 {
+        let mut offset = 0;
+        for record in self.records.iter() {
+            if record.offset > offset {
+                offset = record.offset;
+            }
+        }
+
+        offset
+    }
+
+    #[cfg(test)]
+    fn first_record(&self) -> Record {
+        let first_offset = match &self.header {
+            Some(header) => header.len() as u32,
+            None => 0,
+        };
+        for record in self.records.iter() {
+            if record.offset == first_offset {
+                return record.clone();
+            }
+        }
+
+        panic!("No first record found");
+    }
+
+    #[cfg(test)]
+    fn domains(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .filter_map(|r| r.domain.clone())
+            .collect()
+    }
+
+    // domain and label of each decrypted record, in file order
+    fn names(&self) -> Vec<(String, String)> {
+        self.records
+            .iter()
+            .filter_map(|r| Some((r.domain.clone()?, r.label.clone())))
+            .collect()
+    }
+
+    // listed name (see `display_name`) and password of each decrypted record, in file order
+    fn named_passwords(&self) -> impl Iterator<Item = (String, &str)> {
+        self.records.iter().filter_map(|r| {
+            Some((
+                display_name(r.domain.as_deref()?, &r.label),
+                r.pwd.as_deref()?,
+            ))
+        })
+    }
+
+    /// Passwords used by more than one record
+    ///
+    /// Only the already decrypted records are compared, the file is not read.
+    ///
+    /// # Returns
+    /// * `Vec<(String, Vec<String>)>` - Each reused password with the records (see
+    ///   `display_name`) that use it, in the order the passwords first appear
+    pub fn reused_passwords(&self) -> Vec<(String, Vec<String>)> {
+        let mut groups: Vec<(String, Vec<String>)> = vec![];
+        for (name, pwd) in self.named_passwords() {
+            match groups.iter_mut().find(|(p, _)| p == pwd) {
+                Some((_, domains)) => domains.push(name),
+                None => groups.push((pwd.to_string(), vec![name])),
+            }
+        }
+        groups.retain(|(_, domains)| domains.len() > 1);
+
+        groups
+    }
+
+    /// Records (see `display_name`) whose password was not changed within `max_age`
+    ///
+    /// Records written before the time a password was set was stored are never
+    /// stale, so a file without any times gives nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_age` - How long a password may stay the same
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The names of the stale records in file order
+    pub fn stale_records(&self, max_age: Duration) -> Vec<String> {
+        let now = SystemTime::now();
+        self.records
+            .iter()
+            .filter(|record| {
+                record.modified().is_some_and(|modified| {
+                    now.duration_since(modified).is_ok_and(|age| age > max_age)
+                })
+            })
+            .filter_map(|record| Some(display_name(record.domain.as_deref()?, &record.label)))
+            .collect()
+    }
+
+    /// Re-encrypt all records under a new master password
+    ///
+    /// The file gets a new header with a verifier, the shared salt setting of the
+    /// file is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_master_pwd` - The current master password, checked against the file
+    /// * `new_master_pwd` - The new master password
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Nothing or an error message
+    pub fn change_master_password(
+        &mut self,
+        current_master_pwd: &str,
+        new_master_pwd: &str,
+    ) -> Result<(), String> {
+        if !self.check_master_pwd(current_master_pwd) {
+            return Err("Wrong master password".to_string());
+        }
+        if new_master_pwd.trim().is_empty() {
+            return Err("Master password can not be empty".to_string());
+        }
+
+        let mut keys = KeyCache::default();
+        let shared_salt = self
+            .header
+            .as_ref()
+            .is_some_and(|header| header.shared_salt);
+        let header = FileHeader::new(
+            Kdf::from_env(),
+            shared_salt,
+            new_master_pwd,
+            &file_aad(&self.path()),
+            &mut keys,
+        );
+        let header = match header {
+            Ok(header) => header,
+            Err(_) => return Err("Could not encrypt data.".to_string()),
+        };
+        let mut offset = header.len();
+        // every record is written, so there is nothing left to flush
+        let mode = match self.write_mode {
+            WriteMode::Immediate => WriteMode::Immediate,
+            WriteMode::Deferred { .. } => WriteMode::Deferred { dirty: false },
+        };
+        let mut user = User {
+            records: vec![],
+            path: self.path(),
+            header: Some(header),
+            username: self.username(),
+            write_mode: mode,
+            unreadable: vec![],
+        };
+        for record in self.records.iter() {
+            let (domain, pwd) = record.secret();
+            let data = marshal(&domain, &record.label, &pwd, &record.tags, record.modified);
+            let cipher = match user.encrypt(&data, new_master_pwd, &mut keys) {
+                Ok(cipher) => cipher,
+                Err(_) => return Err("Could not encrypt data.".to_string()),
+            };
+            let mut buffer = vec![];
+            cipher.write(&mut buffer);
+            user.records.push(
+                Record::new(
+                    cipher,
+                    offset as u32,
+                    Some(domain),
+                    Some(pwd),
+                    record.tags.clone(),
+                )
+                .with_label(record.label.clone())
+                .with_modified(record.modified),
+            );
+            offset += buffer.len();
+        }
+        // records that can not be read keep their plaintext as it is
+        for record in self.unreadable.iter() {
+            let data = match record.cypher.decrypt_data() {
+                Ok(data) => Zeroizing::new(data),
+                Err(_) => return Err("Could not decrypt data".to_string()),
+            };
+            let cipher = match user.encrypt(&data, new_master_pwd, &mut keys) {
+                Ok(cipher) => cipher,
+                Err(_) => return Err("Could not encrypt data.".to_string()),
+            };
+            let mut buffer = vec![];
+            cipher.write(&mut buffer);
+            user.unreadable
+                .push(Record::new(cipher, offset as u32, None, None, vec![]));
+            offset += buffer.len();
+        }
+
+        // the whole vault is rewritten, a crash while writing must not lose it
+        let buffer = user.serialize(&user.records);
+        if let Err(e) = replace_file(&self.path(), buffer) {
+            return Err(write_error(e, "Could not write to file."));
+        }
+        *self = user;
+
+        Ok(())
+    }
+
+    /// Records (see `display_name`) whose password is weak, see `password_strength`
+    ///
+    /// Only the already decrypted records are checked, the file is not read.
+    pub fn weak_passwords(&self) -> Vec<String> {
+        self.named_passwords()
+            .filter(|(_, pwd)| password_strength(pwd) == Strength::Weak)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Records (see `display_name`) whose password appears in known breaches, checked
+    /// with HaveIBeenPwned
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, String>` - The names of the records or an error message if
+    ///   the API could not be reached
+    #[cfg(feature = "hibp-online")]
+    pub fn breached_passwords(&self) -> Result<Vec<String>, String> {
+        let mut breached = vec![];
+        for (name, pwd) in self.named_passwords() {
+            if super::hibp::breach_count_online(pwd)? > 0 {
+                breached.push(name);
+            }
+        }
+
+        Ok(breached)
+    }
+
+    #[instrument(skip_all)]
+    fn check_integrity(
+        &self,
+        username: &str,
+        master_pwd: &str,
+        path: &Path,
+        keys: &mut KeyCache,
+    ) -> bool {
+        // a vault without records is valid, the header is checked instead
+        match Record::verify_master_pwd(&user_path(username, path), master_pwd, keys) {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("integrity check failed: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Check that every record of a user decrypts, nothing is kept or revealed
+    ///
+    /// Unlike reading the user, which stops at the first record that fails, every
+    /// record is tried. A record that can not even be parsed ends the file, as
+    /// the records after it can not be found.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the directory where the users data is stored
+    /// * `username` - The username of the user
+    /// * `master_pwd` - The master password of the user
+    ///
+    /// # Returns
+    /// * `Result<VerifyReport, String>` - The number of records and the offsets of
+    ///   those that failed, or an error message if the file can not be read at all
+    #[instrument(skip_all)]
+    pub fn verify(path: &Path, username: &str, master_pwd: &str) -> Result<VerifyReport, String> {
+        let file_path = user_path(username, path);
+        if !file_path.exists() {
+            return Err("User not found".to_string());
+        }
+        let mut keys = KeyCache::default();
+        let (mut reader, header, aad) = Record::open_file(&file_path, master_pwd, &mut keys)?;
+        let mut offset = header.map_or(0, |header| header.len() as u32);
+        let mut raw_records = vec![];
+        let mut unparsed = None;
+        loop {
+            match RawRecord::read(&mut reader, &aad) {
+                Ok(Some(raw)) => {
+                    let len = raw.len as u32;
+                    raw_records.push((offset, raw));
+                    offset += len;
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    warn!(offset, "could not parse record");
+                    unparsed = Some(offset);
+                    break;
+                }
+            }
+        }
+        keys.prefetch(
+            master_pwd,
+            raw_records
+                .iter()
+                .map(|(_, r)| (r.kdf, r.salt.clone()))
+                .collect(),
+        );
+
+        let mut report = VerifyReport {
+            records: raw_records.len() + usize::from(unparsed.is_some()),
+            failed: vec![],
+        };
+        for (offset, raw) in raw_records {
+            let record = raw.into_record(master_pwd, offset, &mut keys);
+            let decrypted = record
+                .cypher
+                .decrypt_data()
+                .map_err(|_| "Could not decrypt data".to_string())
+                .and_then(|data| unmarshal(&Zeroizing::new(data)));
+            if decrypted.is_err() {
+                warn!(offset, "could not decrypt record");
+                report.failed.push(offset);
+            }
+        }
+        report.failed.extend(unparsed);
+
+        Ok(report)
+    }
+
+    /// Check the master password against the users file
+    pub fn check_master_pwd(&self, master_pwd: &str) -> bool {
+        Record::verify_master_pwd(&self.path(), master_pwd, &mut KeyCache::default()).is_ok()
+    }
+
+    /// Export all records as CSV
+    ///
+    /// # Arguments
+    ///
+    /// * `master_pwd` - The master password of the user, checked against the file
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - CSV with a `domain,password` header or an error message,
+    ///   the passwords in it are NOT encrypted
+    pub fn export_csv(&self, master_pwd: &str) -> Result<String, String> {
+        if !self.check_master_pwd(master_pwd) {
+            return Err("Integrity check failed".to_string());
+        }
+
+        let mut csv = "domain,password\n".to_string();
+        for record in self.records.iter() {
+            let (domain, pwd) = record.secret();
+            let name = display_name(&domain, &record.label);
+            csv.push_str(&format!("{},{}\n", csv_field(&name), csv_field(&pwd)));
+        }
+
+        Ok(csv)
+    }
+
+    /// Export all records in the layout of `pass` (password-store)
+    ///
+    /// Every record is written to `<out>/<domain>.txt` (`<out>/<domain>/<label>.txt` if it
+    /// has a label) with the password on the first line and the tags (if any) on the
+    /// second, slashes in the domain make nested directories.
+    /// The files are NOT encrypted, encrypting them for `pass` is left to the user, only
+    /// the owner can read them on unix.
+    ///
+    /// # Arguments
+    ///
+    /// * `master_pwd` - The master password of the user, checked against the file
+    /// * `out` - The directory the records are written to, created if missing
+    /// * `overwrite` - Replace entries that already exist, otherwise nothing is written
+    ///   if one does
+    ///
+    /// # Returns
+    /// * `Result<usize, String>` - How many records were written or an error message
+    pub fn export_pass(
+        &self,
+        master_pwd: &str,
+        out: &Path,
+        overwrite: bool,
+    ) -> Result<usize, String> {
+        if !self.check_master_pwd(master_pwd) {
+            return Err("Integrity check failed".to_string());
+        }
+
+        let mut files = vec![];
+        for record in self.records.iter() {
+            let (domain, _) = record.secret();
+            let entry = if record.label.is_empty() {
+                domain
+            } else {
+                format!("{}/{}", domain, record.label)
+            };
+            let file = pass_entry_path(out, &entry)?;
+            if !overwrite && file.exists() {
+                return Err(format!("{} already exists", file.display()));
+            }
+            files.push(file);
+        }
+
+        for (record, file) in self.records.iter().zip(files) {
+            let (_, pwd) = record.secret();
+            let mut entry = Zeroizing::new(format!("{}\n", pwd));
+            if !record.tags.is_empty() {
+                entry.push_str(&format!("tags: {}\n", record.tags.join(",")));
+            }
+
+            let written = match file.parent() {
+                Some(dir) => fs::create_dir_all(dir)
+                    .and_then(|_| write_private_file(&file, entry.as_bytes(), overwrite)),
+                None => write_private_file(&file, entry.as_bytes(), overwrite),
+            };
+            if written.is_err() {
+                return Err(format!("Could not write {}", file.display()));
+            }
+        }
+
+        Ok(self.records.len())
+    }
+
+    /// Export all records as a single encrypted blob
+    ///
+    /// # Arguments
+    ///
+    /// * `master_pwd` - The master password of the user, checked against the file
+    /// * `passphrase` - The passphrase the export is encrypted with
+    ///
+    /// # Returns
+    /// * `Result<Vec<u8>, String>` - Salt, nonce, length and ciphertext of the JSON encoded
+    ///   records (same layout as a record in the users file) or an error message
+    pub fn export_encrypted(&self, master_pwd: &str, passphrase: &str) -> Result<Vec<u8>, String> {
+        if !self.check_master_pwd(master_pwd) {
+            return Err("Integrity check failed".to_string());
+        }
+
+        let records: Vec<ExportedRecord> = self
+            .records
+            .iter()
+            .map(|r| {
+                let (domain, password) = r.secret();
+                ExportedRecord {
+                    domain,
+                    label: r.label.clone(),
+                    password,
+                    tags: r.tags.clone(),
+                }
+            })
+            .collect();
+        let json = match serde_json::to_string(&records) {
+            Ok(json) => Zeroizing::new(json),
+            Err(_) => return Err("Could not serialize records.".to_string()),
+        };
+
+        let cipher = match CipherConfig::encrypt_data(&json, passphrase, &[]) {
+            Ok(cipher) => cipher,
+            Err(_) => return Err("Could not encrypt data.".to_string()),
+        };
+        let mut buffer = vec![];
+        cipher.write(&mut buffer);
+
+        Ok(buffer)
+    }
+
+    /// Create a new user from a blob made by `export_encrypted`
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The exported blob
+    /// * `passphrase` - The passphrase the export was encrypted with
+    /// * `username` - The username of the new user
+    /// * `master_pwd` - The master password of the new user
+    /// * `path` - Path to the directory where the file (users data) is stored
+    ///
+    /// # Returns
+    /// * `Result<Self, String>` - The new user or an error message
+    pub fn import_encrypted(
+        data: Vec<u8>,
+        passphrase: &str,
+        username: &str,
+        master_pwd: &str,
+        path: &Path,
+    ) -> Result<Self, String> {
+        let record =
+            match Record::read_from_bytes(data, passphrase, 0, &mut KeyCache::default(), &[]) {
+                Ok((record, _, _)) => record,
+                Err(_) => return Err("Could not read export".to_string()),
+            };
+        let json = match record.cypher.decrypt_data() {
+            Ok(json) => Zeroizing::new(json),
+            Err(_) => return Err("Could not decrypt data".to_string()),
+        };
+        let records: Vec<ExportedRecord> = match serde_json::from_str(&json) {
+            Ok(records) => records,
+            Err(_) => return Err("Could not deserialize records.".to_string()),
+        };
+
+        let mut records = records.iter();
+        let first = match records.next() {
+            Some(first) => first,
+            None => return Err("Export contains no records".to_string()),
+        };
+        let config = |r: &ExportedRecord| RecordOperationConfig {
+            label: r.label.clone(),
+            tags: r.tags.clone(),
+            ..RecordOperationConfig::new(username, master_pwd, &r.domain, &r.password, path)
+        };
+        let mut user = User::new(&config(first))?;
+        let records = records.map(config).collect();
+        user.add_records(records)?;
+
+        Ok(user)
+    }
+
+    /// Add the logins of an unencrypted Bitwarden JSON export
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - Content of the export file
+    /// * `username` - The username of the user
+    /// * `master_pwd` - The master password of the user
+    /// * `path` - Path to the directory where the file (users data) is stored
+    ///
+    /// # Returns
+    /// * `Result<ImportSummary, String>` - Added and skipped entries or an error message
+    pub fn import_bitwarden(
+        &mut self,
+        json: &str,
+        username: &str,
+        master_pwd: &str,
+        path: &Path,
+    ) -> Result<ImportSummary, String> {
+        let mut keys = KeyCache::default();
+        if !self.check_integrity(username, master_pwd, path, &mut keys) {
+            return Err("Integrity check failed".to_string());
+        }
+
+        let logins = bitwarden::parse(json)?;
+        let (records, malformed) = bitwarden_records(&logins, username, master_pwd, path);
+        let mut summary = self.append_records(records, &mut keys)?;
+        summary
+            .skipped
+            .splice(0..0, malformed.into_iter().map(|(_, name)| name));
+
+        Ok(summary)
+    }
+
+    /// Check what `import_bitwarden` would do, nothing is written
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - Content of the export file
+    /// * `username` - The username of the user
+    /// * `master_pwd` - The master password of the user
+    /// * `path` - Path to the directory where the file (users data) is stored
+    ///
+    /// # Returns
+    /// * `Result<ImportReport, String>` - Records that would be added, duplicates and
+    ///   malformed entries or an error message
+    pub fn validate_bitwarden_import(
+        &self,
+        json: &str,
+        username: &str,
+        master_pwd: &str,
+        path: &Path,
+    ) -> Result<ImportReport, String> {
+        let logins = bitwarden::parse(json)?;
+        let (records, malformed) = bitwarden_records(&logins, username, master_pwd, path);
+        let mut report = ImportReport {
+            malformed,
+            ..ImportReport::default()
+        };
+        let mut names = self.names();
+        for record in records.iter() {
+            let key = (record.domain.clone(), record.label.clone());
+            let name = display_name(&record.domain, &record.label);
+            if names.contains(&key) {
+                report.duplicates.push(name);
+            } else {
+                names.push(key);
+                report.added.push(name);
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+// records for the logins of a Bitwarden export, the username of a login is the
+// label of its record, so a site with several accounts keeps all of them
+//
+// Logins that can not be stored are returned with the items without a login as
+// their position and name.
+fn bitwarden_records(
+    logins: &BitwardenLogins,
+    username: &str,
+    master_pwd: &str,
+    path: &Path,
+) -> (Vec<RecordOperationConfig>, Vec<(usize, String)>) {
+    let mut records = vec![];
+    let mut malformed = logins.skipped.clone();
+    for login in logins.logins.iter() {
+        let config = RecordOperationConfig::builder()
+            .username(username)
+            .master_pwd(master_pwd)
+            .domain(&login.domain)
+            .label(&login.username)
+            .pwd(&login.password)
+            .path(path)
+            .build();
+        match config {
+            Ok(config) => records.push(config),
+            Err(e) => {
+                debug!(item = login.item, "skipped login: {}", e);
+                malformed.push((login.item, login.name.clone()));
+            }
+        }
+    }
+    malformed.sort_by_key(|(item, _)| *item);
+
+    (records, malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use dotenv::dotenv;
+    use rand::Rng;
+    use std::{env, fs};
+
+    fn random_number() -> u32 {
+        let mut rng = rand::thread_rng();
+        rng.gen_range(10000000..99999999)
+    }
+
+    fn generate_random_username() -> String {
+        format!("keeper-crabby-{}", random_number())
+    }
+
+    fn setup_user_data(domain: &str) -> Result<RecordOperationConfig, String> {
+        dotenv().ok();
+        let username = generate_random_username();
+        let username = username.as_str().to_owned();
+        let master_pwd = "password";
+        let pwd = "password";
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let user = RecordOperationConfig::new(username.as_str(), master_pwd, domain, pwd, &path);
+        match User::new(&user) {
+            Ok(_) => Ok(user.clone()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn create_user(config: &RecordOperationConfig) -> Result<User, String> {
+        User::from(&config.path, &config.username, &config.master_pwd)
+    }
+
+    #[test]
+    fn test_derive_key() {
+        let data = "kepper-crabby";
+        let derived_key = DerivedKey::derive_key(data, None, Kdf::Scrypt);
+        let key = derived_key.key;
+        let salt = derived_key.salt;
+        assert_eq!(key.len(), 16);
+        assert_eq!(salt.len(), 22);
+    }
+
+    #[test]
+    fn test_cipher_config() {
+        let data = "keeper-crabby";
+        let master_pwd = "password";
+        let cipher = CipherConfig::encrypt_data(data, master_pwd, &[]).unwrap();
+        let decrypted = cipher.decrypt_data().unwrap();
+        assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn test_create_user_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data);
+
+        // delete the file (user)
+        let hashed_username = hash(user_data.username.to_string());
+        let file_path = user_data.path.join(hashed_username.as_str());
+        fs::remove_file(file_path).unwrap();
+
+        assert!(user.is_ok());
+    }
+
+    #[test]
+    fn test_create_user_returns_user() {
+        dotenv().ok();
+        let username = generate_random_username();
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let config =
+            RecordOperationConfig::new(&username, "password", "example.com", "password", &path);
+        let user = User::new(&config).unwrap();
+        let read = create_user(&config).unwrap();
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(user.path(), read.path());
+        assert_eq!(user.records(), read.records());
+    }
+
+    #[test]
+    fn test_create_user_fail_already_exists() {
+        // setup_user_data function not used here because we want to test
+        // the case where the user already exists thus we need to try to create
+        // a user with the same username twice (setup_user_data creates a new user each time
+        // with a unique username)
+
+        dotenv().ok();
+        let username = generate_random_username();
+        let username = username.as_str();
+        let master_pwd = "password";
+        let domain = "example.com";
+        let pwd = "password";
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let config = RecordOperationConfig::new(username, master_pwd, domain, pwd, &path);
+        let _ = User::new(&config);
+
+        let config = RecordOperationConfig::new(username, master_pwd, domain, pwd, &path);
+        let res = User::new(&config);
+
+        // delete the file (user)
+        let hashed_username = hash(username.to_string());
+        let file_path = path.join(hashed_username.as_str());
+        fs::remove_file(file_path).unwrap();
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_integrity_success() {
+        dotenv().ok();
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+
+        let integrity = user.check_integrity(
+            &user_data.username,
+            &user_data.master_pwd,
+            &user_data.path,
+            &mut KeyCache::default(),
+        );
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(integrity);
+    }
+
+    #[test]
+    fn test_integrity_fail() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+
+        let integrity = user.check_integrity(
+            &user_data.username,
+            "wrong_pwd",
+            &user_data.path,
+            &mut KeyCache::default(),
+        );
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(!integrity);
+    }
+
+    #[test]
+    fn test_read_record_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+
+        let records = user.records();
+        let first_record = user.first_record();
+        let (domain, pwd) = first_record.secret();
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(domain, "example.com");
+        assert_eq!(pwd, "password");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_read_record_fail() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let try_user = User::from(&user_data.path, &user_data.username, "wrong_pwd");
+
+        // delete the file (user)
+        let hashed_username = hash(user_data.username);
+        let file_path = user_data.path.join(hashed_username.as_str());
+        fs::remove_file(file_path).unwrap();
+
+        // this should panic
+        let _ = try_user.unwrap();
+    }
+
+    #[test]
+    fn test_add_record_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_domain = "example2.com";
+        let new_pwd = "password2";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let res = user.add_record(add_record);
+
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+
+        let records = user.records();
+        let inserted_record = records
+            .iter()
+            .find(|r| r.domain == Some(new_domain.to_string()));
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_ok());
+        assert!(inserted_record.is_some());
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].domain, Some(new_domain.to_string()));
+        assert_eq!(records[1].pwd, Some(new_pwd.to_string()));
+    }
+
+    #[test]
+    fn test_add_record_fail() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_domain = "example2.com";
+        let new_pwd = "password2";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            "wrong_pwd",
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let res = user.add_record(add_record);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(user.records().len(), 1);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_add_record_labels() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        let labeled = |label: &str, pwd: &str| {
+            RecordOperationConfig::builder()
+                .username(&user_data.username)
+                .master_pwd(&user_data.master_pwd)
+                .domain("bank.com")
+                .label(label)
+                .pwd(pwd)
+                .path(&user_data.path)
+                .build()
+                .unwrap()
+        };
+
+        let login = user.add_record(labeled("login", "password2"));
+        let pin = user.add_record(labeled("pin", "1234"));
+        let duplicate = user.add_record(labeled("pin", "5678"));
+        // a `#` is part of the domain, not the start of a label
+        let fragment = user.add_record(RecordOperationConfig {
+            domain: "bank.com#pin".to_string(),
+            ..user_data.clone()
+        });
+        let read = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+        let records: Vec<(String, String, String)> = read
+            .records()
+            .iter()
+            .map(|r| {
+                let (domain, pwd) = r.secret();
+                (domain, r.label().to_string(), pwd)
+            })
+            .collect();
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(login.is_ok());
+        assert!(pin.is_ok());
+        assert_eq!(duplicate, Err("Record already exists".to_string()));
+        assert!(fragment.is_ok());
+        let record = |domain: &str, label: &str, pwd: &str| {
+            (domain.to_string(), label.to_string(), pwd.to_string())
+        };
+        assert_eq!(
+            records[1..],
+            [
+                record("bank.com", "login", "password2"),
+                record("bank.com", "pin", "1234"),
+                record("bank.com#pin", "", "password"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_record_fail_already_exists() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_domain = "example.com";
+        let new_pwd = "password2";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let res = user.add_record(add_record);
+        let read = create_user(&user_data).unwrap();
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(user.records().len(), 1);
+        assert_eq!(read.records().len(), 1);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_remove_record_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_domain = "example2.com";
+        let new_pwd = "password2";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let _ = user.add_record(add_record);
+
+        let new_domain = "example3.com";
+        let new_pwd = "password3";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let _ = user.add_record(add_record);
+
+        let remove_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            "example2.com",
+            "",
+            &user_data.path,
+        );
+        let res = user.remove_record(remove_record);
+
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+
+        let records = user.records();
+        let domains = user.domains();
+
+        let file_length = fs::read(user.path()).unwrap().len();
+        let header_len = user.header.as_ref().map_or(0, |h| h.len());
+        let records_len = records
+            .iter()
+            .fold(header_len, |acc, r| acc + r.cypher.len());
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(records.len(), 2);
+        assert!(domains
+            .iter()
+            .find(|d| d.as_str() == "example2.com")
+            .is_none());
+        assert!(domains
+            .iter()
+            .find(|d| d.as_str() == "example3.com")
+            .is_some());
+        assert!(domains
+            .iter()
+            .find(|d| d.as_str() == "example.com")
+            .is_some());
+        assert_eq!(file_length, records_len);
+    }
+
+    #[test]
+    fn test_remove_record_read_user_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_domain = "example2.com";
+        let new_pwd = "password2";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let _ = user.add_record(add_record);
+
+        let new_domain = "example3.com";
+        let new_pwd = "password3";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let _ = user.add_record(add_record);
+
+        let remove_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            "example2.com",
+            "",
+            &user_data.path,
+        );
+        let res = user.remove_record(remove_record);
+
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+        let records = user.records();
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(records.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_record_fail_not_found() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_domain = "example2.com";
+        let new_pwd = "password2";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let _ = user.add_record(add_record);
+
+        let remove_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            "example3.com",
+            "",
+            &user_data.path,
+        );
+        let res = user.remove_record(remove_record);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_remove_record_fail_integrity_check() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_domain = "example2.com";
+        let new_pwd = "password2";
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            new_domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let _ = user.add_record(add_record);
+
+        let remove_record = RecordOperationConfig::new(
+            &user_data.username,
+            "wrong_pwd",
+            "example2.com",
+            "",
+            &user_data.path,
+        );
+        let res = user.remove_record(remove_record);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    pub fn test_modify_record_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_pwd = "password2";
+        let modify_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            &user_data.domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let res = user.modify_record(modify_record);
+
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+        let records = user.records();
+        let modified_record = records
+            .iter()
+            .find(|r| r.domain == Some(user_data.domain.to_string()));
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_ok());
+        assert!(modified_record.is_some());
+        assert_eq!(modified_record.unwrap().pwd, Some(new_pwd.to_string()));
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn test_add_records_reads_file_once() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        let mut records: Vec<RecordOperationConfig> = (0..50)
+            .map(|i| {
+                let mut config = user_data.clone();
+                config.domain = format!("example{}.org", i);
+                config
+            })
+            .collect();
+        records.push(user_data.clone());
+
+        FILE_READS.with(|r| r.set(0));
+        let summary = user.add_records(records).unwrap();
+        let reads = FILE_READS.with(|r| r.get());
+        let read = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(reads, 1);
+        assert_eq!(summary.imported.len(), 50);
+        assert_eq!(summary.skipped, vec!["example.com".to_string()]);
+        assert_eq!(read.unwrap().records().len(), 51);
+    }
+
+    #[test]
+    fn test_rename_domain_success() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example2.com".to_string();
+        user.add_record(user_data.clone()).unwrap();
+
+        let res = user.rename_domain("example.com", "", "example3.com", &user_data.master_pwd);
+        let read = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_ok());
+        let expected = vec![("example3.com", "password"), ("example2.com", "password")];
+        assert_eq!(user.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(read.unwrap().iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_rename_domain_fail_not_found() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let res = user.rename_domain("example2.com", "", "example3.com", &user_data.master_pwd);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(res, Err("Record not found".to_string()));
+    }
+
+    #[test]
+    fn test_rename_domain_fail_already_exists() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example2.com".to_string();
+        user.add_record(user_data.clone()).unwrap();
+
+        let res = user.rename_domain("example.com", "", "example2.com", &user_data.master_pwd);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(res, Err("Record already exists".to_string()));
+        assert_eq!(user.domains(), vec!["example.com", "example2.com"]);
+    }
+
+    #[test]
+    pub fn test_modify_integrity_fail() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_pwd = "password2";
+        let modify_record = RecordOperationConfig::new(
+            &user_data.username,
+            "wrong_pwd",
+            &user_data.domain,
+            new_pwd,
+            &user_data.path,
+        );
+        let res = user.modify_record(modify_record);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example.org".to_string();
+        user.add_record(user_data.clone()).unwrap();
+        // offsets of records added in memory are not the ones in the file
+        let user = create_user(&user_data).unwrap();
+        let intact = User::verify(&user_data.path, &user_data.username, &user_data.master_pwd);
+
+        // flip the last byte, the end of the tag of the second record
+        let mut bytes = fs::read(user.path()).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        fs::write(user.path(), bytes).unwrap();
+        let corrupted = User::verify(&user_data.path, &user_data.username, &user_data.master_pwd);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(
+            intact.unwrap(),
+            VerifyReport {
+                records: 2,
+                failed: vec![]
+            }
+        );
+        assert_eq!(
+            corrupted.unwrap(),
+            VerifyReport {
+                records: 2,
+                failed: vec![user.records[1].offset]
+            }
+        );
+    }
+
+    #[test]
+    fn test_add_record_read_only() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        let mut permissions = fs::metadata(user.path()).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(user.path(), permissions.clone()).unwrap();
+
+        // permissions do not apply to root
+        let enforced = fs::OpenOptions::new()
+            .append(true)
+            .open(user.path())
+            .is_err();
+        let read_only = user.is_read_only();
+        user_data.domain = "example.org".to_string();
+        let res = user.add_record(user_data.clone());
+
+        // delete the file (user)
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(user.path(), permissions).unwrap();
+        fs::remove_file(user.path()).unwrap();
+
+        if enforced {
+            assert!(read_only);
+            assert_eq!(res.unwrap_err(), "The vault is read-only");
+            assert_eq!(user.record_count(), 1);
+        } else {
+            assert!(!read_only);
+            assert!(res.is_ok());
+        }
+    }
+
+    #[test]
+    fn test_move_record() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        for domain in ["example.org", "example.net"] {
+            user_data.domain = domain.to_string();
+            user.add_record(user_data.clone()).unwrap();
+        }
+        let mut user = create_user(&user_data).unwrap();
+
+        let moved = user.move_record("example.net", "", 0, &user_data.master_pwd);
+        let out_of_range = user.move_record("example.net", "", 3, &user_data.master_pwd);
+        let wrong_pwd = user.move_record("example.net", "", 1, "wrong");
+        let reloaded = create_user(&user_data).unwrap();
+        let report = User::verify(&user_data.path, &user_data.username, &user_data.master_pwd);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(moved.is_ok());
+        assert_eq!(out_of_range.unwrap_err(), "Invalid position");
+        assert!(wrong_pwd.is_err());
+        assert_eq!(
+            reloaded.domains(),
+            vec!["example.net", "example.com", "example.org"]
+        );
+        assert_eq!(user.domains(), reloaded.domains());
+        let offsets = |user: &User| user.records.iter().map(|r| r.offset).collect::<Vec<_>>();
+        assert_eq!(offsets(&user), offsets(&reloaded));
+        assert_eq!(report.unwrap().failed, Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_count_records() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        for domain in ["example.org", "example.net"] {
+            user_data.domain = domain.to_string();
+            user.add_record(user_data.clone()).unwrap();
+        }
+        let count = count_records(&user_data.path, &user_data.username);
+        let decrypted = create_user(&user_data).unwrap().record_count();
+
+        // a truncated record is not counted as one
+        let mut bytes = fs::read(user.path()).unwrap();
+        bytes.pop();
+        fs::write(user.path(), bytes).unwrap();
+        let truncated = count_records(&user_data.path, &user_data.username);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(count.unwrap(), 3);
+        assert_eq!(decrypted, 3);
+        assert_eq!(truncated.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            count_records(&user_data.path, &user_data.username)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    pub fn test_modify_record_fail_not_found() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let new_pwd = "password2";
+        let modify_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            "example2.com",
+            new_pwd,
+            &user_data.path,
+        );
+        let res = user.modify_record(modify_record);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("example.com"), "example.com");
+        assert_eq!(csv_field("pass,word"), "\"pass,word\"");
+        assert_eq!(csv_field("pass\"word"), "\"pass\"\"word\"");
+    }
+
+    #[test]
+    fn test_export_csv_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            "example2.com",
+            "pass,\"word",
+            &user_data.path,
+        );
+        let _ = user.add_record(add_record);
+
+        let csv = user.export_csv(&user_data.master_pwd);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert_eq!(
+            csv.unwrap(),
+            "domain,password\nexample.com,password\nexample2.com,\"pass,\"\"word\"\n"
+        );
+    }
+
+    #[test]
+    fn test_export_pass_success() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "work/mail.example.com".to_string();
+        user_data.pwd = "password2".to_string();
+        user_data.tags = vec!["work".to_string()];
+        user.add_record(user_data.clone()).unwrap();
+        let out = env::temp_dir().join(format!("{}_pass", user_data.username));
+
+        let exported = user.export_pass(&user_data.master_pwd, &out, false);
+        let first = fs::read_to_string(out.join("example.com.txt"));
+        let nested = fs::read_to_string(out.join("work").join("mail.example.com.txt"));
+        let again = user.export_pass(&user_data.master_pwd, &out, false);
+        let overwritten = user.export_pass(&user_data.master_pwd, &out, true);
+
+        // delete the file (user) and the export
+        fs::remove_file(user.path()).unwrap();
+        fs::remove_dir_all(&out).unwrap();
+
+        assert_eq!(exported.unwrap(), 2);
+        assert_eq!(first.unwrap(), "password\n");
+        assert_eq!(nested.unwrap(), "password2\ntags: work\n");
+        assert!(again.unwrap_err().ends_with("already exists"));
+        assert_eq!(overwritten.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_pass_entry_path_fail_outside() {
+        let out = Path::new("/tmp/pass");
+
+        assert!(pass_entry_path(out, "../example.com").is_err());
+        assert!(pass_entry_path(out, "work//example.com").is_err());
+        assert!(pass_entry_path(out, "/example.com").is_err());
+        assert_eq!(
+            pass_entry_path(out, "a/b.com").unwrap(),
+            out.join("a").join("b.com.txt")
+        );
+    }
+
+    #[test]
+    fn test_export_csv_fail_integrity_check() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+
+        let csv = user.export_csv("wrong_pwd");
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(csv.is_err());
+    }
+
+    #[test]
+    fn test_export_import_encrypted_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let add_record = RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            "example2.com",
+            "password2",
+            &user_data.path,
+        );
+        let _ = user.add_record(add_record);
+
+        let export = user
+            .export_encrypted(&user_data.master_pwd, "passphrase")
+            .unwrap();
+
+        let username = generate_random_username();
+        let imported = User::import_encrypted(
+            export,
+            "passphrase",
+            &username,
+            "new_password",
+            &user_data.path,
+        );
+        let imported_from_file = User::from(&user_data.path, &username, "new_password");
+
+        // delete the files (users)
+        fs::remove_file(user.path()).unwrap();
+        let _ = fs::remove_file(user_data.path.join(hash(username)));
+
+        let secrets = |u: &User| u.records().iter().map(|r| r.secret()).collect::<Vec<_>>();
+        assert_eq!(secrets(&imported.unwrap()), secrets(&user));
+        assert_eq!(secrets(&imported_from_file.unwrap()), secrets(&user));
+    }
+
+    #[test]
+    fn test_import_encrypted_fail_wrong_passphrase() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+
+        let export = user
+            .export_encrypted(&user_data.master_pwd, "passphrase")
+            .unwrap();
+
+        let username = generate_random_username();
+        let imported = User::import_encrypted(
+            export,
+            "wrong_passphrase",
+            &username,
+            "new_password",
+            &user_data.path,
+        );
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(imported.is_err());
+        assert!(!user_data.path.join(hash(username)).exists());
+    }
+
+    #[test]
+    fn test_import_bitwarden_success() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+
+        let json = r#"{
+            "items": [
+                {
+                    "name": "Example",
+                    "login": { "password": "password2", "uris": [{ "uri": "https://example.com" }] }
+                },
+                {
+                    "name": "Example 2",
+                    "login": { "password": "password2", "uris": [{ "uri": "https://example2.com" }] }
+                },
+                { "name": "Card" },
+                {
+                    "name": "Example as alice",
+                    "login": {
+                        "username": "alice",
+                        "password": "pass word",
+                        "uris": [{ "uri": "https://example.com" }]
+                    }
+                },
+                { "name": "Router #2", "login": { "password": "password3" } },
+                {
+                    "name": "Example as bob",
+                    "login": {
+                        "username": "bob smith",
+                        "password": "password4",
+                        "uris": [{ "uri": "https://example.com" }]
+                    }
+                }
+            ]
+        }"#;
+        let summary = user.import_bitwarden(
+            json,
+            &user_data.username,
+            &user_data.master_pwd,
+            &user_data.path,
+        );
+
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        let summary = summary.unwrap();
+        assert_eq!(
+            summary.imported,
+            vec![
+                "example2.com",
+                "example.com (alice)",
+                "example.com (bob smith)"
+            ]
+        );
+        assert_eq!(summary.skipped, vec!["Card", "Router #2", "example.com"]);
+        assert_eq!(
+            user.iter().collect::<Vec<_>>(),
+            vec![
+                ("example.com", "password"),
+                ("example2.com", "password2"),
+                ("example.com", "pass word"),
+                ("example.com", "password4"),
+            ]
+        );
+        assert_eq!(user.records()[2].label(), "alice");
+    }
+
+    #[test]
+    fn test_add_records_skips_invalid() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        let config = |domain: &str| RecordOperationConfig {
+            domain: domain.to_string(),
+            ..user_data.clone()
+        };
+
+        let summary = user.add_records(vec![
+            config("example2.com"),
+            config("bad domain"),
+            config(""),
+            RecordOperationConfig {
+                tags: vec!["a,b".to_string()],
+                ..config("example3.com")
+            },
+        ]);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        let summary = summary.unwrap();
+        assert_eq!(summary.imported, vec!["example2.com"]);
+        assert_eq!(summary.skipped, vec!["bad domain", "", "example3.com"]);
+        assert_eq!(user.domains(), vec!["example.com", "example2.com"]);
+    }
+
+    #[test]
+    fn test_validate_bitwarden_import() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+        let modified = fs::metadata(user.path()).unwrap().modified().unwrap();
+
+        let json = r#"{
+            "items": [
+                {
+                    "name": "Example",
+                    "login": { "password": "password2", "uris": [{ "uri": "https://example.com" }] }
+                },
+                {
+                    "name": "Example 2",
+                    "login": { "password": "password2", "uris": [{ "uri": "https://example2.com" }] }
+                },
+                { "name": "Card" },
+                {
+                    "name": "Example 2 again",
+                    "login": { "password": "password3", "uris": [{ "uri": "https://example2.com" }] }
+                },
+                { "name": "Router #2", "login": { "password": "password3" } },
+                {
+                    "name": "Example as alice",
+                    "login": {
+                        "username": "alice",
+                        "password": "password4",
+                        "uris": [{ "uri": "https://example.com" }]
+                    }
+                }
+            ]
+        }"#;
+        let report = user.validate_bitwarden_import(
+            json,
+            &user_data.username,
+            &user_data.master_pwd,
+            &user_data.path,
+        );
+        let unchanged = fs::metadata(user.path()).unwrap().modified().unwrap() == modified;
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        let report = report.unwrap();
+        assert_eq!(report.added, vec!["example2.com", "example.com (alice)"]);
+        assert_eq!(report.duplicates, vec!["example.com", "example2.com"]);
+        assert_eq!(
+            report.malformed,
+            vec![(3, "Card".to_string()), (5, "Router #2".to_string())]
+        );
+        assert!(unchanged);
+        assert_eq!(user.domains(), vec!["example.com"]);
+    }
+
+    #[test]
+    fn test_derive_key_argon2id() {
+        let data = "kepper-crabby";
+        let derived_key = DerivedKey::derive_key(data, None, Kdf::Argon2id);
+        let same_salt = DerivedKey::derive_key(data, Some(derived_key.salt.clone()), Kdf::Argon2id);
+        let scrypt_key = DerivedKey::derive_key(data, Some(derived_key.salt.clone()), Kdf::Scrypt);
+        assert_eq!(derived_key.key.len(), 16);
+        assert_eq!(derived_key.salt.len(), 22);
+        assert_eq!(derived_key.key, same_salt.key);
+        assert_ne!(derived_key.key, scrypt_key.key);
+    }
+
+    #[test]
+    fn test_read_records_with_mixed_kdfs() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+
+        let mut buffer = vec![];
+        let aad = file_aad(&user.path());
+        CipherConfig::encrypt_data_with_kdf(
+            "example2.com password2",
+            "password",
+            Kdf::Argon2id,
+            &aad,
+        )
+        .unwrap()
+        .write(&mut buffer);
+        // a record written before the header existed has no version and kdf bytes
+        let mut legacy = vec![];
+        CipherConfig::encrypt_data_with_kdf("example3.com password3", "password", Kdf::Scrypt, &[])
+            .unwrap()
+            .write(&mut legacy);
+        buffer.extend_from_slice(&legacy[RECORD_HEADER_LEN..]);
+        append_to_file(&user.path(), buffer).unwrap();
+
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
 
-        new_records.push(record);
+        // delete the file (user)
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
+
+        let records = user.unwrap().records();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].cypher.kdf, Kdf::Argon2id);
+        assert_eq!(records[1].secret().1, "password2");
+        assert_eq!(records[2].cypher.kdf, Kdf::Scrypt);
+        assert_eq!(records[2].secret().1, "password3");
+    }
+
+    #[test]
+    fn test_read_record_with_scrypt_cost() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
 
         let mut buffer = vec![];
-        for record in new_records.iter() {
-            record.cypher.write(&mut buffer);
-        }
+        CipherConfig::encrypt_data_with_kdf(
+            "example2.com password2",
+            "password",
+            Kdf::ScryptCost(12),
+            &file_aad(&user.path()),
+        )
+        .unwrap()
+        .write(&mut buffer);
+        append_to_file(&user.path(), buffer).unwrap();
 
-        write_to_file(&self.path(), buffer).unwrap();
-        self.0 = new_records;
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
 
-        Ok(())
+        // delete the file (user)
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
+
+        let records = user.unwrap().records();
+        assert_eq!(records[1].cypher.kdf, Kdf::ScryptCost(12));
+        assert_eq!(records[1].secret().1, "password2");
     }
 
-    fn path(&self) -> PathBuf {
-        self.1.clone()
+    #[test]
+    fn test_scrypt_cost() {
+        assert_eq!(Kdf::scrypt_with_cost(Some("12")), Kdf::ScryptCost(12));
+        assert_eq!(Kdf::scrypt_with_cost(Some("14")), Kdf::Scrypt);
+        assert_eq!(Kdf::scrypt_with_cost(Some("30")), Kdf::Scrypt);
+        assert_eq!(Kdf::scrypt_with_cost(Some("fast")), Kdf::Scrypt);
+        assert_eq!(Kdf::scrypt_with_cost(None), Kdf::Scrypt);
+        assert_eq!(
+            Kdf::from_id(Kdf::ScryptCost(20).id()),
+            Some(Kdf::ScryptCost(20))
+        );
+        assert_eq!(Kdf::from_id(KDF_ID_SCRYPT_COST | 30), None);
     }
 
-    fn last_offset(&self) -> u32 {
-        let mut offset = 0;
-        for record in self.0.iter() {
-            if record.offset > offset {
-                offset = record.offset;
-            }
+    #[test]
+    fn test_read_shared_salt_derives_key_once() {
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let user_data = RecordOperationConfig::new(
+            &generate_random_username(),
+            "password",
+            "example.com",
+            "password",
+            &path,
+        );
+        let mut user = User::new_with_shared_salt(&user_data, true).unwrap();
+        for i in 2..5 {
+            let domain = format!("example{}.com", i);
+            let config = RecordOperationConfig::new(
+                &user_data.username,
+                &user_data.master_pwd,
+                &domain,
+                "password",
+                &user_data.path,
+            );
+            user.add_record(config).unwrap();
         }
+        user.remove_record(RecordOperationConfig::new(
+            &user_data.username,
+            &user_data.master_pwd,
+            "example2.com",
+            "password",
+            &user_data.path,
+        ))
+        .unwrap();
 
-        offset
-    }
+        DERIVATIONS.with(|d| d.set(0));
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
+        let derivations = DERIVATIONS.with(|d| d.get());
 
-    fn first_record(&self) -> Record {
-        for record in self.0.iter() {
-            if record.offset == 0 {
-                return record.clone();
-            }
-        }
+        // delete the file (user)
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
 
-        panic!("No first record found");
+        let user = user.unwrap();
+        assert_eq!(derivations, 1);
+        assert_eq!(user.records().len(), 3);
+        assert_eq!(
+            user.domains(),
+            vec!["example.com", "example3.com", "example4.com"]
+        );
     }
 
-    fn domains(&self) -> Vec<String> {
-        let mut domains = vec![];
-        for record in self.0.iter() {
-            if record.domain.is_some() {
-                domains.push(record.domain.clone().unwrap());
-            }
-        }
+    #[test]
+    fn test_deferred_writes_flush() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        user.defer_writes();
+        let read = || User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
+
+        user_data.domain = "example2.com".to_string();
+        let added = user.add_record(user_data.clone());
+        user_data.domain = "example.com".to_string();
+        let removed = user.remove_record(user_data.clone());
+        let dirty = user.is_dirty();
+        let before_flush = read().map(|u| u.domains());
+        let flushed = user.flush();
+        let after_flush = read().map(|u| u.domains());
 
-        domains
-    }
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
 
-    fn check_integrity(&self, username: &str, master_pwd: &str, path: &PathBuf) -> bool {
-        let records = Record::read_user(path, username, master_pwd);
+        assert!(added.is_ok());
+        assert!(removed.is_ok());
+        assert!(dirty);
+        assert_eq!(before_flush.unwrap(), vec!["example.com"]);
+        assert!(flushed.is_ok());
+        assert!(!user.is_dirty());
+        assert_eq!(after_flush.unwrap(), vec!["example2.com"]);
+    }
 
-        match records {
-            Ok(r) => {
-                let first_record = r[0].clone();
+    #[test]
+    fn test_read_many_records_in_order() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
 
-                match first_record.cypher.decrypt_data() {
-                    Ok(_) => {}
-                    Err(_) => return false,
-                }
-            }
-            Err(_) => return false,
+        // 499 more records, encrypted with one key to keep the test fast
+        let derived_key = DerivedKey::derive_key("password", None, Kdf::Scrypt);
+        let aad = file_aad(&user.path());
+        let mut buffer = vec![];
+        for i in 0..499 {
+            let data = format!("example{}.org password{}", i, i);
+            CipherConfig::encrypt_data_with_key(&data, &derived_key, Kdf::Scrypt, &aad)
+                .unwrap()
+                .write(&mut buffer);
         }
+        append_to_file(&user.path(), buffer).unwrap();
 
-        true
-    }
+        let read = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
+
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
 
-    fn remove_records_from_file(&mut self) {
-        let path = self.path();
-        match clear_file_content(&path) {
-            Ok(_) => {}
-            Err(_) => panic!("Could not clear file content"),
+        let domains = read.unwrap().domains();
+        assert_eq!(domains.len(), 500);
+        assert_eq!(domains[0], "example.com");
+        for (i, domain) in domains[1..].iter().enumerate() {
+            assert_eq!(*domain, format!("example{}.org", i));
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_read_derives_key_once_per_salt() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
 
-    use dotenv::dotenv;
-    use rand::Rng;
-    use std::{env, fs};
+        // 99 more records sharing a single salt, in the per record format
+        let derived_key = DerivedKey::derive_key("password", None, Kdf::Scrypt);
+        let aad = file_aad(&user.path());
+        let mut buffer = vec![];
+        for i in 0..99 {
+            let data = format!("example{}.org password", i);
+            CipherConfig::encrypt_data_with_key(&data, &derived_key, Kdf::Scrypt, &aad)
+                .unwrap()
+                .write(&mut buffer);
+        }
+        append_to_file(&user.path(), buffer).unwrap();
 
-    fn random_number() -> u32 {
-        let mut rng = rand::thread_rng();
-        rng.gen_range(10000000..99999999)
-    }
+        DERIVATIONS.with(|d| d.set(0));
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
+        let derivations = DERIVATIONS.with(|d| d.get());
 
-    fn generate_random_username() -> String {
-        format!("keeper-crabby-{}", random_number())
+        // delete the file (user)
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
+
+        assert_eq!(user.unwrap().records().len(), 100);
+        // one each for the verifier, the first record and the shared salt instead of 101
+        assert_eq!(derivations, 3);
     }
 
-    fn setup_user_data(domain: &str) -> Result<RecordOperationConfig, String> {
-        dotenv().ok();
-        let username = generate_random_username();
-        let username = username.as_str().to_owned();
-        let master_pwd = "password";
-        let pwd = "password";
-        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
-        let user = RecordOperationConfig::new(username.as_str(), master_pwd, domain, pwd, &path);
-        match User::new(&user) {
-            Ok(_) => Ok(user.clone()),
-            Err(e) => Err(e),
+    #[test]
+    fn test_decrypt_records_keeps_file_order() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+
+        // 199 more records sharing a single salt
+        let derived_key = DerivedKey::derive_key("password", None, Kdf::Scrypt);
+        let aad = file_aad(&user.path());
+        let mut buffer = vec![];
+        for i in 0..199 {
+            let data = format!("example{}.org password{}", i, i);
+            CipherConfig::encrypt_data_with_key(&data, &derived_key, Kdf::Scrypt, &aad)
+                .unwrap()
+                .write(&mut buffer);
         }
-    }
+        append_to_file(&user.path(), buffer).unwrap();
 
-    fn create_user(config: &RecordOperationConfig) -> Result<User, String> {
-        User::from(&config.path, &config.username, &config.master_pwd)
-    }
+        let records = Record::read_user(
+            &user_data.path,
+            &user_data.username,
+            &user_data.master_pwd,
+            &mut KeyCache::default(),
+        );
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
 
-    #[test]
-    fn test_derive_key() {
-        let data = "kepper-crabby";
-        let derived_key = DerivedKey::derive_key(data, None);
-        let key = derived_key.key;
-        let salt = derived_key.salt;
-        assert_eq!(key.len(), 16);
-        assert_eq!(salt.len(), 22);
-    }
+        // delete the file (user)
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
 
-    #[test]
-    fn test_cipher_config() {
-        let data = "keeper-crabby";
-        let master_pwd = "password";
-        let cipher = CipherConfig::encrypt_data(data, master_pwd).unwrap();
-        let decrypted = cipher.decrypt_data().unwrap();
-        assert_eq!(decrypted, data);
+        let (_, records) = records.unwrap();
+        let sequential: Vec<RecordFields> = records
+            .iter()
+            .map(|r| unmarshal(&r.cypher.decrypt_data().unwrap()).unwrap())
+            .collect();
+        assert_eq!(sequential.len(), 200);
+        assert_eq!(
+            decrypt_records(&records)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.map(|(fields, _, _)| fields))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            sequential
+        );
+        assert_eq!(
+            user.unwrap()
+                .iter()
+                .map(|(domain, pwd)| (domain.to_string(), pwd.to_string(), vec![]))
+                .collect::<Vec<_>>(),
+            sequential
+        );
     }
 
     #[test]
-    fn test_create_user_success() {
+    fn test_record_moved_to_another_user_fail() {
         let user_data = setup_user_data("example.com").unwrap();
-        let user = create_user(&user_data);
+        let other_data = setup_user_data("example2.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+        let other = create_user(&other_data).unwrap();
 
-        // delete the file (user)
-        let hashed_username = hash(user_data.username.to_string());
-        let file_path = user_data.path.join(hashed_username.as_str());
-        fs::remove_file(file_path).unwrap();
+        // both users have the same master password
+        let mut blob = vec![];
+        user.first_record().cypher.write(&mut blob);
+        append_to_file(&other.path(), blob).unwrap();
+
+        let other = User::from(
+            &other_data.path,
+            &other_data.username,
+            &other_data.master_pwd,
+        );
 
-        assert_eq!(user.is_ok(), true);
+        // delete the files (users)
+        fs::remove_file(user.path()).unwrap();
+        fs::remove_file(user_data.path.join(hash(other_data.username.clone()))).unwrap();
+
+        assert!(other.is_err());
     }
 
     #[test]
-    fn test_create_user_fail_already_exists() {
-        // setup_user_data function not used here because we want to test
-        // the case where the user already exists thus we need to try to create
-        // a user with the same username twice (setup_user_data creates a new user each time
-        // with a unique username)
+    fn test_unmarshal() {
+        assert_eq!(
+            unmarshal("example.com password").unwrap(),
+            ("example.com".to_string(), "password".to_string(), vec![])
+        );
+        assert_eq!(
+            unmarshal("example.com ").unwrap(),
+            ("example.com".to_string(), "".to_string(), vec![])
+        );
+        assert_eq!(
+            unmarshal("example.com").unwrap(),
+            ("example.com".to_string(), "".to_string(), vec![])
+        );
+        assert_eq!(
+            unmarshal("example.com password work,finance").unwrap(),
+            (
+                "example.com".to_string(),
+                "password".to_string(),
+                vec!["work".to_string(), "finance".to_string()]
+            )
+        );
+        assert!(unmarshal("").is_err());
+        assert!(unmarshal(" password").is_err());
+        assert!(unmarshal("a b c d").is_err());
+        assert!(unmarshal(r#"{"domain":"","pwd":"password"}"#).is_err());
+        // not JSON of a record, read as a legacy record
+        assert_eq!(
+            unmarshal("{example.com} password").unwrap(),
+            ("{example.com}".to_string(), "password".to_string(), vec![])
+        );
+    }
 
-        dotenv().ok();
-        let username = generate_random_username();
-        let username = username.as_str();
-        let master_pwd = "password";
-        let domain = "example.com";
-        let pwd = "password";
-        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
-        let config = RecordOperationConfig::new(username, master_pwd, domain, pwd, &path);
-        let _ = User::new(&config);
+    #[test]
+    fn test_marshal_spaces_round_trip() {
+        for pwd in ["pass word", " pass  word ", "a b c d", " "] {
+            let tags = vec!["work".to_string()];
+            assert_eq!(
+                unmarshal(&marshal("example.com", "", pwd, &[], None)).unwrap(),
+                ("example.com".to_string(), pwd.to_string(), vec![])
+            );
+            assert_eq!(
+                unmarshal(&marshal("example.com", "", pwd, &tags, None)).unwrap(),
+                ("example.com".to_string(), pwd.to_string(), tags)
+            );
+        }
+    }
 
-        let config = RecordOperationConfig::new(username, master_pwd, domain, pwd, &path);
-        let res = User::new(&config);
+    #[test]
+    fn test_add_record_password_with_spaces() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example2.com".to_string();
+        user_data.pwd = "correct horse".to_string();
+        user.add_record(user_data.clone()).unwrap();
+        user_data.domain = "example3.com".to_string();
+        user_data.pwd = "correct horse battery  staple".to_string();
+        user_data.tags = vec!["work".to_string()];
+        user.add_record(user_data.clone()).unwrap();
+
+        let loaded = create_user(&user_data).unwrap();
 
         // delete the file (user)
-        let hashed_username = hash(username.to_string());
-        let file_path = path.join(hashed_username.as_str());
-        fs::remove_file(file_path).unwrap();
+        fs::remove_file(user.path()).unwrap();
 
-        assert_eq!(res.is_err(), true);
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            vec![
+                ("example.com", "password"),
+                ("example2.com", "correct horse"),
+                ("example3.com", "correct horse battery  staple"),
+            ]
+        );
+        assert_eq!(loaded.records()[2].tags, vec!["work".to_string()]);
+        assert_eq!(loaded.unreadable(), 0);
     }
 
     #[test]
-    fn test_integrity_success() {
-        dotenv().ok();
+    fn test_unreadable_record_is_kept() {
         let user_data = setup_user_data("example.com").unwrap();
         let user = create_user(&user_data).unwrap();
+        // written by an old version for a password with spaces
+        let mut buffer = vec![];
+        user.encrypt(
+            "legacy.example pass word tag",
+            &user_data.master_pwd,
+            &mut KeyCache::default(),
+        )
+        .unwrap()
+        .write(&mut buffer);
+        append_to_file(&user.path(), buffer).unwrap();
 
-        let integrity =
-            user.check_integrity(&user_data.username, &user_data.master_pwd, &user_data.path);
+        let mut loaded = create_user(&user_data).unwrap();
+        let mut config = user_data.clone();
+        config.domain = "example2.com".to_string();
+        let added = loaded.add_record(config.clone());
+        let removed = loaded.remove_record(config);
+        let reloaded = create_user(&user_data).unwrap();
 
         // delete the file (user)
         fs::remove_file(user.path()).unwrap();
 
-        assert_eq!(integrity, true);
+        assert!(added.is_ok());
+        assert!(removed.is_ok());
+        assert_eq!(loaded.unreadable(), 1);
+        assert_eq!(
+            reloaded.iter().collect::<Vec<_>>(),
+            vec![("example.com", "password")]
+        );
+        assert_eq!(reloaded.unreadable(), 1);
     }
 
     #[test]
-    fn test_integrity_fail() {
+    fn test_single_token_record() {
         let user_data = setup_user_data("example.com").unwrap();
         let user = create_user(&user_data).unwrap();
+        let mut buffer = vec![];
+        user.encrypt(
+            "legacy.example",
+            &user_data.master_pwd,
+            &mut KeyCache::default(),
+        )
+        .unwrap()
+        .write(&mut buffer);
+        append_to_file(&user.path(), buffer).unwrap();
 
-        let integrity = user.check_integrity(&user_data.username, "wrong_pwd", &user_data.path);
+        let loaded = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
 
         // delete the file (user)
         fs::remove_file(user.path()).unwrap();
 
-        assert_eq!(integrity, false);
+        assert_eq!(
+            loaded.unwrap().iter().collect::<Vec<_>>(),
+            vec![("example.com", "password"), ("legacy.example", "")]
+        );
     }
 
     #[test]
-    fn test_read_record_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let user = create_user(&user_data).unwrap();
+    fn test_marshal_tags_round_trip() {
+        let tags = vec!["work".to_string(), "finance".to_string()];
+        let plaintext = marshal("example.com", "", "password", &tags, None);
 
-        let records = user.records();
-        let first_record = user.first_record();
-        let (domain, pwd) = first_record.secret();
+        assert_eq!(
+            plaintext.as_str(),
+            r#"{"domain":"example.com","pwd":"password","tags":["work","finance"]}"#
+        );
+        assert_eq!(
+            unmarshal(&plaintext).unwrap(),
+            ("example.com".to_string(), "password".to_string(), tags)
+        );
+        assert_eq!(
+            marshal("example.com", "", "password", &[], None).as_str(),
+            r#"{"domain":"example.com","pwd":"password"}"#
+        );
+        assert_eq!(
+            unmarshal_record(&marshal("example.com", "", "password", &[], Some(60))).unwrap(),
+            (
+                ("example.com".to_string(), "password".to_string(), vec![]),
+                String::new(),
+                Some(60)
+            )
+        );
+        let plaintext = marshal("bank.com", "pin", "1234", &[], None);
+        assert_eq!(
+            plaintext.as_str(),
+            r#"{"domain":"bank.com","label":"pin","pwd":"1234"}"#
+        );
+        assert_eq!(
+            unmarshal_record(&plaintext).unwrap(),
+            (
+                ("bank.com".to_string(), "1234".to_string(), vec![]),
+                "pin".to_string(),
+                None
+            )
+        );
+        assert_eq!(
+            unmarshal(&marshal("example.com", "", "", &["work".to_string()], None)).unwrap(),
+            (
+                "example.com".to_string(),
+                "".to_string(),
+                vec!["work".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_records_with_tag() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example2.com".to_string();
+        user_data.tags = vec!["work".to_string(), "finance".to_string()];
+        user.add_record(user_data.clone()).unwrap();
+        user_data.domain = "example3.com".to_string();
+        user_data.tags = vec!["work".to_string()];
+        user.add_record(user_data.clone()).unwrap();
+
+        let read = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
 
         // delete the file (user)
         fs::remove_file(user.path()).unwrap();
 
-        assert_eq!(records.len(), 1);
-        assert_eq!(domain, "example.com");
-        assert_eq!(pwd, "password");
+        let read = read.unwrap();
+        let domains = |records: Vec<Record>| {
+            records
+                .iter()
+                .map(|r| r.secret().0)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            domains(read.records_with_tag("work")),
+            vec!["example2.com", "example3.com"]
+        );
+        assert_eq!(
+            domains(read.records_with_tag("finance")),
+            vec!["example2.com"]
+        );
+        assert!(read.records_with_tag("personal").is_empty());
+        assert_eq!(read.tags(), vec!["finance", "work"]);
+        assert!(read.records()[0].tags().is_empty());
     }
 
     #[test]
-    #[should_panic]
-    fn test_read_record_fail() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let try_user = User::from(&user_data.path, &user_data.username, "wrong_pwd");
-
-        // delete the file (user)
-        let hashed_username = hash(user_data.username);
-        let file_path = user_data.path.join(hashed_username.as_str());
-        fs::remove_file(file_path).unwrap();
+    fn test_read_from_bytes_fail_truncated() {
+        let mut keys = KeyCache::default();
+        for len in [0, 10, 37] {
+            let legacy = vec![0u8; len];
+            let mut versioned = vec![0u8; len];
+            if let Some(first) = versioned.first_mut() {
+                *first = RECORD_VERSION;
+            }
+            if let Some(kdf) = versioned.get_mut(1) {
+                *kdf = Kdf::Scrypt.id();
+            }
 
-        // this should panic
-        let _ = try_user.unwrap();
+            for bytes in [legacy, versioned] {
+                assert!(Record::read_from_bytes(bytes, "password", 0, &mut keys, &[]).is_err());
+            }
+        }
     }
 
     #[test]
-    fn test_add_record_success() {
-        let user_data = setup_user_data("example.com").unwrap();
-        let mut user = create_user(&user_data).unwrap();
+    fn test_read_from_bytes_fail_ciphertext_len() {
+        // a full record header claiming more ciphertext than there is
+        let mut bytes = vec![0u8; 38];
+        bytes[34..38].copy_from_slice(&u32::MAX.to_be_bytes());
+        bytes.extend_from_slice(&[0u8; 16]);
 
-        let new_domain = "example2.com";
-        let new_pwd = "password2";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            new_domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let res = user.add_record(add_record);
+        let read = Record::read_from_bytes(bytes, "password", 0, &mut KeyCache::default(), &[]);
 
-        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+        assert!(read.is_err());
+    }
+
+    // a decrypted record that is never written, for the presentation helpers
+    fn plain_record(domain: &str, pwd: &str) -> Record {
+        let cypher = CipherConfig::new(
+            Kdf::Scrypt,
+            Key::<Aes128GcmSiv>::default(),
+            vec![],
+            GenericArray::default(),
+            vec![],
+            vec![],
+        );
+        Record::new(
+            cypher,
+            0,
+            Some(domain.to_string()),
+            Some(pwd.to_string()),
+            vec![],
+        )
+    }
 
-        let records = user.records();
-        let inserted_record = records
+    #[test]
+    fn test_read_only_records_sorted_by_domain() {
+        let records = ReadOnlyRecords(vec![
+            plain_record("b.com", "first"),
+            plain_record("a.com", "pwd"),
+            plain_record("b.com", "second"),
+            plain_record("c.com", "pwd"),
+        ]);
+
+        let sorted: Vec<(String, String)> = records
+            .sorted_by_domain()
+            .records()
             .iter()
-            .find(|r| r.domain == Some(new_domain.to_string()));
+            .map(|r| r.secret())
+            .collect();
 
-        // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        assert_eq!(
+            sorted,
+            vec![
+                ("a.com".to_string(), "pwd".to_string()),
+                ("b.com".to_string(), "first".to_string()),
+                ("b.com".to_string(), "second".to_string()),
+                ("c.com".to_string(), "pwd".to_string()),
+            ]
+        );
+        // the original order is kept
+        assert_eq!(records.records()[0].secret().1, "first");
+    }
+
+    #[test]
+    fn test_read_only_records_filter_no_match() {
+        let records = ReadOnlyRecords(vec![
+            plain_record("a.com", "pwd"),
+            plain_record("b.com", "pwd"),
+        ]);
+
+        let filtered = records.filter(|r| r.secret().0.ends_with(".org"));
 
-        assert_eq!(res.is_ok(), true);
-        assert_eq!(inserted_record.is_some(), true);
+        assert!(filtered.is_empty());
+        assert_eq!(filtered.len(), 0);
         assert_eq!(records.len(), 2);
-        assert_eq!(records[1].domain, Some(new_domain.to_string()));
-        assert_eq!(records[1].pwd, Some(new_pwd.to_string()));
     }
 
     #[test]
-    fn test_add_record_fail() {
-        let user_data = setup_user_data("example.com").unwrap();
+    fn test_read_record_empty_pwd_success() {
+        let mut user_data = setup_user_data("example.com").unwrap();
         let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example2.com".to_string();
+        user_data.pwd = "".to_string();
+        user.add_record(user_data.clone()).unwrap();
 
-        let new_domain = "example2.com";
-        let new_pwd = "password2";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            "wrong_pwd",
-            new_domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let res = user.add_record(add_record);
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
 
         // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
 
-        assert_eq!(user.records().len(), 1);
-        assert_eq!(res.is_err(), true);
+        let records = user.unwrap().records();
+        assert_eq!(
+            records[1].secret(),
+            ("example2.com".to_string(), "".to_string())
+        );
     }
 
     #[test]
-    fn test_add_record_fail_already_exists() {
+    fn test_read_duplicate_domain_keeps_last() {
         let user_data = setup_user_data("example.com").unwrap();
-        let mut user = create_user(&user_data).unwrap();
+        let user = create_user(&user_data).unwrap();
 
-        let new_domain = "example.com";
-        let new_pwd = "password2";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            new_domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let res = user.add_record(add_record);
+        let mut buffer = vec![];
+        CipherConfig::encrypt_data("example.com password2", "password", &file_aad(&user.path()))
+            .unwrap()
+            .write(&mut buffer);
+        append_to_file(&user.path(), buffer).unwrap();
+
+        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
 
         // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
 
-        assert_eq!(user.records().len(), 1);
-        assert_eq!(res.is_err(), true);
+        let records = user.unwrap().records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].secret(),
+            ("example.com".to_string(), "password2".to_string())
+        );
     }
 
     #[test]
-    fn test_remove_record_success() {
-        let user_data = setup_user_data("example.com").unwrap();
+    fn test_reused_passwords() {
+        let mut user_data = setup_user_data("example.com").unwrap();
         let mut user = create_user(&user_data).unwrap();
+        for (domain, pwd) in [("example2.com", "password2"), ("example3.com", "password")] {
+            user_data.domain = domain.to_string();
+            user_data.pwd = pwd.to_string();
+            user.add_record(user_data.clone()).unwrap();
+        }
 
-        let new_domain = "example2.com";
-        let new_pwd = "password2";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            new_domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let _ = user.add_record(add_record);
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
 
-        let new_domain = "example3.com";
-        let new_pwd = "password3";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            new_domain,
-            new_pwd,
-            &user_data.path,
+        assert_eq!(
+            user.reused_passwords(),
+            vec![(
+                "password".to_string(),
+                vec!["example.com".to_string(), "example3.com".to_string()]
+            )]
         );
-        let _ = user.add_record(add_record);
+    }
 
-        let remove_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            "example2.com",
-            "",
-            &user_data.path,
-        );
-        let res = user.remove_record(remove_record);
+    #[test]
+    fn test_stale_records() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let user = create_user(&user_data).unwrap();
+        let mut buffer = vec![];
+        let mut keys = KeyCache::default();
+        for data in [
+            marshal("old.example", "", "password", &[], Some(0)),
+            Zeroizing::new("legacy.example password".to_string()),
+        ] {
+            user.encrypt(&data, &user_data.master_pwd, &mut keys)
+                .unwrap()
+                .write(&mut buffer);
+        }
+        append_to_file(&user.path(), buffer).unwrap();
+        let mut loaded =
+            User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+        let fresh = loaded.stale_records(Duration::from_secs(3600));
+        // new tags keep the time the password was set
+        user_data.domain = "old.example".to_string();
+        user_data.tags = vec!["work".to_string()];
+        let retagged = loaded.modify_record(user_data.clone());
+        let after_retag = loaded.stale_records(Duration::from_secs(3600));
+        user_data.pwd = "password2".to_string();
+        let changed = loaded.modify_record(user_data.clone());
 
-        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
 
-        let records = user.records();
-        let domains = user.domains();
+        assert_eq!(fresh, vec!["old.example".to_string()]);
+        assert!(retagged.is_ok());
+        assert_eq!(after_retag, vec!["old.example".to_string()]);
+        assert!(changed.is_ok());
+        assert!(loaded.stale_records(Duration::from_secs(3600)).is_empty());
+    }
 
-        let file_length = fs::read(user.path()).unwrap().len();
-        let records_len = records.iter().fold(0, |acc, r| acc + r.cypher.len());
+    #[test]
+    fn test_iter() {
+        let mut user_data = setup_user_data("example.com").unwrap();
+        let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example2.com".to_string();
+        user_data.pwd = "password2".to_string();
+        user.add_record(user_data.clone()).unwrap();
 
         // delete the file (user)
         fs::remove_file(user.path()).unwrap();
 
-        assert_eq!(res.is_ok(), true);
-        assert_eq!(records.len(), 2);
-        assert_eq!(
-            domains
-                .iter()
-                .find(|d| d.as_str() == "example2.com")
-                .is_none(),
-            true
-        );
-        assert_eq!(
-            domains
-                .iter()
-                .find(|d| d.as_str() == "example3.com")
-                .is_some(),
-            true
-        );
         assert_eq!(
-            domains
-                .iter()
-                .find(|d| d.as_str() == "example.com")
-                .is_some(),
-            true
+            user.iter().collect::<Vec<_>>(),
+            vec![("example.com", "password"), ("example2.com", "password2")]
         );
-        assert_eq!(file_length, records_len);
     }
 
     #[test]
-    fn test_remove_record_read_user_success() {
-        let user_data = setup_user_data("example.com").unwrap();
+    fn test_change_master_password_success() {
+        let mut user_data = setup_user_data("example.com").unwrap();
         let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example2.com".to_string();
+        user.add_record(user_data.clone()).unwrap();
 
-        let new_domain = "example2.com";
-        let new_pwd = "password2";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            new_domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let _ = user.add_record(add_record);
-
-        let new_domain = "example3.com";
-        let new_pwd = "password3";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            new_domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let _ = user.add_record(add_record);
-
-        let remove_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            "example2.com",
-            "",
-            &user_data.path,
-        );
-        let res = user.remove_record(remove_record);
-
-        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
-        let records = user.records();
+        let res = user.change_master_password(&user_data.master_pwd, "new-password");
+        let old = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
+        let new = User::from(&user_data.path, &user_data.username, "new-password");
 
         // delete the file (user)
         fs::remove_file(user.path()).unwrap();
 
-        assert_eq!(res.is_ok(), true);
-        assert_eq!(records.len(), 2);
+        assert!(res.is_ok());
+        assert!(old.is_err());
+        assert_eq!(new.unwrap().records(), user.records());
+        assert_eq!(user.domains(), vec!["example.com", "example2.com"]);
     }
 
     #[test]
-    fn test_remove_record_fail_not_found() {
+    fn test_change_master_password_fail_wrong_password() {
         let user_data = setup_user_data("example.com").unwrap();
         let mut user = create_user(&user_data).unwrap();
 
-        let new_domain = "example2.com";
-        let new_pwd = "password2";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            new_domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let _ = user.add_record(add_record);
-
-        let remove_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            "example3.com",
-            "",
-            &user_data.path,
-        );
-        let res = user.remove_record(remove_record);
+        let res = user.change_master_password("wrong_pwd", "new-password");
+        let read = User::from(&user_data.path, &user_data.username, &user_data.master_pwd);
 
         // delete the file (user)
         fs::remove_file(user.path()).unwrap();
 
-        assert_eq!(res.is_err(), true);
+        assert_eq!(res, Err("Wrong master password".to_string()));
+        assert!(read.is_ok());
     }
 
     #[test]
-    fn test_remove_record_fail_integrity_check() {
-        let user_data = setup_user_data("example.com").unwrap();
+    fn test_weak_passwords() {
+        let mut user_data = setup_user_data("example.com").unwrap();
         let mut user = create_user(&user_data).unwrap();
+        user_data.domain = "example2.com".to_string();
+        user_data.pwd = "Correct-Horse-9".to_string();
+        user.add_record(user_data.clone()).unwrap();
 
-        let new_domain = "example2.com";
-        let new_pwd = "password2";
-        let add_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            new_domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let _ = user.add_record(add_record);
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
 
-        let remove_record = RecordOperationConfig::new(
-            &user_data.username,
-            "wrong_pwd",
-            "example2.com",
-            "",
+        assert_eq!(user.weak_passwords(), vec!["example.com".to_string()]);
+    }
+
+    #[test]
+    fn test_username_case_insensitive() {
+        let user_data = setup_user_data("example.com").unwrap();
+        let upper = format!(" {} ", user_data.username.to_uppercase());
+
+        let exists = crate::crypto::check_user(&upper, &user_data.path);
+        let user = User::from(&user_data.path, &upper, &user_data.master_pwd);
+        let created_again = User::new(&RecordOperationConfig::new(
+            &upper,
+            "password",
+            "example.com",
+            "password",
             &user_data.path,
-        );
-        let res = user.remove_record(remove_record);
+        ));
 
         // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
 
-        assert_eq!(res.is_err(), true);
+        assert!(exists);
+        assert!(user.is_ok());
+        assert!(created_again.is_err());
     }
 
     #[test]
-    pub fn test_modify_record_success() {
+    fn test_read_wrong_master_pwd_fails_fast() {
         let user_data = setup_user_data("example.com").unwrap();
         let mut user = create_user(&user_data).unwrap();
+        let mut config = user_data.clone();
+        config.domain = "example2.com".to_string();
+        user.add_record(config).unwrap();
 
-        let new_pwd = "password2";
-        let modify_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            &user_data.domain,
-            new_pwd,
-            &user_data.path,
-        );
-        let res = user.modify_record(modify_record);
+        DERIVATIONS.with(|d| d.set(0));
+        let user = User::from(&user_data.path, &user_data.username, "wrong_pwd");
+        let derivations = DERIVATIONS.with(|d| d.get());
 
-        let user = User::from(&user_data.path, &user_data.username, &user_data.master_pwd).unwrap();
-        let records = user.records();
-        let modified_record = records
-            .iter()
-            .find(|r| r.domain == Some(user_data.domain.to_string()));
+        // delete the file (user)
+        fs::remove_file(user_data.path.join(hash(user_data.username.clone()))).unwrap();
+
+        assert_eq!(user, Err("Wrong master password".to_string()));
+        assert_eq!(derivations, 1);
+    }
+
+    #[test]
+    fn test_read_headerless_file_success() {
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let username = generate_random_username();
+        let file_path = create_file(&path, &hash(username.clone())).unwrap();
+
+        let mut buffer = vec![];
+        CipherConfig::encrypt_data("example.com password", "password", &file_aad(&file_path))
+            .unwrap()
+            .write(&mut buffer);
+        write_to_file(&file_path, buffer).unwrap();
+
+        let user = User::from(&path, &username, "password");
 
         // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        fs::remove_file(file_path).unwrap();
 
-        assert_eq!(res.is_ok(), true);
-        assert_eq!(modified_record.is_some(), true);
-        assert_eq!(modified_record.unwrap().pwd, Some(new_pwd.to_string()));
-        assert_eq!(records.len(), 1);
+        let user = user.unwrap();
+        assert_eq!(user.header, None);
+        assert_eq!(user.domains(), vec!["example.com"]);
     }
 
     #[test]
-    pub fn test_modify_integrity_fail() {
-        let user_data = setup_user_data("example.com").unwrap();
+    fn test_add_record_to_emptied_vault() {
+        let mut user_data = setup_user_data("example.com").unwrap();
         let mut user = create_user(&user_data).unwrap();
+        let removed = user.remove_record(user_data.clone());
+        let empty = create_user(&user_data).unwrap();
+        let checked = user.check_master_pwd(&user_data.master_pwd);
+        let wrong = user.check_master_pwd("wrong");
+        user_data.domain = "example2.com".to_string();
+        let added = user.add_record(user_data.clone());
+        let loaded = create_user(&user_data).unwrap();
 
-        let new_pwd = "password2";
-        let modify_record = RecordOperationConfig::new(
-            &user_data.username,
-            "wrong_pwd",
-            &user_data.domain,
-            new_pwd,
-            &user_data.path,
+        // delete the file (user)
+        fs::remove_file(user.path()).unwrap();
+
+        assert!(removed.is_ok());
+        assert_eq!(empty.record_count(), 0);
+        assert!(checked);
+        assert!(!wrong);
+        assert!(added.is_ok());
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            vec![("example2.com", "password")]
         );
-        let res = user.modify_record(modify_record);
+    }
+
+    #[test]
+    fn test_add_record_to_emptied_headerless_vault() {
+        dotenv().ok();
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let username = generate_random_username();
+        let file_path = create_file(&path, &hash(username.clone())).unwrap();
+        let mut buffer = vec![];
+        CipherConfig::encrypt_data("example.com password", "password", &file_aad(&file_path))
+            .unwrap()
+            .write(&mut buffer);
+        write_to_file(&file_path, buffer).unwrap();
+
+        let mut user = User::from(&path, &username, "password").unwrap();
+        let mut config =
+            RecordOperationConfig::new(&username, "password", "example.com", "password", &path);
+        let removed = user.remove_record(config.clone());
+        config.domain = "example2.com".to_string();
+        let wrong = user.add_record(RecordOperationConfig {
+            master_pwd: "wrong".to_string(),
+            ..config.clone()
+        });
+        let added = user.add_record(config);
+        let loaded = User::from(&path, &username, "password");
 
         // delete the file (user)
-        fs::remove_file(user.path()).unwrap();
+        fs::remove_file(file_path).unwrap();
 
-        assert_eq!(res.is_err(), true);
+        assert!(removed.is_ok());
+        assert!(user.header.as_ref().unwrap().verifier.is_some());
+        assert!(wrong.is_err());
+        assert!(added.is_ok());
+        assert_eq!(loaded.unwrap().domains(), vec!["example2.com"]);
     }
 
     #[test]
-    pub fn test_modify_record_fail_not_found() {
-        let user_data = setup_user_data("example.com").unwrap();
+    fn test_check_master_pwd_derives_one_key() {
+        let mut user_data = setup_user_data("example.com").unwrap();
         let mut user = create_user(&user_data).unwrap();
+        for domain in ["example2.com", "example3.com"] {
+            user_data.domain = domain.to_string();
+            user.add_record(user_data.clone()).unwrap();
+        }
 
-        let new_pwd = "password2";
-        let modify_record = RecordOperationConfig::new(
-            &user_data.username,
-            &user_data.master_pwd,
-            "example2.com",
-            new_pwd,
-            &user_data.path,
-        );
-        let res = user.modify_record(modify_record);
+        DERIVATIONS.with(|d| d.set(0));
+        let checked = user.check_master_pwd(&user_data.master_pwd);
+        let derivations = DERIVATIONS.with(|d| d.get());
+        let wrong = user.check_master_pwd("wrong");
 
         // delete the file (user)
         fs::remove_file(user.path()).unwrap();
 
-        assert_eq!(res.is_err(), true);
+        assert!(checked);
+        assert!(!wrong);
+        assert_eq!(derivations, 1);
     }
 }
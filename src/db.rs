@@ -1,5 +1,7 @@
 use directories::ProjectDirs;
+use rand::RngCore;
 use std::{
+    env,
     fs::OpenOptions,
     fs::{self, File},
     io::{self, Write},
@@ -9,11 +11,8 @@ use std::{
 const DB_DIR: &str = "keeper-crabby";
 
 fn create_parent_dir(p: &Path) -> io::Result<()> {
-    match p.parent() {
-        Some(parent) => {
-            fs::create_dir_all(parent)?;
-        }
-        None => {}
+    if let Some(parent) = p.parent() {
+        fs::create_dir_all(parent)?;
     }
     Ok(())
 }
@@ -26,29 +25,128 @@ fn create_if_not_exists(p: &Path) -> io::Result<()> {
     Ok(())
 }
 
-pub fn init() -> Result<PathBuf, io::Error> {
-    if let Some(proj_dirs) = ProjectDirs::from("", "", DB_DIR) {
-        let proj_dirs = proj_dirs.data_dir();
-        if !proj_dirs.is_dir() {
-            let res = create_if_not_exists(proj_dirs);
-            assert!(res.is_ok());
+// fails if no file can be created in the directory
+fn check_writable(p: &Path) -> io::Result<()> {
+    let probe = p.join(".krab-write-test");
+    File::create(&probe)?;
+    fs::remove_file(probe)
+}
+
+/// Data directory given with `--data-dir` or `KRAB_DATA_DIR`, used as it is
+///
+/// The path has to be absolute, it is created if it does not exist.
+fn init_data_dir(p: &Path) -> Result<PathBuf, io::Error> {
+    if !p.is_absolute() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The data directory must be an absolute path",
+        ));
+    }
+    create_if_not_exists(p)?;
+    check_writable(p)?;
+    Ok(p.to_path_buf())
+}
+
+/// What the data directory is set with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataDirSource {
+    Flag,
+    Env,
+    Platform,
+}
+
+impl DataDirSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataDirSource::Flag => "--data-dir",
+            DataDirSource::Env => "KRAB_DATA_DIR",
+            DataDirSource::Platform => "platform data directory",
         }
-        Ok(proj_dirs.to_path_buf())
-    } else {
-        panic!("Could not get project directories");
     }
 }
 
-pub fn create_file(p: &PathBuf, file_name: &str) -> io::Result<PathBuf> {
+/// The directory the users data is stored in and what set it, nothing is created
+///
+/// `data_dir` (from `--data-dir`) wins over `KRAB_DATA_DIR`, without either the
+/// platform data directory is used.
+///
+/// # Arguments
+///
+/// * `data_dir` - Absolute path of the data directory, if given on the command line
+///
+/// # Returns
+/// * `Option<(PathBuf, DataDirSource)>` - The data directory, `None` if there is
+///   no platform data directory
+pub fn locate(data_dir: Option<&Path>) -> Option<(PathBuf, DataDirSource)> {
+    if let Some(data_dir) = data_dir {
+        return Some((data_dir.to_path_buf(), DataDirSource::Flag));
+    }
+    if let Some(data_dir) = env::var_os("KRAB_DATA_DIR") {
+        return Some((PathBuf::from(data_dir), DataDirSource::Env));
+    }
+    ProjectDirs::from("", "", DB_DIR)
+        .map(|proj_dirs| (proj_dirs.data_dir().to_path_buf(), DataDirSource::Platform))
+}
+
+/// Find (and create) the directory the users data is stored in, see `locate`
+///
+/// # Arguments
+///
+/// * `data_dir` - Absolute path of the data directory, if given on the command line
+///
+/// # Returns
+/// * `Result<PathBuf, io::Error>` - The data directory or an error
+pub fn init(data_dir: Option<&Path>) -> Result<PathBuf, io::Error> {
+    match locate(data_dir) {
+        Some((proj_dirs, DataDirSource::Platform)) => {
+            if !proj_dirs.is_dir() {
+                create_if_not_exists(&proj_dirs)?;
+            }
+            Ok(proj_dirs)
+        }
+        Some((data_dir, _)) => init_data_dir(&data_dir),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not get project directories",
+        )),
+    }
+}
+
+/// Whether a file can be created in a directory
+pub fn is_writable_dir(p: &Path) -> bool {
+    p.is_dir() && check_writable(p).is_ok()
+}
+
+/// Whether a file can not be written, because of its permissions or those of
+/// its directory or because the file system is mounted read-only
+///
+/// A file that does not exist is not read-only.
+pub fn is_read_only(p: &Path) -> bool {
+    let file = OpenOptions::new().append(true).open(p);
+    let dir = p.parent().map_or(Ok(()), check_writable);
+    [file.map(|_| ()), dir]
+        .iter()
+        .any(|res| res.as_ref().is_err_and(is_read_only_error))
+}
+
+/// Whether an error is caused by missing write permissions or a read-only file system
+pub fn is_read_only_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+pub fn create_file(p: &Path, file_name: &str) -> io::Result<PathBuf> {
     let file_path = p.join(file_name);
     if !file_path.exists() {
         File::create(file_path.as_path())?;
-        return Ok(file_path);
+        Ok(file_path)
     } else {
-        return Err(io::Error::new(
+        Err(io::Error::new(
             io::ErrorKind::AlreadyExists,
             "File already exists",
-        ));
+        ))
     }
 }
 
@@ -75,6 +173,54 @@ pub fn write_to_file(p: &PathBuf, data: Vec<u8>) -> io::Result<()> {
     Ok(())
 }
 
+/// Replace the content of a file at once
+///
+/// The data is written to a temporary file next to it first, so a crash leaves
+/// either the old or the new content. The temporary file is created with mode
+/// 0o600 on unix, so the replaced file is only readable by its owner.
+pub fn replace_file(p: &PathBuf, data: Vec<u8>) -> io::Result<()> {
+    if !p.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "File does not exist",
+        ));
+    }
+    let tmp = p.with_extension("tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut f = options.open(&tmp)?;
+    // a temporary file left by a crash keeps its mode when it is opened again
+    #[cfg(unix)]
+    f.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    f.write_all(&data)?;
+    f.sync_all()?;
+    fs::rename(&tmp, p)
+}
+
+/// Write data only the owner can read, like exported passwords
+///
+/// The file is created with mode 0o600 on unix. An existing file is an
+/// `AlreadyExists` error unless `overwrite` is set, then it is truncated and its
+/// mode is set to 0o600 as well.
+pub fn write_private_file(p: &Path, data: &[u8], overwrite: bool) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut f = options.open(p)?;
+    #[cfg(unix)]
+    f.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    f.write_all(data)?;
+    Ok(())
+}
+
 pub fn append_to_file(p: &PathBuf, data: Vec<u8>) -> io::Result<()> {
     if !p.exists() {
         return Err(io::Error::new(
@@ -86,3 +232,347 @@ pub fn append_to_file(p: &PathBuf, data: Vec<u8>) -> io::Result<()> {
     f.write_all(&data)?;
     Ok(())
 }
+
+/// Overwrite a file with random data before removing it
+///
+/// This is best-effort: on SSDs (wear-leveling) and copy-on-write or journaling
+/// file systems the old blocks may still be recoverable.
+///
+/// # Arguments
+///
+/// * `p` - Path of the file
+/// * `passes` - How many times the content is overwritten
+///
+/// # Returns
+/// * `io::Result<()>` - Nothing or an error
+pub fn secure_remove_file(p: &Path, passes: u32) -> io::Result<()> {
+    let len = fs::metadata(p)?.len() as usize;
+    let mut f = OpenOptions::new().write(true).open(p)?;
+    let mut data = vec![0u8; len];
+    for _ in 0..passes {
+        rand::thread_rng().fill_bytes(&mut data);
+        io::Seek::rewind(&mut f)?;
+        f.write_all(&data)?;
+        f.sync_all()?;
+    }
+    drop(f);
+    fs::remove_file(p)
+}
+
+// user files are named after the sha256 hash of the username
+fn is_user_file(p: &Path) -> bool {
+    p.is_file() && is_user_file_name(p)
+}
+
+fn is_user_file_name(p: &Path) -> bool {
+    p.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.len() == 64 && n.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Number of user files in the data directory, 0 if it can not be read
+pub fn count_user_files(data_dir: &Path) -> usize {
+    match fs::read_dir(data_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| is_user_file(&entry.path()))
+            .count(),
+        Err(_) => 0,
+    }
+}
+
+/// Archive every user file in the data directory into a single tar file
+///
+/// The files are copied as they are (still encrypted), no master password is needed.
+///
+/// # Arguments
+///
+/// * `data_dir` - Path to the directory where the users data is stored
+/// * `out` - Path of the archive to create
+///
+/// # Returns
+/// * `io::Result<()>` - Nothing or an error
+pub fn backup(data_dir: &Path, out: &Path) -> io::Result<()> {
+    let mut archive = tar::Builder::new(File::create(out)?);
+    for entry in fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        if is_user_file(&path) {
+            archive.append_path_with_name(&path, path.file_name().unwrap())?;
+        }
+    }
+    archive.finish()
+}
+
+/// Restore user files from an archive created by `backup`
+///
+/// # Arguments
+///
+/// * `archive` - Path of the archive
+/// * `data_dir` - Path to the directory where the users data is stored
+/// * `force` - Overwrite user files that already exist
+///
+/// # Returns
+/// * `io::Result<()>` - Nothing or an error, nothing is written if a file exists and `force` is not set
+///   or if the archive holds anything but user files
+pub fn restore(archive: &Path, data_dir: &Path, force: bool) -> io::Result<()> {
+    let mut files = vec![];
+    for entry in tar::Archive::new(File::open(archive)?).entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.into_owned();
+        // only plain file names, anything else could escape the data directory
+        if name.components().count() != 1 || name.file_name().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Archive contains an invalid path",
+            ));
+        }
+        if !is_user_file_name(&name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Archive contains a file that is not a user",
+            ));
+        }
+        let mut data = vec![];
+        io::Read::read_to_end(&mut entry, &mut data)?;
+        files.push((data_dir.join(name), data));
+    }
+
+    if !force && files.iter().any(|(p, _)| p.exists()) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "File already exists",
+        ));
+    }
+
+    for (path, data) in files {
+        write_private_file(&path, &data, force)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use std::env;
+
+    fn temp_dir() -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "keeper-crabby-{}",
+            rand::thread_rng().gen_range(10000000..99999999)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_init_data_dir() {
+        let parent = temp_dir();
+        let data_dir = parent.join("vault");
+
+        let res = init(Some(&data_dir));
+        let created = data_dir.is_dir();
+        let relative = init(Some(Path::new("relative/vault")));
+
+        fs::remove_dir_all(&parent).unwrap();
+
+        assert_eq!(res.unwrap(), data_dir);
+        assert!(created);
+        assert_eq!(relative.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_init_fail_not_writable() {
+        let parent = temp_dir();
+        // a directory can not be created below a file
+        let file = parent.join("file");
+        fs::write(&file, b"data").unwrap();
+
+        let res = init(Some(&file.join("vault")));
+        let file_as_dir = init(Some(&file));
+
+        fs::remove_dir_all(&parent).unwrap();
+
+        assert!(res.is_err());
+        assert!(file_as_dir.is_err());
+    }
+
+    #[test]
+    fn test_is_read_only() {
+        let data_dir = temp_dir();
+        let file = data_dir.join("d".repeat(64));
+        fs::write(&file, b"data").unwrap();
+
+        let writable = is_read_only(&file);
+        let missing = is_read_only(&data_dir.join("missing"));
+
+        fs::remove_dir_all(&data_dir).unwrap();
+
+        assert!(!writable);
+        assert!(!missing);
+        assert!(is_read_only_error(&io::Error::from(
+            io::ErrorKind::ReadOnlyFilesystem
+        )));
+        assert!(is_read_only_error(&io::Error::from(
+            io::ErrorKind::PermissionDenied
+        )));
+        assert!(!is_read_only_error(&io::Error::from(
+            io::ErrorKind::NotFound
+        )));
+    }
+
+    #[test]
+    fn test_backup_restore_success() {
+        let data_dir = temp_dir();
+        let restore_dir = temp_dir();
+        let user_file = "a".repeat(64);
+        fs::write(data_dir.join(&user_file), b"data").unwrap();
+        fs::write(data_dir.join("not-a-user"), b"other").unwrap();
+
+        let archive = data_dir.join("backup.tar");
+        let users = count_user_files(&data_dir);
+        let backup_res = backup(&data_dir, &archive);
+        let restore_res = restore(&archive, &restore_dir, false);
+        let restored = fs::read(restore_dir.join(&user_file));
+        let metadata = fs::metadata(restore_dir.join(&user_file)).unwrap();
+        let other_restored = restore_dir.join("not-a-user").exists();
+
+        fs::remove_dir_all(&data_dir).unwrap();
+        fs::remove_dir_all(&restore_dir).unwrap();
+
+        assert_eq!(users, 1);
+        assert!(backup_res.is_ok());
+        assert!(restore_res.is_ok());
+        assert_eq!(restored.unwrap(), b"data");
+        assert!(!other_restored);
+        #[cfg(unix)]
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777,
+            0o600
+        );
+        #[cfg(not(unix))]
+        let _ = metadata;
+    }
+
+    #[test]
+    fn test_restore_fail_not_a_user() {
+        let data_dir = temp_dir();
+        let restore_dir = temp_dir();
+        let log = data_dir.join("krab.log");
+        fs::write(&log, b"log").unwrap();
+        fs::write(data_dir.join("c".repeat(64)), b"data").unwrap();
+
+        let archive = data_dir.join("backup.tar");
+        let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+        builder.append_path_with_name(&log, "krab.log").unwrap();
+        builder
+            .append_path_with_name(data_dir.join("c".repeat(64)), "c".repeat(64))
+            .unwrap();
+        builder.finish().unwrap();
+        drop(builder);
+
+        let res = restore(&archive, &restore_dir, false);
+        let written = fs::read_dir(&restore_dir).unwrap().count();
+
+        fs::remove_dir_all(&data_dir).unwrap();
+        fs::remove_dir_all(&restore_dir).unwrap();
+
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(written, 0);
+    }
+
+    #[test]
+    fn test_restore_fail_already_exists() {
+        let data_dir = temp_dir();
+        let user_file = "b".repeat(64);
+        fs::write(data_dir.join(&user_file), b"data").unwrap();
+
+        let archive = data_dir.join("backup.tar");
+        backup(&data_dir, &archive).unwrap();
+        fs::write(data_dir.join(&user_file), b"newer").unwrap();
+
+        let res = restore(&archive, &data_dir, false);
+        let kept = fs::read(data_dir.join(&user_file)).unwrap();
+        let forced_res = restore(&archive, &data_dir, true);
+        let forced = fs::read(data_dir.join(&user_file)).unwrap();
+
+        fs::remove_dir_all(&data_dir).unwrap();
+
+        assert!(res.is_err());
+        assert_eq!(kept, b"newer");
+        assert!(forced_res.is_ok());
+        assert_eq!(forced, b"data");
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let data_dir = temp_dir();
+        let file = data_dir.join("user");
+        fs::write(&file, b"old").unwrap();
+        #[cfg(unix)]
+        fs::set_permissions(&file, std::os::unix::fs::PermissionsExt::from_mode(0o644)).unwrap();
+
+        let res = replace_file(&file, b"new".to_vec());
+        let content = fs::read(&file).unwrap();
+        let metadata = fs::metadata(&file).unwrap();
+        let tmp_left = file.with_extension("tmp").exists();
+
+        fs::remove_dir_all(&data_dir).unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(content, b"new");
+        assert!(!tmp_left);
+        #[cfg(unix)]
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777,
+            0o600
+        );
+        #[cfg(not(unix))]
+        let _ = metadata;
+    }
+
+    #[test]
+    fn test_write_private_file() {
+        let data_dir = temp_dir();
+        let file = data_dir.join("export.csv");
+        fs::write(&file, b"old").unwrap();
+        #[cfg(unix)]
+        fs::set_permissions(&file, std::os::unix::fs::PermissionsExt::from_mode(0o644)).unwrap();
+
+        let res = write_private_file(&file, b"new", false);
+        let kept = fs::read(&file).unwrap();
+        let forced_res = write_private_file(&file, b"new", true);
+        let forced = fs::read(&file).unwrap();
+        let metadata = fs::metadata(&file).unwrap();
+
+        fs::remove_dir_all(&data_dir).unwrap();
+
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(kept, b"old");
+        assert!(forced_res.is_ok());
+        assert_eq!(forced, b"new");
+        #[cfg(unix)]
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777,
+            0o600
+        );
+        #[cfg(not(unix))]
+        let _ = metadata;
+    }
+
+    #[test]
+    fn test_secure_remove_file_success() {
+        let data_dir = temp_dir();
+        let file = data_dir.join("c".repeat(64));
+        fs::write(&file, b"data").unwrap();
+
+        let res = secure_remove_file(&file, 3);
+        let exists = file.exists();
+
+        fs::remove_dir_all(&data_dir).unwrap();
+
+        assert!(res.is_ok());
+        assert!(!exists);
+    }
+}
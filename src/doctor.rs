@@ -0,0 +1,279 @@
+use std::{
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+
+use ratatui::crossterm::{
+    style::available_color_count,
+    terminal::{disable_raw_mode, enable_raw_mode, size},
+};
+
+use crate::{
+    db::{count_user_files, is_writable_dir, locate, DataDirSource},
+    ui::{MIN_HEIGHT, MIN_WIDTH},
+};
+
+// variables krab reads, the values of secret ones are not printed
+const ENV_VARS: [(&str, bool); 25] = [
+    ("KRAB_DATA_DIR", false),
+    ("KRAB_MASTER_PASSWORD", true),
+    ("KRAB_DEFAULT_USER", false),
+    ("KRAB_KDF", false),
+    ("KRAB_SCRYPT_COST", false),
+    ("KRAB_SHARED_SALT", false),
+    ("KRAB_LOG", false),
+    ("KRAB_LOGIN_DELAY_MS", false),
+    ("KRAB_LOCK_SECS", false),
+    ("KRAB_REVEAL_SECS", false),
+    ("KRAB_REVEAL_REQUIRES_MASTER", false),
+    ("KRAB_STALE_DAYS", false),
+    ("KRAB_PRIVACY", false),
+    ("KRAB_HIDE_ON_POPUP", false),
+    ("KRAB_SCROLLBARS", false),
+    ("KRAB_DEFER_WRITES", false),
+    ("KRAB_ENFORCE_RECORD_STRENGTH", false),
+    ("KRAB_PWD_SYMBOLS", false),
+    ("KRAB_CLIPBOARD_MODE", false),
+    ("KRAB_CLIPBOARD_SECS", false),
+    ("KRAB_CLIPBOARD_DISABLED", false),
+    ("KRAB_AUTOTYPE", false),
+    ("KRAB_AUTOTYPE_DELAY_MS", false),
+    ("KRAB_THEME", false),
+    ("KRAB_KEYMAP", false),
+];
+
+/// One line of the `krab doctor` report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub value: String,
+    /// krab can not be used until it is fixed
+    pub blocking: bool,
+}
+
+impl Check {
+    fn new(name: &str, value: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            value: value.into(),
+            blocking: false,
+        }
+    }
+
+    fn blocking(name: &str, value: impl Into<String>) -> Self {
+        Check {
+            blocking: true,
+            ..Check::new(name, value)
+        }
+    }
+}
+
+/// Check the data directory without creating it
+///
+/// A missing directory is created on the next start, so only a relative path set
+/// by the user, a file in its place or a directory that can not be written are
+/// blocking. The platform data directory may be read-only, its vaults are opened
+/// without saving changes.
+///
+/// # Arguments
+///
+/// * `data_dir` - Data directory given with `--data-dir`
+///
+/// # Returns
+/// * `Vec<Check>` - What was found
+pub fn data_dir_checks(data_dir: Option<&Path>) -> Vec<Check> {
+    let (path, source) = match locate(data_dir) {
+        Some(located) => located,
+        None => {
+            return vec![Check::blocking(
+                "Data directory",
+                "no platform data directory, set KRAB_DATA_DIR",
+            )]
+        }
+    };
+    let mut checks = vec![Check::new(
+        "Data directory",
+        format!("{} (from {})", path.display(), source.name()),
+    )];
+    if source != DataDirSource::Platform && !path.is_absolute() {
+        checks.push(Check::blocking("Absolute path", "no, it must be absolute"));
+        return checks;
+    }
+    if !path.exists() {
+        checks.push(Check::new("Exists", "no, it is created on the next start"));
+        return checks;
+    }
+    if !path.is_dir() {
+        checks.push(Check::blocking("Exists", "no, it is not a directory"));
+        return checks;
+    }
+    checks.push(Check::new("Exists", "yes"));
+    checks.push(match is_writable_dir(&path) {
+        true => Check::new("Writable", "yes"),
+        false if source == DataDirSource::Platform => {
+            Check::new("Writable", "no, vaults are opened read-only")
+        }
+        false => Check::blocking("Writable", "no"),
+    });
+    checks.push(Check::new(
+        "User files",
+        count_user_files(&path).to_string(),
+    ));
+
+    checks
+}
+
+/// Report the `.env` file and the variables krab reads
+///
+/// # Arguments
+///
+/// * `dotenv` - Path of the loaded `.env` file
+/// * `var` - Value of an environment variable
+///
+/// # Returns
+/// * `Vec<Check>` - One check for the file and one for each variable that is set
+pub fn env_checks(dotenv: Option<PathBuf>, var: impl Fn(&str) -> Option<String>) -> Vec<Check> {
+    let mut checks = vec![Check::new(
+        ".env file",
+        dotenv.map_or("none found".to_string(), |path| path.display().to_string()),
+    )];
+    for (name, secret) in ENV_VARS {
+        if let Some(value) = var(name) {
+            checks.push(Check::new(
+                name,
+                if secret {
+                    "set (hidden)".to_string()
+                } else {
+                    value
+                },
+            ));
+        }
+    }
+    if checks.len() == 1 {
+        checks.push(Check::new("KRAB_* variables", "none set"));
+    }
+
+    checks
+}
+
+/// Check that the TUI can run in the current terminal
+///
+/// Raw mode is turned on and off again to see if the terminal supports it. A
+/// small window or few colors are reported, but the TUI still runs.
+///
+/// # Returns
+/// * `Vec<Check>` - What was found
+pub fn terminal_checks() -> Vec<Check> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return vec![Check::new(
+            "Terminal",
+            "not attached, run krab doctor in the terminal the TUI is used in",
+        )];
+    }
+    let mut checks = vec![Check::new("Terminal", "yes")];
+    checks.push(match enable_raw_mode().and_then(|_| disable_raw_mode()) {
+        Ok(_) => Check::new("Raw mode", "yes"),
+        Err(_) => Check::blocking("Raw mode", "not supported"),
+    });
+    checks.push(match size() {
+        Ok((width, height)) if width < MIN_WIDTH || height < MIN_HEIGHT => Check::new(
+            "Size",
+            format!(
+                "{}x{}, at least {}x{} is needed",
+                width, height, MIN_WIDTH, MIN_HEIGHT
+            ),
+        ),
+        Ok((width, height)) => Check::new("Size", format!("{}x{}", width, height)),
+        Err(_) => Check::blocking("Size", "unknown"),
+    });
+    checks.push(Check::new("Colors", available_color_count().to_string()));
+    let features: Vec<&str> = [
+        ("autotype", cfg!(feature = "autotype")),
+        ("browser", cfg!(feature = "browser")),
+        ("hibp-online", cfg!(feature = "hibp-online")),
+        ("rayon", cfg!(feature = "rayon")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| feature)
+    .collect();
+    checks.push(Check::new(
+        "Features",
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        },
+    ));
+
+    checks
+}
+
+/// The report printed by `krab doctor`, blocking problems are marked
+pub fn report(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|check| {
+            format!(
+                "{}{}: {}",
+                if check.blocking { "[!] " } else { "" },
+                check.name,
+                check.value
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use dotenv::dotenv;
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn test_data_dir_checks() {
+        dotenv().ok();
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap())
+            .join(format!("keeper-crabby-doctor-{}", std::process::id()));
+
+        let checks = data_dir_checks(Some(&path));
+        assert!(checks.iter().all(|check| !check.blocking));
+        assert_eq!(checks[1].value, "no, it is created on the next start");
+
+        fs::create_dir_all(&path).unwrap();
+        let checks = data_dir_checks(Some(&path));
+        assert!(checks.iter().all(|check| !check.blocking));
+        assert!(checks[0].value.ends_with("(from --data-dir)"));
+        assert_eq!(checks.last().unwrap(), &Check::new("User files", "0"));
+        fs::remove_dir(&path).unwrap();
+
+        let checks = data_dir_checks(Some(Path::new("relative/dir")));
+        assert!(checks.last().unwrap().blocking);
+        assert!(report(&checks).ends_with("[!] Absolute path: no, it must be absolute"));
+    }
+
+    #[test]
+    fn test_env_checks() {
+        let var = |name: &str| match name {
+            "KRAB_MASTER_PASSWORD" => Some("password".to_string()),
+            "KRAB_LOCK_SECS" => Some("60".to_string()),
+            _ => None,
+        };
+        let checks = env_checks(None, var);
+
+        assert_eq!(
+            checks,
+            vec![
+                Check::new(".env file", "none found"),
+                Check::new("KRAB_MASTER_PASSWORD", "set (hidden)"),
+                Check::new("KRAB_LOCK_SECS", "60"),
+            ]
+        );
+        assert_eq!(
+            env_checks(None, |_| None)[1],
+            Check::new("KRAB_* variables", "none set")
+        );
+    }
+}
@@ -1,19 +1,32 @@
 use ratatui::prelude::Rect;
 use std::{cell::RefCell, path::PathBuf};
+use zeroize::Zeroizing;
 
 use ui::{
+    clipboard,
+    keymap::Keymap,
     popups::Popup,
     states::{startup_state::StartUp, ScreenState},
+    theme::Theme,
+    vault_path,
 };
 
-mod crypto;
+mod cli;
+pub mod crypto;
 mod db;
+mod doctor;
+mod logging;
 mod ui;
 
-pub use crypto::hash;
-pub use db::{clear_file_content, create_file, init as db_init};
+pub use cli::{doctor, error_output, run as run_command, Cli, Command};
+pub use crypto::{hash, user_path};
+pub use db::{backup, clear_file_content, create_file, init as db_init, restore};
+pub use logging::init as log_init;
 pub use ui::start;
 
+/// Version of the crate, followed by the git hash of the build if there is one
+pub const VERSION: &str = env!("KRAB_VERSION");
+
 #[derive(Clone)]
 pub struct Application {
     immutable_app_state: ImmutableAppState,
@@ -25,29 +38,42 @@ pub struct Application {
 struct ImmutableAppState {
     pub name: String,
     pub db_path: PathBuf,
+    /// The data directory as it is shown, read once at startup
+    pub vault_path: String,
     pub rect: Option<Rect>,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    /// Secrets are never put on the clipboard, read once at startup
+    pub clipboard_disabled: bool,
 }
 
 #[derive(Clone)]
 struct MutableAppState {
     pub popups: Vec<Box<dyn Popup>>,
     pub running: bool,
+    /// Password kept on the clipboard after exiting (copy and quit)
+    pub clipboard_on_exit: Option<Zeroizing<String>>,
 }
 
 impl Application {
     fn create(db_path: PathBuf, rect: Rect) -> RefCell<Self> {
         let immutable_app_state = ImmutableAppState {
             name: "Keeper Crabby".to_string(),
+            vault_path: vault_path(&db_path),
             db_path,
             rect: Some(rect),
+            theme: Theme::load(),
+            keymap: Keymap::load(),
+            clipboard_disabled: clipboard::disabled(),
         };
 
         let mutable_app_state = MutableAppState {
             popups: Vec::new(),
             running: true,
+            clipboard_on_exit: None,
         };
 
-        let state = ScreenState::StartUp(StartUp::new());
+        let state = ScreenState::StartUp(StartUp::new(&immutable_app_state.db_path));
         RefCell::new(Self {
             immutable_app_state,
             mutable_app_state,
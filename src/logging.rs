@@ -0,0 +1,45 @@
+use std::{env, fs::OpenOptions, path::Path, str::FromStr, sync::Mutex};
+
+use tracing::Level;
+
+const LOG_FILE: &str = "krab.log";
+
+// level set with `KRAB_LOG` (error, warn, info, debug or trace), no logging without it
+fn level_from_env() -> Option<Level> {
+    env::var("KRAB_LOG")
+        .ok()
+        .and_then(|level| Level::from_str(&level).ok())
+}
+
+/// Append logs to `krab.log` in the data directory if `KRAB_LOG` is set
+///
+/// The TUI owns the terminal, so nothing is logged to it. Secrets (passwords,
+/// domains, decrypted data) are never logged.
+///
+/// # Arguments
+///
+/// * `dir` - The data directory
+///
+/// # Returns
+/// * `Result<(), String>` - Nothing or an error message
+pub fn init(dir: &Path) -> Result<(), String> {
+    let level = match level_from_env() {
+        Some(level) => level,
+        None => return Ok(()),
+    };
+    let file = match OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(LOG_FILE))
+    {
+        Ok(file) => file,
+        Err(_) => return Err("Could not open the log file".to_string()),
+    };
+
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(false)
+        .with_writer(Mutex::new(file))
+        .try_init()
+        .map_err(|_| "Could not set up logging".to_string())
+}
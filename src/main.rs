@@ -1,15 +1,44 @@
 extern crate dotenv;
 extern crate downcast_rs;
 
+use clap::Parser;
 use dotenv::dotenv;
-use keeper_crabby::{db_init, start};
+use keeper_crabby::{db_init, doctor, error_output, log_init, run_command, start, Cli, Command};
+use std::process;
 
 fn main() {
     dotenv().ok();
 
-    let db_path = db_init().unwrap();
-    match start(db_path) {
-        Ok(_) => {}
-        Err(e) => eprintln!("Error: {}", e),
+    let cli = Cli::parse();
+    // runs before the data directory is created, so it is reported as it is
+    if cli.command == Some(Command::Doctor) {
+        if let Err(e) = doctor(cli.data_dir.as_deref()) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    let db_path = match db_init(cli.data_dir.as_deref()) {
+        Ok(db_path) => db_path,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = log_init(&db_path) {
+        eprintln!("Error: {}", e);
+    }
+    match cli.command {
+        Some(command) => {
+            let json = command.json();
+            if let Err(e) = run_command(command, &db_path, cli.master_file.as_deref()) {
+                eprintln!("{}", error_output(&e, json));
+                process::exit(1);
+            }
+        }
+        None => match start(db_path) {
+            Ok(_) => {}
+            Err(e) => eprintln!("Error: {}", e),
+        },
     }
 }
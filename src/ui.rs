@@ -1,38 +1,182 @@
-use std::{cell::RefCell, error::Error, io, path::PathBuf};
+use std::{
+    cell::RefCell,
+    env,
+    error::Error,
+    io,
+    panic::{self, PanicHookInfo},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
+use directories::BaseDirs;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
     crossterm::{
-        event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+        event::{
+            self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste,
+            EnableMouseCapture, Event,
+        },
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
-    layout::{Constraint, Direction, Layout, Rect},
-    widgets::{Block, Borders},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Wrap},
     Frame, Terminal,
 };
+use unicode_segmentation::UnicodeSegmentation;
+use zeroize::Zeroizing;
 
 use crate::{
     ui::{
-        popups::PopupType,
-        states::{ScreenState, State},
+        clipboard::ClipboardMode,
+        popups::{health_popup::HealthPopup, lock_popup::LockPopup, PopupType},
+        states::{
+            home_state::{reveal_timeout, Home},
+            ScreenState, State,
+        },
     },
     Application,
 };
 
+pub mod autotype;
+pub mod browser;
+pub mod clipboard;
 pub mod components;
+pub mod keymap;
 pub mod popups;
 pub mod states;
+pub mod theme;
+pub mod view_state;
+
+// how long to wait for an event before the time based hooks run again
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const DEFAULT_LOCK_SECS: u64 = 300;
+// smallest terminal the views can be rendered in
+pub(crate) const MIN_WIDTH: u16 = 60;
+pub(crate) const MIN_HEIGHT: u16 = 20;
+
+/// Inactivity after which the home screen is locked, set with `KRAB_LOCK_SECS` (0 disables it)
+fn lock_timeout() -> Option<Duration> {
+    let secs = env::var("KRAB_LOCK_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_LOCK_SECS);
+    match secs {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+// wipes the secrets and covers the home screen until the master password is entered
+fn lock(app: &mut Application) {
+    let locked = app
+        .mutable_app_state
+        .popups
+        .iter()
+        .any(|p| matches!(p.popup_type(), PopupType::Lock));
+    if let ScreenState::Home(s) = &mut app.state {
+        if !locked {
+            s.lock();
+            app.mutable_app_state
+                .popups
+                .push(Box::new(LockPopup::new()));
+        }
+    }
+}
+
+/// Timers checked on every pass of the main loop, whether or not an event came in
+struct Timers {
+    lock_after: Option<Duration>,
+    reveal_for: Option<Duration>,
+}
+
+impl Timers {
+    fn from_env() -> Self {
+        Timers {
+            lock_after: lock_timeout(),
+            reveal_for: reveal_timeout(),
+        }
+    }
+
+    // runs the time based hooks and picks up a finished login or breach check,
+    // `last_key` is when the last key was pressed
+    fn tick(&self, app: &mut Application, last_key: Instant) {
+        if let ScreenState::Login(s) = &app.state {
+            let mut s = s.clone();
+            s.poll_login(app);
+        }
+        if let Some(reveal_for) = self.reveal_for {
+            if let ScreenState::Home(s) = &mut app.state {
+                s.hide_expired_secrets(reveal_for);
+            }
+        }
+        for popup in app.mutable_app_state.popups.iter_mut() {
+            if let Some(health) = popup.downcast_mut::<HealthPopup>() {
+                health.poll_breached();
+            }
+        }
+        if self
+            .lock_after
+            .is_some_and(|lock_after| last_key.elapsed() >= lock_after)
+        {
+            lock(app);
+        }
+    }
+}
 
 pub fn ui(f: &mut Frame, app: &Application) {
+    if f.area().width < MIN_WIDTH || f.area().height < MIN_HEIGHT {
+        let message = format!(
+            "Please enlarge your terminal (need at least {}x{})",
+            MIN_WIDTH, MIN_HEIGHT
+        );
+        let rect = centered_absolute_rect(f.area(), message.len() as u16, 3);
+        let message_p = Paragraph::new(message)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+        f.render_widget(message_p, rect);
+        return;
+    }
+
     let wrapper = Rect::new(0, 0, f.area().width, f.area().height);
-    f.render_widget(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(app.immutable_app_state.name.clone()),
-        wrapper,
-    );
-    let rect = centered_rect(f.area(), 97, 94);
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .title(app.immutable_app_state.name.clone());
+    if let ScreenState::Home(s) = &app.state {
+        block = block
+            .title_bottom(Line::from(Home::legend(&app.immutable_app_state.keymap)).left_aligned())
+            .title_bottom(
+                Line::styled(
+                    s.status(),
+                    Style::default().fg(app.immutable_app_state.theme.text),
+                )
+                .right_aligned(),
+            );
+    }
+    // the data directory in use, so a wrong KRAB_DATA_DIR is noticed before logging in
+    let vault_path = &app.immutable_app_state.vault_path;
+    let vault = match &app.state {
+        ScreenState::StartUp(s) => {
+            Some(format!(" vault: {} ({} users) ", vault_path, s.user_count))
+        }
+        ScreenState::Login(_) => Some(format!(" vault: {} ", vault_path)),
+        _ => None,
+    };
+    if let Some(vault) = vault {
+        block = block.title_bottom(
+            Line::styled(
+                vault,
+                Style::default().fg(app.immutable_app_state.theme.inactive),
+            )
+            .right_aligned(),
+        );
+    }
+    f.render_widget(block, wrapper);
+    let rect = view_rect(f.area());
     match &app.state {
         ScreenState::Login(s) => s.render(f, app, rect),
         ScreenState::StartUp(s) => {
@@ -42,6 +186,8 @@ pub fn ui(f: &mut Frame, app: &Application) {
             s.render(f, app, rect);
         }
         ScreenState::Home(s) => s.render(f, app, rect),
+        ScreenState::ChangeMaster(s) => s.render(f, app, rect),
+        ScreenState::DeleteAccount(s) => s.render(f, app, rect),
     }
     for popup in &app.mutable_app_state.popups {
         popup.render(f, app, popup.wrapper(rect));
@@ -51,69 +197,207 @@ pub fn ui(f: &mut Frame, app: &Application) {
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     application: RefCell<Application>,
-) -> io::Result<bool> {
+) -> io::Result<Option<Zeroizing<String>>> {
+    let timers = Timers::from_env();
+    let mut last_key = Instant::now();
+    // where the home screen was last seen, stored once it is left
+    let mut home_view = None;
+
     loop {
+        timers.tick(&mut application.borrow_mut(), last_key);
+
         let app = application.borrow();
         let should_break = !app.mutable_app_state.running;
 
+        match &app.state {
+            ScreenState::Home(s) if !should_break => {
+                home_view = s.view_state().map(|view| (s.user.username(), view));
+            }
+            _ => {
+                // the view is not worth failing over, it is just not restored
+                if let Some((username, view)) = home_view.take() {
+                    let _ = view.save(&app.immutable_app_state.db_path, &username);
+                }
+            }
+        }
+
         if should_break {
             break;
         }
 
-        let _ = terminal.draw(|f| ui(f, &app));
+        terminal.draw(|f| ui(f, &app))?;
         drop(app);
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == event::KeyEventKind::Release {
-                continue;
-            }
-            let app = application.borrow();
-            let app_copy = app.clone();
-            let amount_of_popups = app_copy.mutable_app_state.popups.len();
-            drop(app);
-            if amount_of_popups > 0 {
-                let mut app = application.borrow_mut();
-                let (changed_app, last_state) =
-                    app.mutable_app_state.popups[amount_of_popups - 1].handle_key(&key, &app_copy);
-                app.mutable_app_state = changed_app.mutable_app_state;
-                app.state = changed_app.state;
-
-                if let Some(last_state) = last_state {
-                    let mut new_app: Application = app.clone();
-                    match last_state.popup_type() {
-                        PopupType::InsertPwd => match &mut app.state {
-                            ScreenState::Register(s) => {
-                                new_app = s.handle_insert_record_popup(new_app, last_state);
+        // wait for an event at most one interval, so the timers fire without a keypress
+        if !event::poll(POLL_INTERVAL)? {
+            continue;
+        }
+
+        match event::read()? {
+            Event::Key(key) => {
+                if key.kind == event::KeyEventKind::Release {
+                    continue;
+                }
+                last_key = Instant::now();
+                let app = application.borrow();
+                let app_copy = app.clone();
+                let amount_of_popups = app_copy.mutable_app_state.popups.len();
+                drop(app);
+                if amount_of_popups > 0 {
+                    let mut app = application.borrow_mut();
+                    let (changed_app, last_state) = app.mutable_app_state.popups
+                        [amount_of_popups - 1]
+                        .handle_key(&key, &app_copy);
+                    app.mutable_app_state = changed_app.mutable_app_state;
+                    app.state = changed_app.state;
+
+                    if let Some(last_state) = last_state {
+                        let mut new_app: Application = app.clone();
+                        // a confirmed `ConfirmPopup` hands on the popup it closed, so
+                        // the type is the one of that popup
+                        if let PopupType::InsertPwd = last_state.popup_type() {
+                            match &mut app.state {
+                                ScreenState::Register(s) => {
+                                    new_app = s.handle_insert_record_popup(new_app, last_state);
+                                }
+                                ScreenState::Home(s) => {
+                                    new_app = s.handle_insert_record_popup(new_app, last_state);
+                                }
+                                _ => {}
+                            }
+                        } else if let PopupType::MasterPwd = last_state.popup_type() {
+                            if let ScreenState::Home(s) = &mut app.state {
+                                new_app = s.handle_master_pwd_popup(new_app, last_state);
+                            }
+                        } else if let PopupType::Rename = last_state.popup_type() {
+                            if let ScreenState::Home(s) = &mut app.state {
+                                new_app = s.handle_rename_popup(new_app, last_state);
+                            }
+                        } else if let PopupType::Tag = last_state.popup_type() {
+                            if let ScreenState::Home(s) = &mut app.state {
+                                new_app = s.handle_tag_popup(new_app, last_state);
+                            }
+                        } else if let PopupType::Settings = last_state.popup_type() {
+                            // outside the home screen the settings are only shown
+                            if let ScreenState::Home(s) = &mut app.state {
+                                new_app = s.handle_settings_popup(new_app, last_state);
+                            }
+                        } else if let PopupType::Confirm = last_state.popup_type() {
+                            match &mut app.state {
+                                ScreenState::DeleteAccount(s) => {
+                                    new_app = s.handle_confirm_popup(new_app, last_state);
+                                }
+                                ScreenState::Home(s) => {
+                                    new_app = s.handle_confirm_popup(new_app, last_state);
+                                }
+                                _ => {}
                             }
-                            _ => {}
-                        },
-                        _ => {}
+                        }
+
+                        app.mutable_app_state = new_app.mutable_app_state;
+                        app.state = new_app.state;
                     }
+                } else {
+                    let mut app = application.borrow_mut();
+                    let changed_app = match &mut app.state {
+                        ScreenState::Login(s) => s.handle_key(&key, &app_copy),
+                        ScreenState::StartUp(s) => s.handle_key(&key, &app_copy),
+                        ScreenState::Home(s) => s.handle_key(&key, &app_copy),
+                        ScreenState::Register(s) => s.handle_key(&key, &app_copy),
+                        ScreenState::ChangeMaster(s) => s.handle_key(&key, &app_copy),
+                        ScreenState::DeleteAccount(s) => s.handle_key(&key, &app_copy),
+                    };
 
-                    app.mutable_app_state = new_app.mutable_app_state;
-                    app.state = new_app.state;
+                    app.mutable_app_state = changed_app.mutable_app_state;
+                    app.state = changed_app.state;
                 }
-            } else {
+            }
+            // pasted text goes into the focused field as is, it is never read as key bindings
+            Event::Paste(text) => {
+                last_key = Instant::now();
+                let text = Zeroizing::new(pasted(&text));
                 let mut app = application.borrow_mut();
-                let changed_app: Application;
-                match &mut app.state {
-                    ScreenState::Login(s) => changed_app = s.handle_key(&key, &app_copy),
-                    ScreenState::StartUp(s) => changed_app = s.handle_key(&key, &app_copy),
-                    ScreenState::Home(s) => changed_app = s.handle_key(&key, &app_copy),
-                    ScreenState::Register(s) => changed_app = s.handle_key(&key, &app_copy),
-                };
-
-                app.mutable_app_state = changed_app.mutable_app_state;
-                app.state = changed_app.state;
+                if let Some(popup) = app.mutable_app_state.popups.last_mut() {
+                    popup.handle_paste(&text);
+                } else {
+                    let app_copy = app.clone();
+                    let changed_app = match &mut app.state {
+                        ScreenState::Login(s) => s.handle_paste(&text, &app_copy),
+                        ScreenState::StartUp(s) => s.handle_paste(&text, &app_copy),
+                        ScreenState::Home(s) => s.handle_paste(&text, &app_copy),
+                        ScreenState::Register(s) => s.handle_paste(&text, &app_copy),
+                        ScreenState::ChangeMaster(s) => s.handle_paste(&text, &app_copy),
+                        ScreenState::DeleteAccount(s) => s.handle_paste(&text, &app_copy),
+                    };
+
+                    app.mutable_app_state = changed_app.mutable_app_state;
+                    app.state = changed_app.state;
+                }
             }
+            Event::Mouse(mouse) => {
+                let app = application.borrow();
+                let app_copy = app.clone();
+                let amount_of_popups = app_copy.mutable_app_state.popups.len();
+                drop(app);
+                if amount_of_popups == 0 {
+                    let mut app = application.borrow_mut();
+                    let changed_app = match &mut app.state {
+                        ScreenState::Login(s) => s.handle_mouse(&mouse, &app_copy),
+                        ScreenState::StartUp(s) => s.handle_mouse(&mouse, &app_copy),
+                        ScreenState::Home(s) => s.handle_mouse(&mouse, &app_copy),
+                        ScreenState::Register(s) => s.handle_mouse(&mouse, &app_copy),
+                        ScreenState::ChangeMaster(s) => s.handle_mouse(&mouse, &app_copy),
+                        ScreenState::DeleteAccount(s) => s.handle_mouse(&mouse, &app_copy),
+                    };
+
+                    app.mutable_app_state = changed_app.mutable_app_state;
+                    app.state = changed_app.state;
+                }
+            }
+            _ => {}
         }
         let mut app = application.borrow_mut();
         app.immutable_app_state.rect = Some(terminal.get_frame().area());
     }
-    Ok(true)
+    Ok(application
+        .borrow_mut()
+        .mutable_app_state
+        .clipboard_on_exit
+        .take())
+}
+
+/// The data directory as it is shown, with the home directory replaced by `~`
+pub fn vault_path(data_dir: &Path) -> String {
+    let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    tilde_path(data_dir, home.as_deref())
 }
 
+/// A path with the home directory replaced by `~`
+fn tilde_path(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+/// Pasted text as it is inserted into a single line field, without line breaks and
+/// other control characters
+fn pasted(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// Remove the last grapheme of a field, so backspace deletes what looks like one
+/// character even if it is made of several (an accent, a flag or an emoji sequence)
+fn pop_grapheme(text: &mut String) {
+    let last = text.graphemes(true).next_back().map_or(0, str::len);
+    text.truncate(text.len() - last);
+}
+
+/// Rect in the middle of `r` taking the given percentages of its size (at most 100)
 fn centered_rect(r: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let percent_x = percent_x.min(100);
+    let percent_y = percent_y.min(100);
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -133,26 +417,181 @@ fn centered_rect(r: Rect, percent_x: u16, percent_y: u16) -> Rect {
         .split(popup_layout[1])[1]
 }
 
+/// Rect the current state is rendered in, inside the border of the terminal
+fn view_rect(area: Rect) -> Rect {
+    centered_absolute_rect(
+        area,
+        area.width.saturating_sub(6),
+        area.height.saturating_sub(4),
+    )
+}
+
+/// Rect of the given size in the middle of `r`, shrunk to fit if `r` is smaller
+fn centered_absolute_rect(r: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(r.width);
+    let height = height.min(r.height);
+    Rect::new(
+        r.x + (r.width - width) / 2,
+        r.y + (r.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+// leaves raw mode and the alternate screen so the terminal is usable again
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )?;
+    Ok(())
+}
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+// puts the panic hook that was set before the TUI back when dropped, also when
+// starting the TUI fails half way
+struct PanicHookGuard(Arc<PanicHook>);
+
+impl PanicHookGuard {
+    // restores the terminal before the previous hook prints the panic message,
+    // otherwise the message is lost in the alternate screen, panics of other
+    // threads (the login worker) leave the terminal to the TUI
+    fn install() -> Self {
+        let original_hook = Arc::new(panic::take_hook());
+        let hook = Arc::clone(&original_hook);
+        let main_thread = thread::current().id();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().id() == main_thread {
+                let _ = restore_terminal();
+            }
+            hook(info);
+        }));
+        PanicHookGuard(original_hook)
+    }
+}
+
+impl Drop for PanicHookGuard {
+    fn drop(&mut self) {
+        // the hook can not be changed while unwinding, the panic ends the process anyway
+        if thread::panicking() {
+            return;
+        }
+        let _ = panic::take_hook();
+        let original_hook = Arc::clone(&self.0);
+        panic::set_hook(Box::new(move |info| original_hook(info)));
+    }
+}
+
 pub fn start(db_path: PathBuf) -> Result<(), Box<dyn Error>> {
+    let panic_hook = PanicHookGuard::install();
+
     enable_raw_mode()?;
 
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
 
     let beckend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(beckend)?;
 
     let rect = terminal.get_frame().area();
     let app = Application::create(db_path, rect);
-    let _res = run_app(&mut terminal, app);
+    let res = run_app(&mut terminal, app);
 
+    // overwrite the cells before leaving, revealed passwords are not left on a
+    // terminal that keeps the alternate screen in its history
+    terminal.clear()?;
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
+    drop(panic_hook);
+
+    // the terminal is restored first so the error is readable once printed
+    if let Some(pwd) = res? {
+        let mode = ClipboardMode::from_env();
+        match mode.timeout() {
+            Some(timeout) => println!(
+                "Copied, it is kept on the clipboard for {} seconds or until something else is copied",
+                timeout.as_secs()
+            ),
+            None => println!("Copied, it is kept on the clipboard until something else is copied"),
+        }
+        clipboard::hold_after_exit(&pwd, mode)?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tilde_path() {
+        let home = Path::new("/home/crab");
+
+        assert_eq!(
+            tilde_path(
+                Path::new("/home/crab/.local/share/keeper-crabby"),
+                Some(home)
+            ),
+            "~/.local/share/keeper-crabby"
+        );
+        assert_eq!(tilde_path(home, Some(home)), "~");
+        assert_eq!(
+            tilde_path(Path::new("/home/crabby"), Some(home)),
+            "/home/crabby"
+        );
+        assert_eq!(tilde_path(Path::new("/srv/vault"), None), "/srv/vault");
+    }
+
+    #[test]
+    fn test_pasted() {
+        assert_eq!(pasted("p4ss word\r\n"), "p4ss word");
+        assert_eq!(pasted("a\tdq\u{1b}g"), "adqg");
+    }
+
+    #[test]
+    fn test_pop_grapheme() {
+        let mut text = "mu\u{0308}nchen.de 👩‍👩‍👧 e\u{0301}".to_string();
+        pop_grapheme(&mut text);
+        assert_eq!(text, "mu\u{0308}nchen.de 👩‍👩‍👧 ");
+        pop_grapheme(&mut text);
+        pop_grapheme(&mut text);
+        assert_eq!(text, "mu\u{0308}nchen.de ");
+
+        let mut text = "ü".to_string();
+        pop_grapheme(&mut text);
+        pop_grapheme(&mut text);
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn test_centered_absolute_rect() {
+        let r = Rect::new(2, 3, 10, 5);
+        assert_eq!(centered_absolute_rect(r, 40, 20), r);
+        assert_eq!(centered_absolute_rect(r, 4, 1), Rect::new(5, 5, 4, 1));
+    }
+
+    #[test]
+    fn test_centered_rect_fits() {
+        let r = Rect::new(0, 0, 10, 5);
+        let rect = centered_rect(r, 400, 200);
+        assert!(rect.width <= r.width && rect.height <= r.height);
+        assert!(r.contains(rect.as_position()));
+    }
+}
@@ -0,0 +1,73 @@
+use std::{env, thread, time::Duration};
+
+use zeroize::Zeroizing;
+
+// time the user has to focus the target window before typing starts
+const DEFAULT_AUTOTYPE_DELAY_MS: u64 = 2000;
+
+/// Whether auto-type can be used
+///
+/// Auto-type needs the `autotype` feature and `KRAB_AUTOTYPE=1`, it is off otherwise.
+pub fn enabled() -> bool {
+    cfg!(feature = "autotype") && env::var("KRAB_AUTOTYPE").is_ok_and(|v| v == "1")
+}
+
+// delay before typing, overridden by `KRAB_AUTOTYPE_DELAY_MS`
+fn delay() -> Duration {
+    let millis = env::var("KRAB_AUTOTYPE_DELAY_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_AUTOTYPE_DELAY_MS);
+    Duration::from_millis(millis)
+}
+
+/// Type a password into the focused window after a short delay
+///
+/// The keystrokes are typed from a background thread so the TUI keeps running
+/// while the user switches to the target window.
+///
+/// Security: the password is sent as synthetic key events to whatever window
+/// has focus when the delay ends, which may not be the one the user intended.
+/// Other programs on the same display (X11 in particular) can observe the
+/// events, and keyloggers see them like real typing.
+///
+/// # Arguments
+///
+/// * `pwd` - The password to type
+///
+/// # Returns
+/// * `Result<(), String>` - Nothing or an error message if auto-type is not available
+pub fn type_password(pwd: &str) -> Result<(), String> {
+    if !enabled() {
+        return Err("Auto-type is not enabled".to_string());
+    }
+
+    let pwd = Zeroizing::new(pwd.to_string());
+    let delay = delay();
+    thread::spawn(move || {
+        thread::sleep(delay);
+        // errors can not be shown from here, the TUI owns the screen
+        let _ = type_text(&pwd);
+    });
+
+    Ok(())
+}
+
+#[cfg(feature = "autotype")]
+fn type_text(text: &str) -> Result<(), String> {
+    use enigo::{Enigo, Keyboard, Settings};
+
+    let mut enigo = match Enigo::new(&Settings::default()) {
+        Ok(enigo) => enigo,
+        Err(_) => return Err("Could not connect to the display".to_string()),
+    };
+    match enigo.text(text) {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Could not type the password".to_string()),
+    }
+}
+
+#[cfg(not(feature = "autotype"))]
+fn type_text(_text: &str) -> Result<(), String> {
+    Err("Auto-type is not available".to_string())
+}
@@ -0,0 +1,96 @@
+/// URL a domain is opened at, `None` if the domain does not look like a web address
+///
+/// `https://` is prepended to a domain without a scheme, other schemes than
+/// `http` and `https` are not opened.
+///
+/// # Arguments
+///
+/// * `domain` - The domain of a record
+///
+/// # Returns
+/// * `Option<String>` - The URL
+pub fn url_for(domain: &str) -> Option<String> {
+    let domain = domain.trim();
+    let (url, rest) = match domain.split_once("://") {
+        Some(("http" | "https", rest)) => (domain.to_string(), rest),
+        Some(_) => return None,
+        None => (format!("https://{}", domain), domain),
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => host,
+        Some(_) => return None,
+        None => host,
+    };
+    let label_ok = |label: &str| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+    };
+
+    if url.chars().any(char::is_whitespace)
+        || !(host == "localhost" || host.contains('.') && host.split('.').all(label_ok))
+    {
+        return None;
+    }
+
+    Some(url)
+}
+
+/// Open a domain in the default browser
+///
+/// Needs the `browser` feature, without it an error is returned.
+///
+/// # Arguments
+///
+/// * `domain` - The domain of a record
+///
+/// # Returns
+/// * `Result<String, String>` - The opened URL or an error message
+pub fn open_domain(domain: &str) -> Result<String, String> {
+    let url = match url_for(domain) {
+        Some(url) => url,
+        None => return Err("The domain is not a web address".to_string()),
+    };
+    open_url(&url)?;
+
+    Ok(url)
+}
+
+#[cfg(feature = "browser")]
+fn open_url(url: &str) -> Result<(), String> {
+    // the browser is started detached, its output would draw over the TUI
+    open::that_detached(url).map_err(|_| "Could not open the browser".to_string())
+}
+
+#[cfg(not(feature = "browser"))]
+fn open_url(_url: &str) -> Result<(), String> {
+    Err("Opening the browser is not available".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_for() {
+        assert_eq!(
+            url_for("github.com"),
+            Some("https://github.com".to_string())
+        );
+        assert_eq!(
+            url_for("http://localhost:8080/login"),
+            Some("http://localhost:8080/login".to_string())
+        );
+        assert_eq!(
+            url_for("mail.example.co.uk/inbox"),
+            Some("https://mail.example.co.uk/inbox".to_string())
+        );
+        assert_eq!(url_for("bank pin"), None);
+        assert_eq!(url_for("wifi"), None);
+        assert_eq!(url_for("ftp://example.com"), None);
+        assert_eq!(url_for("example..com"), None);
+        assert_eq!(url_for("example.com:port"), None);
+    }
+}
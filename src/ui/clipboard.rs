@@ -0,0 +1,189 @@
+use std::{
+    cell::RefCell,
+    env,
+    time::{Duration, Instant},
+};
+
+use arboard::Clipboard;
+
+// how long copied passwords are kept after krab exits
+const DEFAULT_CLIPBOARD_SECS: u64 = 30;
+
+thread_local! {
+    // on Linux the copied text is served by this process, so the clipboard
+    // has to stay alive as long as the TUI runs
+    static CLIPBOARD: RefCell<Option<Clipboard>> = const { RefCell::new(None) };
+}
+
+/// When a password copied on exit is removed from the clipboard
+///
+/// Set with `KRAB_CLIPBOARD_MODE=secs|paste|never`, `secs` is the default.
+///
+/// A paste can not be observed on any platform: on Linux the clipboard is served
+/// from a thread of the clipboard library that does not report requests, macOS and
+/// Windows do not notify the owner at all. `ClearAfterFirstPaste` therefore falls
+/// back to the timer of `ClearAfterSecs`. All modes stop as soon as something else
+/// is copied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipboardMode {
+    /// Removed after the given time
+    ClearAfterSecs(Duration),
+    /// Removed after the first paste, see above
+    ClearAfterFirstPaste,
+    /// Kept until something else is copied, on Linux krab waits until then
+    Never,
+}
+
+impl ClipboardMode {
+    /// Mode set with `KRAB_CLIPBOARD_MODE`, the timer is set with `KRAB_CLIPBOARD_SECS`
+    pub fn from_env() -> Self {
+        ClipboardMode::from_value(env::var("KRAB_CLIPBOARD_MODE").ok().as_deref())
+    }
+
+    fn from_value(value: Option<&str>) -> Self {
+        match value.map(|v| v.to_ascii_lowercase()).as_deref() {
+            Some("paste") => ClipboardMode::ClearAfterFirstPaste,
+            Some("never") => ClipboardMode::Never,
+            _ => ClipboardMode::ClearAfterSecs(hold_timeout()),
+        }
+    }
+
+    /// How long the text is kept at most, `None` until something else is copied
+    pub fn timeout(&self) -> Option<Duration> {
+        match self {
+            ClipboardMode::ClearAfterSecs(timeout) => Some(*timeout),
+            // a paste can not be observed, the timer is used instead
+            ClipboardMode::ClearAfterFirstPaste => Some(hold_timeout()),
+            ClipboardMode::Never => None,
+        }
+    }
+}
+
+// how often the clipboard is checked while waiting to clear it
+#[cfg(not(target_os = "linux"))]
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the clipboard is held after exiting, set with `KRAB_CLIPBOARD_SECS`
+pub fn hold_timeout() -> Duration {
+    let secs = env::var("KRAB_CLIPBOARD_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CLIPBOARD_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Whether the clipboard is never used, set with `KRAB_CLIPBOARD_DISABLED=1|true`
+pub fn disabled() -> bool {
+    match env::var("KRAB_CLIPBOARD_DISABLED") {
+        Ok(v) => v == "1" || v.eq_ignore_ascii_case("true"),
+        Err(_) => false,
+    }
+}
+
+/// Copy text to the system clipboard
+///
+/// # Arguments
+///
+/// * `text` - The text to copy
+///
+/// # Returns
+/// * `Result<(), String>` - Nothing or an error message if there is no clipboard
+pub fn copy(text: &str) -> Result<(), String> {
+    CLIPBOARD.with(|clipboard| {
+        let mut clipboard = clipboard.borrow_mut();
+        if clipboard.is_none() {
+            *clipboard = Clipboard::new().ok();
+        }
+        match clipboard.as_mut() {
+            Some(clipboard) => clipboard
+                .set_text(text)
+                .map_err(|_| "Could not copy to the clipboard".to_string()),
+            None => Err("No clipboard available".to_string()),
+        }
+    })
+}
+
+/// Keep text on the clipboard after the TUI exited
+///
+/// On Linux the clipboard is served by the process that set it, so this blocks
+/// until another application takes the clipboard or the timeout of `mode` ends,
+/// after which the text is gone. Elsewhere the clipboard outlives the process, so
+/// the clipboard is polled until the timeout ends and cleared if it still holds
+/// the text. With `ClipboardMode::Never` it only returns on Linux once something
+/// else is copied.
+///
+/// # Arguments
+///
+/// * `text` - The text to keep
+/// * `mode` - When the text is removed
+pub fn hold_after_exit(text: &str, mode: ClipboardMode) -> Result<(), String> {
+    let mut clipboard = match Clipboard::new() {
+        Ok(clipboard) => clipboard,
+        Err(_) => return Err("No clipboard available".to_string()),
+    };
+
+    #[cfg(target_os = "linux")]
+    let res = {
+        use arboard::SetExtLinux;
+
+        let set = clipboard.set().exclude_from_history();
+        match mode.timeout() {
+            Some(timeout) => set.wait_until(Instant::now() + timeout).text(text),
+            None => set.wait().text(text),
+        }
+    };
+    #[cfg(not(target_os = "linux"))]
+    let res = clipboard.set_text(text).and_then(|_| match mode.timeout() {
+        Some(timeout) => clear_unless_replaced(&mut clipboard, text, timeout),
+        None => Ok(()),
+    });
+
+    res.map_err(|_| "Could not copy to the clipboard".to_string())
+}
+
+// clears the clipboard once the timeout ends, unless something else was copied before
+#[cfg(not(target_os = "linux"))]
+fn clear_unless_replaced(
+    clipboard: &mut Clipboard,
+    text: &str,
+    timeout: Duration,
+) -> Result<(), arboard::Error> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if clipboard.get_text().ok().as_deref() != Some(text) {
+            return Ok(());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    match clipboard.get_text() {
+        Ok(current) if current == text => clipboard.clear(),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipboard_mode() {
+        assert_eq!(
+            ClipboardMode::from_value(Some("paste")),
+            ClipboardMode::ClearAfterFirstPaste
+        );
+        assert_eq!(
+            ClipboardMode::from_value(Some("Never")),
+            ClipboardMode::Never
+        );
+        assert_eq!(
+            ClipboardMode::from_value(None),
+            ClipboardMode::ClearAfterSecs(hold_timeout())
+        );
+        assert_eq!(ClipboardMode::Never.timeout(), None);
+        assert_eq!(
+            ClipboardMode::ClearAfterFirstPaste.timeout(),
+            Some(hold_timeout())
+        );
+    }
+}
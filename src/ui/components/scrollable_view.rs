@@ -1,39 +1,64 @@
 use ratatui::{
     buffer::Cell,
     prelude::{Buffer, Rect},
-    style::{Color, Style},
+    style::Style,
     widgets::Block,
     widgets::{Borders, Widget},
 };
 
-use crate::ui::{centered_rect, states::home_state::Position};
+use crate::ui::{states::home_state::Position, theme::Theme, view_rect};
 
 pub struct ScrollView {}
 
 impl ScrollView {
     pub fn check_if_width_out_of_bounds(
         position: &Position,
-        buffer_to_render: &Buffer,
+        buffer_area: Rect,
         area: Rect,
     ) -> bool {
-        let area = centered_rect(area, 97, 94);
-        if position.offset_x + area.width - 4 > buffer_to_render.area().width {
+        let area = view_rect(area);
+        if position.offset_x + area.width - 4 > buffer_area.width {
             return true;
         }
         false
     }
 
     pub fn inner_buffer_bounding_box(area: Rect) -> (u16, u16) {
-        let area = centered_rect(area, 97, 94);
+        let area = view_rect(area);
         (area.width - 4, area.height - 3)
     }
 
-    pub fn render(buffer: &mut Buffer, position: &Position, area: Rect, buffer_to_render: &Buffer) {
+    /// Render the part of `buffer_to_render` at `position` with scrollbars
+    ///
+    /// `buffer_to_render` may hold only the rows in view of a larger content,
+    /// the scrollbars are sized to `content_area`. A scrollbar is only drawn if
+    /// the content does not fit in its direction and `scrollbars` is set, the
+    /// view takes up the space of a scrollbar that is not drawn.
+    pub fn render(
+        buffer: &mut Buffer,
+        position: &Position,
+        area: Rect,
+        content_area: Rect,
+        buffer_to_render: &Buffer,
+        theme: &Theme,
+        scrollbars: bool,
+    ) {
         let area = ScrollView::render_borders(buffer, area);
-        let area = ScrollView::render_scrollbars(buffer, position, area, buffer_to_render);
+        let (x_size, y_size) = ScrollView::scrollbar_sizes(area, content_area);
+        let shown = (scrollbars && x_size < 1.0, scrollbars && y_size < 1.0);
+        let area =
+            ScrollView::render_scrollbars(buffer, position, area, content_area, shown, theme);
         ScrollView::render_view(buffer, position, area, buffer_to_render);
     }
 
+    // share of the content in view horizontally and vertically, 1.0 if it fits
+    fn scrollbar_sizes(area: Rect, content_area: Rect) -> (f32, f32) {
+        let scrollbar_x_size = (area.width as f32 - 1.0) / content_area.width as f32;
+        let scrollbar_y_size = (area.height as f32 - 2.0) / content_area.height as f32;
+
+        (scrollbar_x_size.min(1.0), scrollbar_y_size.min(1.0))
+    }
+
     fn render_borders(buffer: &mut Buffer, area: Rect) -> Rect {
         let b = Block::default().borders(Borders::ALL);
 
@@ -46,92 +71,106 @@ impl ScrollView {
         buffer: &mut Buffer,
         position: &Position,
         area: Rect,
-        buffer_to_render: &Buffer,
+        content_area: Rect,
+        (show_x, show_y): (bool, bool),
+        theme: &Theme,
     ) -> Rect {
         let scrollbar_x_start = area.x;
         let scrollbar_x_end = area.x + area.width;
         let scrollbar_y_start = area.y;
         let scrollbar_y_end = area.y + area.height;
-        for i in scrollbar_x_start..scrollbar_x_end - 2 {
+        // the columns of the vertical and the row of the horizontal scrollbar
+        let reserved_x = if show_y { 2 } else { 0 };
+        let reserved_y = if show_x { 1 } else { 0 };
+        let view = Rect::new(
+            scrollbar_x_start,
+            scrollbar_y_start,
+            area.width - reserved_x,
+            area.height - reserved_y,
+        );
+        if !show_x && !show_y {
+            return view;
+        }
+
+        let scrollbar_x_bar_end = if show_x {
+            scrollbar_x_end - reserved_x
+        } else {
+            scrollbar_x_start
+        };
+        for i in scrollbar_x_start..scrollbar_x_bar_end {
             if i == scrollbar_x_start
-                || i == scrollbar_x_end - 3
+                || i == scrollbar_x_bar_end - 1
                 || i == scrollbar_x_start + 1
-                || i == scrollbar_x_end - 4
+                || i == scrollbar_x_bar_end - 2
             {
                 buffer[(i, scrollbar_y_end - 1)] = Cell::new("█")
-                    .set_style(Style::default().fg(Color::White))
+                    .set_style(Style::default().fg(theme.text))
                     .clone();
             } else {
                 buffer[(i, scrollbar_y_end - 1)] = Cell::new("━")
-                    .set_style(Style::default().fg(Color::White))
+                    .set_style(Style::default().fg(theme.text))
                     .clone();
             }
         }
-        for i in scrollbar_y_start..scrollbar_y_end - 1 {
-            if i == scrollbar_y_start || i == scrollbar_y_end - 2 {
+        let scrollbar_y_bar_end = if show_y {
+            scrollbar_y_end - reserved_y
+        } else {
+            scrollbar_y_start
+        };
+        for i in scrollbar_y_start..scrollbar_y_bar_end {
+            if i == scrollbar_y_start || i == scrollbar_y_bar_end - 1 {
                 buffer[(scrollbar_x_end - 2, i)] = Cell::new("██")
-                    .set_style(Style::default().fg(Color::White))
+                    .set_style(Style::default().fg(theme.text))
                     .clone();
             } else {
                 buffer[(scrollbar_x_end - 2, i)] = Cell::new("▕▏")
-                    .set_style(Style::default().fg(Color::White))
+                    .set_style(Style::default().fg(theme.text))
                     .clone();
             }
         }
 
-        let buffer_to_render_width = buffer_to_render.area().width;
-        let buffer_to_render_height = buffer_to_render.area().height;
-
-        let mut scrollbar_x_size = (area.width as f32 - 1.0) / buffer_to_render_width as f32;
-        if scrollbar_x_size > 1.0 {
-            scrollbar_x_size = 1.0;
-        }
-        let mut scrollbar_y_size = (area.height as f32 - 2.0) / buffer_to_render_height as f32;
-        if scrollbar_y_size > 1.0 {
-            scrollbar_y_size = 1.0;
-        }
+        let buffer_to_render_width = content_area.width;
+        let buffer_to_render_height = content_area.height;
+        let (scrollbar_x_size, scrollbar_y_size) = ScrollView::scrollbar_sizes(area, content_area);
 
-        if scrollbar_x_size < 1.0 {
+        if show_x {
             let scrollbar_x_position_start = (position.offset_x as f32
                 / buffer_to_render_width as f32)
-                * (area.width as f32 - 2.0) as f32
+                * (area.width as f32 - 2.0)
                 + area.x as f32;
             let scrollbar_x_position_end =
-                scrollbar_x_position_start + scrollbar_x_size * (area.width as f32 - 2.0) as f32;
+                scrollbar_x_position_start + scrollbar_x_size * (area.width as f32 - 2.0);
 
             for i in scrollbar_x_position_start as u16..scrollbar_x_position_end as u16 {
                 buffer[(i, scrollbar_y_end - 1)] = Cell::new("▒")
-                    .set_style(Style::default().fg(Color::Yellow))
+                    .set_style(Style::default().fg(theme.accent))
                     .clone();
             }
         }
 
-        if scrollbar_y_size < 1.0 {
+        if show_y {
             let scrollbar_y_position_start = (position.offset_y as f32
                 / buffer_to_render_height as f32)
-                * (area.height as f32 - 1.0) as f32
+                * (area.height as f32 - 1.0)
                 + area.y as f32;
             let scrollbar_y_position_end =
-                scrollbar_y_position_start + scrollbar_y_size * (area.height as f32 - 1.0) as f32;
+                scrollbar_y_position_start + scrollbar_y_size * (area.height as f32 - 1.0);
 
             for i in scrollbar_y_position_start as u16..scrollbar_y_position_end as u16 {
                 buffer[(scrollbar_x_end - 2, i)] = Cell::new("▒▒")
-                    .set_style(Style::default().fg(Color::Yellow))
+                    .set_style(Style::default().fg(theme.accent))
                     .clone();
             }
         }
 
-        let bottom_right_corner = "  ";
-        buffer[(scrollbar_x_end - 2, scrollbar_y_end - 1)] = Cell::new(bottom_right_corner)
-            .set_style(Style::default().fg(Color::Yellow))
-            .clone();
+        if show_x && show_y {
+            let bottom_right_corner = "  ";
+            buffer[(scrollbar_x_end - 2, scrollbar_y_end - 1)] = Cell::new(bottom_right_corner)
+                .set_style(Style::default().fg(theme.accent))
+                .clone();
+        }
 
-        Rect::new(
-            scrollbar_x_start,
-            scrollbar_y_start,
-            scrollbar_x_end - scrollbar_x_start - 2,
-            scrollbar_y_end - scrollbar_y_start - 1,
-        )
+        view
     }
 
     fn render_view(
@@ -140,8 +179,8 @@ impl ScrollView {
         area: Rect,
         buffer_to_render: &Buffer,
     ) {
-        for i in 0 + area.x..area.width + area.x {
-            for j in 0 + area.y..area.height + area.y {
+        for i in area.x..area.width + area.x {
+            for j in area.y..area.height + area.y {
                 let cell = buffer_to_render.cell(ratatui::prelude::Position {
                     x: i - area.x + position.offset_x,
                     y: j - area.y + position.offset_y,
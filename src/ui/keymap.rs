@@ -0,0 +1,270 @@
+use directories::ProjectDirs;
+use ratatui::crossterm::event::KeyCode;
+use std::{collections::HashMap, env, fs, path::PathBuf};
+
+/// Action on the home screen that can be bound to a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    MoveUp,
+    MoveDown,
+    Left,
+    Right,
+    Reveal,
+    Details,
+    AutoType,
+    CopyAndQuit,
+    Share,
+    Generate,
+    Add,
+    Duplicate,
+    Delete,
+    Undo,
+    Rename,
+    ReusedPasswords,
+    Health,
+    Privacy,
+    Tags,
+    Qr,
+    OpenUrl,
+    Save,
+    Settings,
+    ChangeMaster,
+    Quit,
+}
+
+impl Action {
+    const ALL: [Action; 31] = [
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Left,
+        Action::Right,
+        Action::Reveal,
+        Action::Details,
+        Action::AutoType,
+        Action::CopyAndQuit,
+        Action::Share,
+        Action::Generate,
+        Action::Add,
+        Action::Duplicate,
+        Action::Delete,
+        Action::Undo,
+        Action::Rename,
+        Action::ReusedPasswords,
+        Action::Health,
+        Action::Privacy,
+        Action::Tags,
+        Action::Qr,
+        Action::OpenUrl,
+        Action::Save,
+        Action::Settings,
+        Action::ChangeMaster,
+        Action::Quit,
+    ];
+
+    // name of the action in the keymap file
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Reveal => "reveal",
+            Action::Details => "details",
+            Action::AutoType => "autotype",
+            Action::CopyAndQuit => "copy_and_quit",
+            Action::Share => "share",
+            Action::Generate => "generate",
+            Action::Add => "add",
+            Action::Duplicate => "duplicate",
+            Action::Delete => "delete",
+            Action::Undo => "undo",
+            Action::Rename => "rename",
+            Action::ReusedPasswords => "reused_passwords",
+            Action::Health => "health",
+            Action::Privacy => "privacy",
+            Action::Tags => "tags",
+            Action::Qr => "qr",
+            Action::OpenUrl => "open_url",
+            Action::Save => "save",
+            Action::Settings => "settings",
+            Action::ChangeMaster => "change_master",
+            Action::Quit => "quit",
+        }
+    }
+}
+
+/// Parse a key like `j`, `enter` or `pagedown`
+pub fn key_from(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    match name.to_lowercase().as_str() {
+        "enter" => Some(KeyCode::Enter),
+        "tab" => Some(KeyCode::Tab),
+        "esc" => Some(KeyCode::Esc),
+        "backspace" => Some(KeyCode::Backspace),
+        "space" => Some(KeyCode::Char(' ')),
+        "up" => Some(KeyCode::Up),
+        "down" => Some(KeyCode::Down),
+        "left" => Some(KeyCode::Left),
+        "right" => Some(KeyCode::Right),
+        "home" => Some(KeyCode::Home),
+        "end" => Some(KeyCode::End),
+        "pageup" => Some(KeyCode::PageUp),
+        "pagedown" => Some(KeyCode::PageDown),
+        _ => None,
+    }
+}
+
+/// Name of a key as shown in the legend
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        key => format!("{:?}", key).to_lowercase(),
+    }
+}
+
+/// Keys bound to the actions of the home screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<Action, KeyCode>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = HashMap::from([
+            (Action::Up, KeyCode::Char('k')),
+            (Action::Down, KeyCode::Char('j')),
+            (Action::PageUp, KeyCode::PageUp),
+            (Action::PageDown, KeyCode::PageDown),
+            (Action::Top, KeyCode::Char('g')),
+            (Action::Bottom, KeyCode::Char('G')),
+            (Action::MoveUp, KeyCode::Char('K')),
+            (Action::MoveDown, KeyCode::Char('J')),
+            (Action::Left, KeyCode::Char('h')),
+            (Action::Right, KeyCode::Char('l')),
+            (Action::Reveal, KeyCode::Enter),
+            (Action::Details, KeyCode::Char('v')),
+            (Action::AutoType, KeyCode::Char('t')),
+            (Action::CopyAndQuit, KeyCode::Char('x')),
+            (Action::Share, KeyCode::Char('y')),
+            (Action::Generate, KeyCode::Char('P')),
+            (Action::Add, KeyCode::Char('a')),
+            (Action::Duplicate, KeyCode::Char('D')),
+            (Action::Delete, KeyCode::Char('d')),
+            (Action::Undo, KeyCode::Char('u')),
+            (Action::Rename, KeyCode::Char('n')),
+            (Action::ReusedPasswords, KeyCode::Char('r')),
+            (Action::Health, KeyCode::Char('H')),
+            (Action::Privacy, KeyCode::Char('p')),
+            (Action::Tags, KeyCode::Char('f')),
+            (Action::Qr, KeyCode::Char('c')),
+            (Action::OpenUrl, KeyCode::Char('o')),
+            (Action::Save, KeyCode::Char('s')),
+            (Action::Settings, KeyCode::Char(',')),
+            (Action::ChangeMaster, KeyCode::Char('M')),
+            (Action::Quit, KeyCode::Char('q')),
+        ]);
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// Load the keymap from `KRAB_KEYMAP` or `keymap.toml` in the config directory
+    ///
+    /// The file maps action names (`up`, `down`, `page_up`, `page_down`, `top`, `bottom`,
+    /// `move_up`, `move_down`, `left`, `right`, `reveal`, `details`, `autotype`,
+    /// `copy_and_quit`, `share`, `generate`, `add`, `duplicate`, `delete`, `undo`,
+    /// `rename`, `reused_passwords`, `health`, `privacy`, `tags`, `qr`, `open_url`,
+    /// `save`, `settings`, `change_master`, `quit`) to keys.
+    /// `top` jumps to the first record when its key is pressed twice.
+    /// Actions that are missing or bound to an unknown key keep their default key.
+    pub fn load() -> Self {
+        let path = match env::var("KRAB_KEYMAP") {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => ProjectDirs::from("", "", "keeper-crabby")
+                .map(|dirs| dirs.config_dir().join("keymap.toml")),
+        };
+        match path.and_then(|path| fs::read_to_string(path).ok()) {
+            Some(content) => Keymap::from_toml(&content),
+            None => Keymap::default(),
+        }
+    }
+
+    fn from_toml(content: &str) -> Self {
+        let file: HashMap<String, String> = toml::from_str(content).unwrap_or_default();
+        let mut keymap = Keymap::default();
+        for action in Action::ALL {
+            if let Some(key) = file.get(action.name()).and_then(|k| key_from(k)) {
+                keymap.bindings.insert(action, key);
+            }
+        }
+
+        keymap
+    }
+
+    /// Key bound to an action
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.bindings[&action]
+    }
+
+    /// Action bound to a key
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|action| self.bindings[action] == key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_from() {
+        assert_eq!(key_from("j"), Some(KeyCode::Char('j')));
+        assert_eq!(key_from("PageDown"), Some(KeyCode::PageDown));
+        assert_eq!(key_from("nope"), None);
+    }
+
+    #[test]
+    fn test_keymap_from_toml() {
+        let keymap = Keymap::from_toml("down = \"down\"\nup = \"nope\"\n");
+
+        assert_eq!(keymap.action(KeyCode::Down), Some(Action::Down));
+        assert_eq!(keymap.action(KeyCode::Char('j')), None);
+        assert_eq!(keymap.key(Action::Up), KeyCode::Char('k'));
+    }
+}
@@ -5,14 +5,36 @@ use ratatui::{crossterm::event::KeyEvent, layout::Rect, Frame};
 
 use crate::Application;
 
+pub mod about_popup;
+pub mod confirm_popup;
+pub mod detail_popup;
 pub mod exit_popup;
+pub mod health_popup;
 pub mod insert_pwd_popup;
+pub mod lock_popup;
+pub mod master_pwd_popup;
 pub mod message_popup;
+pub mod qr_popup;
+pub mod rename_popup;
+pub mod reused_pwd_popup;
+pub mod settings_popup;
+pub mod tag_popup;
 
 pub enum PopupType {
+    About,
+    Confirm,
+    Detail,
     Exit,
+    Health,
     InsertPwd,
+    Lock,
+    MasterPwd,
     Message,
+    Qr,
+    Rename,
+    ReusedPwd,
+    Settings,
+    Tag,
 }
 
 pub trait Popup: DynClone + Downcast {
@@ -23,6 +45,9 @@ pub trait Popup: DynClone + Downcast {
         app: &Application,
     ) -> (Application, Option<Box<dyn Popup>>);
 
+    /// Insert pasted text into the focused field, popups without one ignore it
+    fn handle_paste(&mut self, _text: &str) {}
+
     fn wrapper(&self, rect: Rect) -> Rect;
 
     fn popup_type(&self) -> PopupType;
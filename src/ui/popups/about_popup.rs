@@ -0,0 +1,69 @@
+use ratatui::{
+    crossterm::event::KeyEvent,
+    prelude::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Clear, Padding, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    ui::{
+        centered_rect,
+        popups::{Popup, PopupType},
+    },
+    Application, VERSION,
+};
+
+/// Version of the build and the data directory in use, closed with any key
+#[derive(Clone)]
+pub struct AboutPopup;
+
+impl Popup for AboutPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let label = Style::default().fg(theme.inactive);
+        let text = vec![
+            Line::from(Span::styled("Version", label)),
+            Line::from(Span::raw(VERSION)),
+            Line::from(""),
+            Line::from(Span::styled("Data directory", label)),
+            Line::from(Span::raw(
+                app.immutable_app_state.db_path.display().to_string(),
+            )),
+        ];
+        let about_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .title(format!(
+                        " {} - press any key to close ",
+                        app.immutable_app_state.name
+                    ))
+                    .padding(Padding::uniform(1))
+                    .border_style(Style::default().fg(theme.text)),
+            )
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, rect);
+        f.render_widget(about_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        _key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        app.mutable_app_state.popups.pop();
+
+        (app, None)
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 50, 40)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::About
+    }
+}
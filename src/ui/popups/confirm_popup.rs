@@ -0,0 +1,96 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::{Alignment, Rect},
+    style::Style,
+    widgets::{Block, Clear, Padding, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    ui::{
+        centered_rect,
+        popups::{Popup, PopupType},
+    },
+    Application,
+};
+
+/// Asks a yes or no question
+///
+/// Made with `new` it closes itself and is handed on with `confirmed` set to the
+/// answer. Made with `discarding` it guards closing the popup below it: on `y`
+/// both popups are closed and `on_confirm` is handed on as if the popup below
+/// had closed itself, on `n` or `Esc` the popup below stays open.
+#[derive(Clone)]
+pub struct ConfirmPopup {
+    pub message: String,
+    pub on_confirm: Option<Box<dyn Popup>>,
+    pub confirmed: bool,
+}
+
+impl ConfirmPopup {
+    pub fn new(message: String) -> Self {
+        ConfirmPopup {
+            message,
+            on_confirm: None,
+            confirmed: false,
+        }
+    }
+
+    pub fn discarding(message: String, on_confirm: Box<dyn Popup>) -> Self {
+        ConfirmPopup {
+            message,
+            on_confirm: Some(on_confirm),
+            confirmed: false,
+        }
+    }
+}
+
+impl Popup for ConfirmPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let confirm_p = Paragraph::new(self.message.clone())
+            .block(
+                Block::bordered()
+                    .title(" y: yes | n: no ")
+                    .padding(Padding::new(0, 0, rect.height / 3, 0))
+                    .border_style(Style::default().fg(theme.warning)),
+            )
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(Clear, rect);
+        f.render_widget(confirm_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        let confirmed = match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => false,
+            _ => return (app, None),
+        };
+        app.mutable_app_state.popups.pop();
+        self.confirmed = confirmed;
+
+        match (&self.on_confirm, confirmed) {
+            (Some(on_confirm), true) => {
+                app.mutable_app_state.popups.pop();
+                (app, Some(on_confirm.clone()))
+            }
+            (Some(_), false) => (app, None),
+            (None, _) => (app, Some(Box::new(self.clone()))),
+        }
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 40, 20)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Confirm
+    }
+}
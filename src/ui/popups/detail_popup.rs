@@ -0,0 +1,106 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Clear, Padding, Paragraph, Wrap},
+    Frame,
+};
+use zeroize::Zeroize;
+
+use crate::{
+    crypto::strength::password_entropy_bits,
+    ui::{
+        centered_rect,
+        popups::{Popup, PopupType},
+    },
+    Application,
+};
+
+/// Focused view of the selected record, the password is masked until toggled with space
+#[derive(Clone)]
+pub struct DetailPopup {
+    pub domain: String,
+    pub pwd: String,
+    pub shown: bool,
+}
+
+impl DetailPopup {
+    pub fn new(domain: String, pwd: String) -> Self {
+        DetailPopup {
+            domain,
+            pwd,
+            shown: false,
+        }
+    }
+
+    fn pwd_text(&self) -> String {
+        if self.shown {
+            self.pwd.clone()
+        } else {
+            "•".repeat(self.pwd.chars().count())
+        }
+    }
+}
+
+impl Drop for DetailPopup {
+    fn drop(&mut self) {
+        self.pwd.zeroize();
+    }
+}
+
+impl Popup for DetailPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let label = Style::default().fg(theme.inactive);
+        let text = vec![
+            Line::from(Span::styled("Domain", label)),
+            Line::from(Span::raw(self.domain.clone())),
+            Line::from(""),
+            Line::from(Span::styled(
+                format!("Password (~{:.0} bits)", password_entropy_bits(&self.pwd)),
+                label,
+            )),
+            Line::from(Span::raw(self.pwd_text())),
+        ];
+        let detail_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .title(" Record - space to reveal, esc to close ")
+                    .padding(Padding::uniform(1))
+                    .border_style(Style::default().fg(theme.text)),
+            )
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, rect);
+        f.render_widget(detail_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        app.mutable_app_state.popups.pop();
+
+        match key.code {
+            KeyCode::Esc => return (app, None),
+            KeyCode::Char(' ') => {
+                self.shown = !self.shown;
+            }
+            _ => {}
+        }
+
+        app.mutable_app_state.popups.push(Box::new(self.clone()));
+        (app, None)
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 50, 40)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Detail
+    }
+}
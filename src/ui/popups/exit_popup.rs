@@ -1,7 +1,7 @@
 use ratatui::{
     crossterm::event::{KeyCode, KeyEvent},
     layout::Rect,
-    style::{Color, Style},
+    style::Style,
     widgets::{Block, Borders, Clear},
     Frame,
 };
@@ -14,12 +14,14 @@ use crate::{
     Application,
 };
 
+#[allow(dead_code)]
 #[derive(Clone)]
 pub struct Exit {
     x_percent: u16,
     y_percent: u16,
 }
 
+#[allow(dead_code)]
 impl Exit {
     pub fn new() -> Self {
         Exit {
@@ -30,11 +32,12 @@ impl Exit {
 }
 
 impl Popup for Exit {
-    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
         let block = Block::default()
             .title("Press q to exit")
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::Red));
+            .style(Style::default().fg(theme.error));
         f.render_widget(Clear, rect);
         f.render_widget(block, rect);
     }
@@ -45,11 +48,8 @@ impl Popup for Exit {
         app: &Application,
     ) -> (Application, Option<Box<dyn Popup>>) {
         let mut app = app.clone();
-        match key.code {
-            KeyCode::Char('q') => {
-                app.mutable_app_state.running = false;
-            }
-            _ => {}
+        if key.code == KeyCode::Char('q') {
+            app.mutable_app_state.running = false;
         }
 
         (app, None)
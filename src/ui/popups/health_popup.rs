@@ -0,0 +1,194 @@
+use ratatui::{
+    crossterm::event::KeyEvent,
+    prelude::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, Padding, Paragraph, Wrap},
+    Frame,
+};
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    crypto::user::User,
+    ui::{
+        centered_rect,
+        popups::{Popup, PopupType},
+    },
+    Application,
+};
+
+// result of the breach check running on a worker thread, `None` until it is done
+type PendingBreached = Arc<Mutex<Option<Result<Vec<String>, String>>>>;
+
+/// Report of weak, reused and (with the `hibp-online` feature) breached passwords
+///
+/// Only domains are shown, the passwords stay hidden.
+#[derive(Clone)]
+pub struct HealthPopup {
+    pub weak: Vec<String>,
+    pub reused: Vec<Vec<String>>,
+    pub breached: Option<Result<Vec<String>, String>>,
+    // the breach check asks the API for every password, it runs on a worker
+    // thread so the screen keeps redrawing, see `poll_breached`
+    pending: Option<PendingBreached>,
+}
+
+impl HealthPopup {
+    pub fn new(user: &User) -> Self {
+        #[cfg(feature = "hibp-online")]
+        let pending = {
+            let pending: PendingBreached = Arc::new(Mutex::new(None));
+            let result = Arc::clone(&pending);
+            let user = user.clone();
+            std::thread::spawn(move || {
+                *result.lock().unwrap() = Some(user.breached_passwords());
+            });
+            Some(pending)
+        };
+        #[cfg(not(feature = "hibp-online"))]
+        let pending = None;
+
+        HealthPopup {
+            weak: user.weak_passwords(),
+            reused: user
+                .reused_passwords()
+                .into_iter()
+                .map(|(_, domains)| domains)
+                .collect(),
+            breached: None,
+            pending,
+        }
+    }
+
+    /// Pick up the result of the breach check once it is done
+    ///
+    /// Called on every tick of the main loop while the popup is open.
+    pub fn poll_breached(&mut self) {
+        let result = match &self.pending {
+            Some(pending) => pending.lock().unwrap().take(),
+            None => return,
+        };
+        if result.is_some() {
+            self.breached = result;
+            self.pending = None;
+        }
+    }
+
+    // heading with the count, followed by a line per affected domain (group)
+    fn section(title: &str, items: Vec<String>, color: Color) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(Span::styled(
+            format!("{} ({})", title, items.len()),
+            Style::default().fg(color),
+        ))];
+        lines.extend(
+            items
+                .into_iter()
+                .map(|item| Line::from(format!("  {}", item))),
+        );
+        lines.push(Line::from(""));
+
+        lines
+    }
+
+    fn text(&self, error: Color, warning: Color, info: Color) -> Vec<Line<'static>> {
+        let mut lines = vec![];
+        match &self.breached {
+            Some(Ok(breached)) => {
+                lines.extend(HealthPopup::section("Breached", breached.clone(), error))
+            }
+            Some(Err(e)) => {
+                lines.push(Line::from(Span::styled(
+                    format!("Breached: {}", e),
+                    Style::default().fg(error),
+                )));
+                lines.push(Line::from(""));
+            }
+            None if self.pending.is_some() => {
+                lines.push(Line::from(Span::styled(
+                    "Breached: checking…",
+                    Style::default().fg(error),
+                )));
+                lines.push(Line::from(""));
+            }
+            None => {}
+        }
+        let reused = self
+            .reused
+            .iter()
+            .map(|domains| domains.join(", "))
+            .collect();
+        lines.extend(HealthPopup::section("Reused", reused, warning));
+        lines.extend(HealthPopup::section("Weak", self.weak.clone(), info));
+
+        lines
+    }
+}
+
+impl Popup for HealthPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let health_p = Paragraph::new(self.text(theme.error, theme.warning, theme.info))
+            .block(
+                Block::bordered()
+                    .title(" Vault health - press any key to continue ")
+                    .padding(Padding::uniform(1))
+                    .border_style(Style::default().fg(theme.text)),
+            )
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, rect);
+        f.render_widget(health_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        _key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        app.mutable_app_state.popups.pop();
+
+        (app, None)
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 60, 70)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Health
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the text of the report as plain lines
+    fn lines(popup: &HealthPopup) -> Vec<String> {
+        popup
+            .text(Color::Red, Color::Yellow, Color::Blue)
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_poll_breached() {
+        let pending: PendingBreached = Arc::new(Mutex::new(None));
+        let mut popup = HealthPopup {
+            weak: vec![],
+            reused: vec![],
+            breached: None,
+            pending: Some(Arc::clone(&pending)),
+        };
+
+        popup.poll_breached();
+        assert!(lines(&popup).contains(&"Breached: checking…".to_string()));
+
+        *pending.lock().unwrap() = Some(Ok(vec!["example.com".to_string()]));
+        popup.poll_breached();
+        assert!(popup.pending.is_none());
+        assert_eq!(lines(&popup)[..2], ["Breached (1)", "  example.com"]);
+    }
+}
@@ -1,16 +1,20 @@
 use ratatui::{
-    crossterm::event::{KeyCode, KeyEvent},
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
     prelude::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::Style,
     text::{Line, Span},
     widgets::{Block, Clear, Paragraph},
     Frame,
 };
 
 use crate::{
+    crypto::{
+        generator::{generate_password, PasswordPolicy},
+        strength::password_entropy_bits,
+    },
     ui::{
-        centered_rect,
-        popups::{Popup, PopupType},
+        centered_rect, pop_grapheme,
+        popups::{confirm_popup::ConfirmPopup, Popup, PopupType},
     },
     Application,
 };
@@ -18,6 +22,7 @@ use crate::{
 #[derive(Clone)]
 pub enum InsertPwdState {
     Domain,
+    Label,
     Pwd,
     Confirm,
     Quit,
@@ -32,9 +37,19 @@ pub enum InsertPwdExitState {
 #[derive(Clone)]
 pub struct InsertPwd {
     pub domain: String,
+    /// Tells the record apart from others of the same domain, empty for none
+    pub label: String,
     pub pwd: String,
     pub state: InsertPwdState,
     pub exit_state: Option<InsertPwdExitState>,
+    /// The password is shown as typed instead of masked, toggled with Ctrl+R
+    pub revealed: bool,
+    /// Tags the record is stored with, carried over when a record is duplicated
+    pub tags: Vec<String>,
+    /// What Ctrl+G generates, changed with Alt and `-`, `+`, `u`, `n` or `s`
+    pub policy: PasswordPolicy,
+    // the password was generated, changing the policy generates a new one
+    generated: bool,
     x_percent: u16,
     y_percent: u16,
 }
@@ -43,11 +58,25 @@ impl InsertPwd {
     pub fn new() -> Self {
         InsertPwd {
             domain: String::new(),
+            label: String::new(),
             pwd: String::new(),
             state: InsertPwdState::Domain,
             exit_state: None,
+            revealed: false,
+            tags: vec![],
+            policy: PasswordPolicy::from_env(),
+            generated: false,
             x_percent: 40,
-            y_percent: 20,
+            y_percent: 27,
+        }
+    }
+
+    /// Popup for a copy of a record, with its password and tags and an empty domain
+    pub fn duplicate_of(pwd: String, tags: Vec<String>) -> Self {
+        InsertPwd {
+            pwd,
+            tags,
+            ..InsertPwd::new()
         }
     }
 
@@ -55,68 +84,168 @@ impl InsertPwd {
         self.domain.push(c);
     }
 
+    pub fn label_append(&mut self, c: char) {
+        self.label.push(c);
+    }
+
     pub fn pwd_append(&mut self, c: char) {
         self.pwd.push(c);
+        self.generated = false;
     }
 
     pub fn domain_pop(&mut self) {
-        self.domain.pop();
+        pop_grapheme(&mut self.domain);
+    }
+
+    pub fn label_pop(&mut self) {
+        pop_grapheme(&mut self.label);
     }
 
     pub fn pwd_pop(&mut self) {
-        self.pwd.pop();
+        pop_grapheme(&mut self.pwd);
+        self.generated = false;
+    }
+
+    /// Replace the password with a generated one that matches the policy
+    pub fn generate(&mut self) {
+        self.pwd = generate_password(&self.policy);
+        self.generated = true;
+    }
+
+    // changes the policy for Alt and a key, returns whether the key was one of them
+    fn change_policy(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('+' | '=') => self.policy.longer(),
+            KeyCode::Char('-') => self.policy.shorter(),
+            KeyCode::Char('u') => self.policy.upper = !self.policy.upper,
+            KeyCode::Char('n') => self.policy.digits = !self.policy.digits,
+            KeyCode::Char('s') => self.policy.symbols = !self.policy.symbols,
+            _ => return false,
+        }
+        // a typed password is kept, a generated one follows the policy
+        if self.generated {
+            self.generate();
+        }
+
+        true
+    }
+
+    // the password as rendered, one mask glyph per character unless revealed
+    fn pwd_line(&self) -> String {
+        if self.revealed {
+            self.pwd.clone()
+        } else {
+            "•".repeat(self.pwd.chars().count())
+        }
+    }
+
+    /// Something was typed that would be lost by quitting
+    pub fn has_input(&self) -> bool {
+        !self.domain.is_empty() || !self.label.is_empty() || !self.pwd.is_empty()
+    }
+
+    // closes the popup, after a confirmation if typed input would be lost
+    fn quit(&mut self, app: &mut Application) -> Option<Box<dyn Popup>> {
+        app.mutable_app_state.popups.pop();
+        let mut closed = self.clone();
+        closed.exit_state = Some(InsertPwdExitState::Quit);
+        if self.has_input() {
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            app.mutable_app_state
+                .popups
+                .push(Box::new(ConfirmPopup::discarding(
+                    "Discard the typed record?".to_string(),
+                    Box::new(closed),
+                )));
+            return None;
+        }
+
+        Some(Box::new(closed))
     }
 }
 
 impl Popup for InsertPwd {
-    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
                 Constraint::Min(0),
                 Constraint::Min(0),
                 Constraint::Min(0),
+                Constraint::Min(0),
             ])
             .split(rect);
 
         let text = vec![Line::from(vec![Span::raw(self.domain.clone())])];
-        let domain_p = Paragraph::new(text).block(Block::bordered().title("Domain").border_style(
+        let title = if self.tags.is_empty() {
+            "Domain".to_string()
+        } else {
+            format!("Domain (tags: {})", self.tags.join(", "))
+        };
+        let domain_p = Paragraph::new(text).block(Block::bordered().title(title).border_style(
             Style::default().fg(match self.state {
-                InsertPwdState::Domain => Color::White,
-                _ => Color::DarkGray,
+                InsertPwdState::Domain => theme.text,
+                _ => theme.inactive,
             }),
         ));
 
-        let text = vec![Line::from(vec![Span::raw(self.pwd.clone())])];
-        let pwd_p = Paragraph::new(text).block(Block::bordered().title("Password").border_style(
-            Style::default().fg(match self.state {
-                InsertPwdState::Pwd => Color::White,
-                _ => Color::DarkGray,
-            }),
-        ));
+        let text = vec![Line::from(vec![Span::raw(self.label.clone())])];
+        let label_p = Paragraph::new(text).block(
+            Block::bordered()
+                .title("Label (optional, for another password of a domain)")
+                .border_style(Style::default().fg(match self.state {
+                    InsertPwdState::Label => theme.text,
+                    _ => theme.inactive,
+                })),
+        );
+
+        let text = vec![Line::from(vec![Span::raw(self.pwd_line())])];
+        let mut pwd_block = Block::bordered();
+        if !self.pwd.is_empty() {
+            pwd_block = pwd_block.title_bottom(
+                Line::from(format!(" ~{:.0} bits ", password_entropy_bits(&self.pwd)))
+                    .right_aligned(),
+            );
+        }
+        let pwd_p = Paragraph::new(text).block(
+            pwd_block
+                .title(if self.revealed {
+                    "Password (Ctrl+G: generate, Ctrl+R: hide)"
+                } else {
+                    "Password (Ctrl+G: generate, Ctrl+R: show)"
+                })
+                .title(Line::from(format!(" {} ", self.policy.summary())).right_aligned())
+                .title_bottom(" Alt + -/+: length, u: upper, n: numbers, s: symbols ")
+                .border_style(Style::default().fg(match self.state {
+                    InsertPwdState::Pwd => theme.text,
+                    _ => theme.inactive,
+                })),
+        );
 
         let inner_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
-            .split(layout[2]);
+            .split(layout[3]);
 
         let quit_p = Paragraph::new(Span::raw("Quit")).block(Block::bordered().border_style(
             Style::default().fg(match self.state {
-                InsertPwdState::Quit => Color::White,
-                _ => Color::DarkGray,
+                InsertPwdState::Quit => theme.text,
+                _ => theme.inactive,
             }),
         ));
 
         let confirm_p = Paragraph::new(Span::raw("Confirm")).block(Block::bordered().border_style(
             Style::default().fg(match self.state {
-                InsertPwdState::Confirm => Color::White,
-                _ => Color::DarkGray,
+                InsertPwdState::Confirm => theme.text,
+                _ => theme.inactive,
             }),
         ));
 
         f.render_widget(Clear, rect);
         f.render_widget(domain_p, layout[0]);
-        f.render_widget(pwd_p, layout[1]);
+        f.render_widget(label_p, layout[1]);
+        f.render_widget(pwd_p, layout[2]);
         f.render_widget(quit_p, inner_layout[0]);
         f.render_widget(confirm_p, inner_layout[1]);
     }
@@ -129,6 +258,31 @@ impl Popup for InsertPwd {
         let mut app = app.clone();
         let mut poped = false;
 
+        if key.code == KeyCode::Char('g') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.generate();
+            app.mutable_app_state.popups.pop();
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        if key.modifiers.contains(KeyModifiers::ALT) && self.change_policy(key.code) {
+            app.mutable_app_state.popups.pop();
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        if key.code == KeyCode::Char('r') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.revealed = !self.revealed;
+            app.mutable_app_state.popups.pop();
+            app.mutable_app_state.popups.push(Box::new(self.clone()));
+            return (app, None);
+        }
+
+        if key.code == KeyCode::Esc {
+            let last_state = self.quit(&mut app);
+            return (app, last_state);
+        }
+
         match self.state {
             InsertPwdState::Domain => match key.code {
                 KeyCode::Char(c) => {
@@ -140,6 +294,21 @@ impl Popup for InsertPwd {
                 KeyCode::Up => {
                     self.state = InsertPwdState::Quit;
                 }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Enter => {
+                    self.state = InsertPwdState::Label;
+                }
+                _ => {}
+            },
+            InsertPwdState::Label => match key.code {
+                KeyCode::Char(c) => {
+                    self.label_append(c);
+                }
+                KeyCode::Backspace => {
+                    self.label_pop();
+                }
+                KeyCode::Up => {
+                    self.state = InsertPwdState::Domain;
+                }
                 KeyCode::Down | KeyCode::Tab | KeyCode::Enter => {
                     self.state = InsertPwdState::Pwd;
                 }
@@ -153,7 +322,7 @@ impl Popup for InsertPwd {
                     self.pwd_pop();
                 }
                 KeyCode::Up => {
-                    self.state = InsertPwdState::Domain;
+                    self.state = InsertPwdState::Label;
                 }
                 KeyCode::Down | KeyCode::Tab | KeyCode::Enter => {
                     self.state = InsertPwdState::Quit;
@@ -162,9 +331,8 @@ impl Popup for InsertPwd {
             },
             InsertPwdState::Quit => match key.code {
                 KeyCode::Enter => {
-                    app.mutable_app_state.popups.pop();
-                    self.exit_state = Some(InsertPwdExitState::Quit);
-                    poped = true;
+                    let last_state = self.quit(&mut app);
+                    return (app, last_state);
                 }
                 KeyCode::Up => {
                     self.state = InsertPwdState::Pwd;
@@ -205,6 +373,18 @@ impl Popup for InsertPwd {
         (app, Some(Box::new(self.clone())))
     }
 
+    fn handle_paste(&mut self, text: &str) {
+        match self.state {
+            InsertPwdState::Domain => self.domain.push_str(text),
+            InsertPwdState::Label => self.label.push_str(text),
+            InsertPwdState::Pwd => {
+                self.pwd.push_str(text);
+                self.generated = false;
+            }
+            _ => {}
+        }
+    }
+
     fn wrapper(&self, rect: Rect) -> Rect {
         centered_rect(rect, self.x_percent, self.y_percent)
     }
@@ -213,3 +393,50 @@ impl Popup for InsertPwd {
         PopupType::InsertPwd
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pwd_line_masked() {
+        let mut popup = InsertPwd::new();
+        popup.pwd = "pässword".to_string();
+
+        let masked = popup.pwd_line();
+        popup.revealed = true;
+        let revealed = popup.pwd_line();
+
+        assert_eq!(masked.chars().count(), popup.pwd.chars().count());
+        assert!(masked.chars().all(|c| c == '•'));
+        assert_eq!(revealed, "pässword");
+    }
+
+    #[test]
+    fn test_duplicate_of() {
+        let popup = InsertPwd::duplicate_of("password".to_string(), vec!["work".to_string()]);
+
+        assert!(popup.domain.is_empty());
+        assert_eq!(popup.pwd, "password");
+        assert_eq!(popup.tags, vec!["work".to_string()]);
+        assert!(popup.has_input());
+    }
+
+    #[test]
+    fn test_change_policy() {
+        let mut popup = InsertPwd::new();
+        popup.generate();
+
+        assert!(popup.change_policy(KeyCode::Char('s')));
+        assert!(popup.change_policy(KeyCode::Char('-')));
+        assert!(!popup.change_policy(KeyCode::Char('x')));
+        assert!(!popup.policy.symbols);
+        assert!(popup.policy.matches(&popup.pwd));
+
+        // a typed password is not replaced
+        popup.pwd_append('!');
+        let typed = popup.pwd.clone();
+        popup.change_policy(KeyCode::Char('u'));
+        assert_eq!(popup.pwd, typed);
+    }
+}
@@ -0,0 +1,121 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::{Alignment, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Clear, Padding, Paragraph},
+    Frame,
+};
+use zeroize::Zeroize;
+
+use crate::{
+    ui::{
+        pop_grapheme,
+        popups::{Popup, PopupType},
+        states::ScreenState,
+    },
+    Application,
+};
+
+/// Covers the home screen after inactivity until the master password is entered again
+#[derive(Clone)]
+pub struct LockPopup {
+    pub master_pwd: String,
+    pub wrong_pwd: bool,
+}
+
+impl LockPopup {
+    pub fn new() -> Self {
+        LockPopup {
+            master_pwd: String::new(),
+            wrong_pwd: false,
+        }
+    }
+}
+
+impl Drop for LockPopup {
+    fn drop(&mut self) {
+        self.master_pwd.zeroize();
+    }
+}
+
+impl Popup for LockPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let mut text = vec![
+            Line::from(Span::raw("Enter the master password to continue")),
+            Line::from(Span::raw("•".repeat(self.master_pwd.chars().count()))),
+        ];
+        if self.wrong_pwd {
+            text.push(Line::from(Span::styled(
+                "Wrong master password",
+                Style::default().fg(theme.error),
+            )));
+        }
+        let lock_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .title(" Locked (esc to log out) ")
+                    .padding(Padding::new(0, 0, rect.height / 3, 0))
+                    .border_style(Style::default().fg(theme.text)),
+            )
+            .alignment(Alignment::Center);
+
+        f.render_widget(Clear, rect);
+        f.render_widget(lock_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+
+        match key.code {
+            KeyCode::Char(c) => {
+                self.master_pwd.push(c);
+            }
+            KeyCode::Backspace => {
+                pop_grapheme(&mut self.master_pwd);
+            }
+            KeyCode::Esc => {
+                app.mutable_app_state.popups.pop();
+                if let ScreenState::Home(mut s) = app.state.clone() {
+                    s.logout(&mut app);
+                }
+                return (app, None);
+            }
+            KeyCode::Enter => {
+                let unlocked = match &mut app.state {
+                    ScreenState::Home(s) => s.unlock(&self.master_pwd).is_ok(),
+                    _ => true,
+                };
+                if unlocked {
+                    app.mutable_app_state.popups.pop();
+                    return (app, None);
+                }
+                self.master_pwd.zeroize();
+                self.wrong_pwd = true;
+            }
+            _ => {}
+        }
+
+        app.mutable_app_state.popups.pop();
+        app.mutable_app_state.popups.push(Box::new(self.clone()));
+        (app, None)
+    }
+
+    fn handle_paste(&mut self, text: &str) {
+        self.master_pwd.push_str(text);
+    }
+
+    // covers the whole screen so no domain stays visible
+    fn wrapper(&self, rect: Rect) -> Rect {
+        rect
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Lock
+    }
+}
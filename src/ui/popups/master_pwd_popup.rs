@@ -0,0 +1,108 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::{Alignment, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph},
+    Frame,
+};
+use zeroize::Zeroize;
+
+use crate::{
+    ui::{
+        centered_rect,
+        keymap::Action,
+        pop_grapheme,
+        popups::{Popup, PopupType},
+    },
+    Application,
+};
+
+/// Asks for the master password before an action of the home screen is run
+#[derive(Clone)]
+pub struct MasterPwdPopup {
+    pub master_pwd: String,
+    pub action: Action,
+    pub confirmed: bool,
+    title: String,
+}
+
+impl MasterPwdPopup {
+    pub fn new(action: Action, title: String) -> Self {
+        MasterPwdPopup {
+            master_pwd: String::new(),
+            action,
+            confirmed: false,
+            title,
+        }
+    }
+}
+
+impl Drop for MasterPwdPopup {
+    fn drop(&mut self) {
+        self.master_pwd.zeroize();
+    }
+}
+
+impl Popup for MasterPwdPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let text = vec![
+            Line::from(Span::raw("Master password (esc to cancel)")),
+            Line::from(Span::raw("•".repeat(self.master_pwd.chars().count()))),
+        ];
+        let master_pwd_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .title(format!(" {} ", self.title))
+                    .border_style(Style::default().fg(theme.text)),
+            )
+            .alignment(Alignment::Center);
+
+        f.render_widget(Clear, rect);
+        f.render_widget(master_pwd_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+
+        match key.code {
+            KeyCode::Char(c) => {
+                self.master_pwd.push(c);
+            }
+            KeyCode::Backspace => {
+                pop_grapheme(&mut self.master_pwd);
+            }
+            KeyCode::Enter => {
+                app.mutable_app_state.popups.pop();
+                self.confirmed = true;
+                return (app, Some(Box::new(self.clone())));
+            }
+            KeyCode::Esc => {
+                app.mutable_app_state.popups.pop();
+                return (app, Some(Box::new(self.clone())));
+            }
+            _ => {}
+        }
+
+        app.mutable_app_state.popups.pop();
+        app.mutable_app_state.popups.push(Box::new(self.clone()));
+        (app, None)
+    }
+
+    fn handle_paste(&mut self, text: &str) {
+        self.master_pwd.push_str(text);
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 40, 20)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::MasterPwd
+    }
+}
@@ -1,7 +1,7 @@
 use ratatui::{
     crossterm::event::KeyEvent,
     prelude::{Alignment, Rect},
-    style::{Color, Style},
+    style::Style,
     widgets::{Block, Clear, Padding, Paragraph},
     Frame,
 };
@@ -26,13 +26,14 @@ impl MessagePopup {
 }
 
 impl Popup for MessagePopup {
-    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
         let message_p = Paragraph::new(self.message.clone())
             .block(
                 Block::bordered()
                     .title(" Press any key to continue ")
                     .padding(Padding::new(0, 0, rect.height / 3, 0))
-                    .border_style(Style::default().fg(Color::White)),
+                    .border_style(Style::default().fg(theme.text)),
             )
             .alignment(Alignment::Center);
 
@@ -0,0 +1,135 @@
+use qrcode::{Color, QrCode};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::Rect,
+    style::{Color as TermColor, Style},
+    text::Line,
+    widgets::{Block, Clear, Paragraph},
+    Frame,
+};
+use zeroize::Zeroize;
+
+use crate::{
+    ui::{
+        centered_absolute_rect,
+        popups::{Popup, PopupType},
+    },
+    Application,
+};
+
+// light modules around the code, scanners need some margin to find it
+const QUIET_ZONE: usize = 2;
+
+// draws two rows of modules per line with half blocks, light modules are filled so
+// the code reads right with the light on dark colors it is rendered in
+fn qr_lines(data: &str) -> Result<Vec<String>, String> {
+    let code = match QrCode::new(data.as_bytes()) {
+        Ok(code) => code,
+        Err(_) => return Err("The password is too long for a QR code".to_string()),
+    };
+    let width = code.width();
+    let colors = code.to_colors();
+    let size = width + 2 * QUIET_ZONE;
+    let dark = |x: usize, y: usize| {
+        let in_code = QUIET_ZONE..QUIET_ZONE + width;
+        in_code.contains(&x)
+            && in_code.contains(&y)
+            && colors[(y - QUIET_ZONE) * width + x - QUIET_ZONE] == Color::Dark
+    };
+
+    Ok((0..size)
+        .step_by(2)
+        .map(|y| {
+            (0..size)
+                .map(|x| match (dark(x, y), dark(x, y + 1)) {
+                    (false, false) => '█',
+                    (false, true) => '▀',
+                    (true, false) => '▄',
+                    (true, true) => ' ',
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Password of a record as a QR code, closed with `Esc`
+#[derive(Clone)]
+pub struct QrPopup {
+    pub domain: String,
+    lines: Vec<String>,
+}
+
+impl QrPopup {
+    pub fn new(domain: String, pwd: &str) -> Result<Self, String> {
+        Ok(QrPopup {
+            domain,
+            lines: qr_lines(pwd)?,
+        })
+    }
+}
+
+impl Drop for QrPopup {
+    fn drop(&mut self) {
+        self.lines.zeroize();
+    }
+}
+
+impl Popup for QrPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let lines: Vec<Line> = self
+            .lines
+            .iter()
+            .map(|line| Line::from(line.as_str()))
+            .collect();
+        let qr_p = Paragraph::new(lines)
+            .style(Style::default().fg(TermColor::White).bg(TermColor::Black))
+            .block(
+                Block::bordered()
+                    .title(format!(" {} (esc to close) ", self.domain))
+                    .border_style(Style::default().fg(theme.text)),
+            );
+
+        f.render_widget(Clear, rect);
+        f.render_widget(qr_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        if key.code == KeyCode::Esc {
+            app.mutable_app_state.popups.pop();
+        }
+
+        (app, None)
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        let width = self.lines.first().map_or(0, |line| line.chars().count());
+        centered_absolute_rect(rect, width as u16 + 2, self.lines.len() as u16 + 2)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Qr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qr_lines() {
+        let lines = qr_lines("password").unwrap();
+        // version 1 is 21 modules wide
+        let size = 21 + 2 * QUIET_ZONE;
+
+        assert_eq!(lines.len(), size.div_ceil(2));
+        assert!(lines.iter().all(|line| line.chars().count() == size));
+        // the quiet zone is light
+        assert!(lines[0].chars().all(|c| c == '█'));
+    }
+}
@@ -0,0 +1,138 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph},
+    Frame,
+};
+use zeroize::Zeroize;
+
+use crate::{
+    ui::{
+        centered_rect, pop_grapheme,
+        popups::{Popup, PopupType},
+    },
+    Application,
+};
+
+#[derive(Clone, PartialEq)]
+pub enum RenameState {
+    Domain,
+    MasterPwd,
+}
+
+/// Asks for the new domain of a record and the master password
+#[derive(Clone)]
+pub struct RenamePopup {
+    pub old_domain: String,
+    pub domain: String,
+    pub master_pwd: String,
+    pub state: RenameState,
+    pub confirmed: bool,
+}
+
+impl RenamePopup {
+    pub fn new(old_domain: String) -> Self {
+        RenamePopup {
+            domain: old_domain.clone(),
+            old_domain,
+            master_pwd: String::new(),
+            state: RenameState::Domain,
+            confirmed: false,
+        }
+    }
+}
+
+impl Drop for RenamePopup {
+    fn drop(&mut self) {
+        self.master_pwd.zeroize();
+    }
+}
+
+impl Popup for RenamePopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Min(0), Constraint::Min(0)])
+            .split(rect);
+        let border = |state: RenameState| {
+            Style::default().fg(if self.state == state {
+                theme.text
+            } else {
+                theme.inactive
+            })
+        };
+
+        let text = vec![Line::from(Span::raw(self.domain.clone()))];
+        let domain_p = Paragraph::new(text).block(
+            Block::bordered()
+                .title(format!("Rename {} (esc to cancel)", self.old_domain))
+                .border_style(border(RenameState::Domain)),
+        );
+
+        let text = vec![Line::from(Span::raw(
+            "•".repeat(self.master_pwd.chars().count()),
+        ))];
+        let master_pwd_p = Paragraph::new(text).block(
+            Block::bordered()
+                .title("Master password")
+                .border_style(border(RenameState::MasterPwd)),
+        );
+
+        f.render_widget(Clear, rect);
+        f.render_widget(domain_p, layout[0]);
+        f.render_widget(master_pwd_p, layout[1]);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        app.mutable_app_state.popups.pop();
+
+        match (key.code, &self.state) {
+            (KeyCode::Esc, _) => return (app, Some(Box::new(self.clone()))),
+            (KeyCode::Enter, RenameState::MasterPwd) => {
+                self.confirmed = true;
+                return (app, Some(Box::new(self.clone())));
+            }
+            (KeyCode::Enter | KeyCode::Tab | KeyCode::Down, RenameState::Domain) => {
+                self.state = RenameState::MasterPwd;
+            }
+            (KeyCode::Tab | KeyCode::Up, RenameState::MasterPwd) => {
+                self.state = RenameState::Domain;
+            }
+            (KeyCode::Char(c), RenameState::Domain) => self.domain.push(c),
+            (KeyCode::Char(c), RenameState::MasterPwd) => self.master_pwd.push(c),
+            (KeyCode::Backspace, RenameState::Domain) => {
+                pop_grapheme(&mut self.domain);
+            }
+            (KeyCode::Backspace, RenameState::MasterPwd) => {
+                pop_grapheme(&mut self.master_pwd);
+            }
+            _ => {}
+        }
+
+        app.mutable_app_state.popups.push(Box::new(self.clone()));
+        (app, None)
+    }
+
+    fn handle_paste(&mut self, text: &str) {
+        match self.state {
+            RenameState::Domain => self.domain.push_str(text),
+            RenameState::MasterPwd => self.master_pwd.push_str(text),
+        }
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 40, 15)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Rename
+    }
+}
@@ -0,0 +1,80 @@
+use ratatui::{
+    crossterm::event::KeyEvent,
+    prelude::Rect,
+    style::Style,
+    widgets::{Block, Clear, Padding, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    ui::{
+        centered_rect,
+        popups::{Popup, PopupType},
+    },
+    Application,
+};
+
+/// Read-only report of the domains that share a password, the passwords are not shown
+#[derive(Clone)]
+pub struct ReusedPwdPopup {
+    pub groups: Vec<Vec<String>>,
+}
+
+impl ReusedPwdPopup {
+    pub fn new(reused_passwords: Vec<(String, Vec<String>)>) -> Self {
+        ReusedPwdPopup {
+            groups: reused_passwords
+                .into_iter()
+                .map(|(_, domains)| domains)
+                .collect(),
+        }
+    }
+
+    fn text(&self) -> String {
+        if self.groups.is_empty() {
+            return "No password is used more than once".to_string();
+        }
+
+        self.groups
+            .iter()
+            .map(|domains| format!("Same password: {}", domains.join(", ")))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl Popup for ReusedPwdPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let report_p = Paragraph::new(self.text())
+            .block(
+                Block::bordered()
+                    .title(" Reused passwords - press any key to continue ")
+                    .padding(Padding::uniform(1))
+                    .border_style(Style::default().fg(theme.text)),
+            )
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(Clear, rect);
+        f.render_widget(report_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        _key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        app.mutable_app_state.popups.pop();
+
+        (app, None)
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 60, 50)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::ReusedPwd
+    }
+}
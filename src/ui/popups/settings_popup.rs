@@ -0,0 +1,225 @@
+use directories::ProjectDirs;
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Clear, Padding, Paragraph, Wrap},
+    Frame,
+};
+use std::time::Duration;
+
+use crate::{
+    crypto::{generator::PasswordPolicy, strength::min_record_bits},
+    ui::{
+        centered_rect,
+        clipboard::ClipboardMode,
+        lock_timeout,
+        popups::{Popup, PopupType},
+        states::home_state::{reveal_requires_master_from_env, reveal_timeout, stale_after},
+    },
+    Application,
+};
+
+fn secs(timeout: Option<Duration>) -> String {
+    match timeout {
+        Some(timeout) => format!("{} seconds", timeout.as_secs()),
+        None => "off".to_string(),
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+// options that are read once at start up, with the variable or file they are set with
+fn startup_options(clipboard_disabled: bool) -> Vec<(&'static str, String)> {
+    let clipboard = if clipboard_disabled {
+        "disabled".to_string()
+    } else {
+        match ClipboardMode::from_env() {
+            ClipboardMode::ClearAfterSecs(timeout) => {
+                format!("cleared after {}", secs(Some(timeout)))
+            }
+            ClipboardMode::ClearAfterFirstPaste => format!(
+                "cleared after the first paste, at most {}",
+                secs(ClipboardMode::ClearAfterFirstPaste.timeout())
+            ),
+            ClipboardMode::Never => "kept until something else is copied".to_string(),
+        }
+    };
+    let config_dir = ProjectDirs::from("", "", "keeper-crabby")
+        .map_or("none".to_string(), |dirs| {
+            dirs.config_dir().display().to_string()
+        });
+
+    vec![
+        (
+            "Clipboard (KRAB_CLIPBOARD_MODE, KRAB_CLIPBOARD_SECS)",
+            clipboard,
+        ),
+        ("Reveal timeout (KRAB_REVEAL_SECS)", secs(reveal_timeout())),
+        (
+            "Reveal asks for the master password (KRAB_REVEAL_REQUIRES_MASTER)",
+            on_off(reveal_requires_master_from_env()).to_string(),
+        ),
+        ("Lock after (KRAB_LOCK_SECS)", secs(lock_timeout())),
+        (
+            "Mark passwords older than (KRAB_STALE_DAYS)",
+            stale_after().map_or("off".to_string(), |age| {
+                format!("{} days", age.as_secs() / (24 * 60 * 60))
+            }),
+        ),
+        (
+            "Generated passwords (KRAB_PWD_SYMBOLS)",
+            PasswordPolicy::from_env().summary(),
+        ),
+        (
+            "Record passwords (KRAB_ENFORCE_RECORD_STRENGTH)",
+            min_record_bits().map_or("any is stored".to_string(), |bits| {
+                format!("at least {} bits", bits)
+            }),
+        ),
+        ("Theme and keymap (theme.toml, keymap.toml)", config_dir),
+    ]
+}
+
+/// Options of the current session, the ones of the home screen can be toggled
+///
+/// The toggles are handed back to the home screen when the popup is closed and
+/// last until logout. Options read at start up are only listed, with the variable
+/// they are set with.
+#[derive(Clone)]
+pub struct SettingsPopup {
+    /// Privacy mode of the home screen, `None` outside of it
+    pub privacy: Option<bool>,
+    /// Revealed secrets are hidden when a popup opens, `None` outside the home screen
+    pub hide_on_popup: Option<bool>,
+    options: Vec<(&'static str, String)>,
+}
+
+impl SettingsPopup {
+    /// Settings of the home screen, with its toggles
+    pub fn new(privacy: bool, hide_on_popup: bool, clipboard_disabled: bool) -> Self {
+        SettingsPopup {
+            privacy: Some(privacy),
+            hide_on_popup: Some(hide_on_popup),
+            options: startup_options(clipboard_disabled),
+        }
+    }
+
+    /// Settings outside the home screen, nothing can be toggled
+    pub fn read_only(clipboard_disabled: bool) -> Self {
+        SettingsPopup {
+            privacy: None,
+            hide_on_popup: None,
+            options: startup_options(clipboard_disabled),
+        }
+    }
+
+    // toggles the option of a key, returns whether there was one
+    fn toggle(&mut self, key: KeyCode) -> bool {
+        let option = match key {
+            KeyCode::Char('p') => self.privacy.as_mut(),
+            KeyCode::Char('h') => self.hide_on_popup.as_mut(),
+            _ => None,
+        };
+        match option {
+            Some(value) => {
+                *value = !*value;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Popup for SettingsPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let label = Style::default().fg(theme.inactive);
+        let mut text = vec![];
+        let toggles = [
+            ("p", "Privacy mode (KRAB_PRIVACY)", self.privacy),
+            (
+                "h",
+                "Hide revealed secrets on popups (KRAB_HIDE_ON_POPUP)",
+                self.hide_on_popup,
+            ),
+        ];
+        for (key, name, value) in toggles {
+            if let Some(value) = value {
+                text.push(Line::from(Span::styled(
+                    format!("[{}] {}", key, name),
+                    label,
+                )));
+                text.push(Line::from(format!("    {}", on_off(value))));
+            }
+        }
+        for (name, value) in &self.options {
+            text.push(Line::from(Span::styled(*name, label)));
+            text.push(Line::from(format!("    {}", value)));
+        }
+        let settings_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .title(" Settings (esc to close) ")
+                    .padding(Padding::uniform(1))
+                    .border_style(Style::default().fg(theme.text)),
+            )
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, rect);
+        f.render_widget(settings_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        app.mutable_app_state.popups.pop();
+
+        if matches!(key.code, KeyCode::Esc | KeyCode::Enter) {
+            return (app, Some(Box::new(self.clone())));
+        }
+        self.toggle(key.code);
+
+        app.mutable_app_state.popups.push(Box::new(self.clone()));
+        (app, None)
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 50, 60)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle() {
+        let mut popup = SettingsPopup::new(false, true, false);
+
+        assert!(popup.toggle(KeyCode::Char('p')));
+        assert!(popup.toggle(KeyCode::Char('h')));
+        assert!(!popup.toggle(KeyCode::Char('x')));
+        assert_eq!(popup.privacy, Some(true));
+        assert_eq!(popup.hide_on_popup, Some(false));
+
+        let mut popup = SettingsPopup::read_only(true);
+        assert!(!popup.toggle(KeyCode::Char('p')));
+        assert_eq!(popup.privacy, None);
+        assert_eq!(popup.options[0].1, "disabled");
+    }
+}
@@ -0,0 +1,111 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    prelude::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Clear, Padding, Paragraph},
+    Frame,
+};
+
+use crate::{
+    ui::{
+        centered_rect,
+        popups::{Popup, PopupType},
+    },
+    Application,
+};
+
+/// Picks the tag the home screen is filtered by, the first entry shows all records
+#[derive(Clone)]
+pub struct TagPopup {
+    pub tags: Vec<String>,
+    pub selected: usize,
+    pub confirmed: bool,
+}
+
+impl TagPopup {
+    pub fn new(tags: Vec<String>, current: Option<&str>) -> Self {
+        let selected = match current {
+            Some(current) => tags.iter().position(|t| t == current).map_or(0, |i| i + 1),
+            None => 0,
+        };
+        TagPopup {
+            tags,
+            selected,
+            confirmed: false,
+        }
+    }
+
+    /// The picked tag, `None` to show all records
+    pub fn tag(&self) -> Option<String> {
+        match self.selected {
+            0 => None,
+            i => Some(self.tags[i - 1].clone()),
+        }
+    }
+}
+
+impl Popup for TagPopup {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let lines: Vec<Line> = ["All records".to_string()]
+            .iter()
+            .chain(self.tags.iter())
+            .enumerate()
+            .map(|(i, tag)| {
+                if i == self.selected {
+                    Line::from(Span::styled(
+                        format!("> {}", tag),
+                        Style::default().bg(theme.selected_bg).fg(theme.selected_fg),
+                    ))
+                } else {
+                    Line::from(format!("  {}", tag))
+                }
+            })
+            .collect();
+        let tags_p = Paragraph::new(lines).block(
+            Block::bordered()
+                .title(" Filter by tag (esc to cancel) ")
+                .padding(Padding::uniform(1))
+                .border_style(Style::default().fg(theme.text)),
+        );
+
+        f.render_widget(Clear, rect);
+        f.render_widget(tags_p, rect);
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &KeyEvent,
+        app: &Application,
+    ) -> (Application, Option<Box<dyn Popup>>) {
+        let mut app = app.clone();
+        app.mutable_app_state.popups.pop();
+
+        match key.code {
+            KeyCode::Esc => return (app, Some(Box::new(self.clone()))),
+            KeyCode::Enter => {
+                self.confirmed = true;
+                return (app, Some(Box::new(self.clone())));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.tags.len());
+            }
+            _ => {}
+        }
+
+        app.mutable_app_state.popups.push(Box::new(self.clone()));
+        (app, None)
+    }
+
+    fn wrapper(&self, rect: Rect) -> Rect {
+        centered_rect(rect, 40, 50)
+    }
+
+    fn popup_type(&self) -> PopupType {
+        PopupType::Tag
+    }
+}
@@ -1,15 +1,22 @@
-use ratatui::{crossterm::event::KeyEvent, layout::Rect, Frame};
+use ratatui::{
+    crossterm::event::{KeyEvent, MouseEvent},
+    layout::Rect,
+    Frame,
+};
 
 use crate::{
     ui::{
         popups::Popup,
         states::{
+            change_master_state::ChangeMaster, delete_account_state::DeleteAccount,
             home_state::Home, login_state::Login, register_state::Register, startup_state::StartUp,
         },
     },
     Application,
 };
 
+pub mod change_master_state;
+pub mod delete_account_state;
 pub mod home_state;
 pub mod login_state;
 pub mod register_state;
@@ -21,12 +28,23 @@ pub enum ScreenState {
     StartUp(StartUp),
     Register(Register),
     Home(Home),
+    ChangeMaster(ChangeMaster),
+    DeleteAccount(DeleteAccount),
 }
 
 pub trait State {
     fn render(&self, f: &mut Frame, app: &Application, rect: Rect);
     fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application;
 
+    fn handle_mouse(&mut self, _mouse: &MouseEvent, app: &Application) -> Application {
+        app.clone()
+    }
+
+    /// Insert pasted text into the focused field, states without one ignore it
+    fn handle_paste(&mut self, _text: &str, app: &Application) -> Application {
+        app.clone()
+    }
+
     fn handle_insert_record_popup(
         &mut self,
         _app: Application,
@@ -34,4 +52,28 @@ pub trait State {
     ) -> Application {
         unreachable!("This state does not handle insert record popups");
     }
+
+    fn handle_master_pwd_popup(
+        &mut self,
+        _app: Application,
+        _popup: Box<dyn Popup>,
+    ) -> Application {
+        unreachable!("This state does not handle master password popups");
+    }
+
+    fn handle_rename_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This state does not handle rename popups");
+    }
+
+    fn handle_tag_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This state does not handle tag popups");
+    }
+
+    fn handle_confirm_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This state does not handle confirm popups");
+    }
+
+    fn handle_settings_popup(&mut self, _app: Application, _popup: Box<dyn Popup>) -> Application {
+        unreachable!("This state does not handle settings popups");
+    }
 }
@@ -0,0 +1,231 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+use zeroize::Zeroize;
+
+use crate::{
+    crypto::user::User,
+    ui::{
+        centered_rect, pop_grapheme,
+        popups::message_popup::MessagePopup,
+        states::{
+            home_state::{Home, Position},
+            ScreenState, State,
+        },
+    },
+    Application,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeMasterState {
+    CurrentPassword,
+    NewPassword,
+    ConfirmPassword,
+    Confirm,
+    Quit,
+}
+
+/// Changes the master password of the logged in user, reached from the home screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeMaster {
+    pub home: Home,
+    pub current_password: String,
+    pub new_password: String,
+    pub confirm_password: String,
+    pub state: ChangeMasterState,
+}
+
+impl ChangeMaster {
+    pub fn new(home: Home) -> Self {
+        ChangeMaster {
+            home,
+            current_password: String::new(),
+            new_password: String::new(),
+            confirm_password: String::new(),
+            state: ChangeMasterState::CurrentPassword,
+        }
+    }
+
+    fn field(&mut self) -> Option<&mut String> {
+        match self.state {
+            ChangeMasterState::CurrentPassword => Some(&mut self.current_password),
+            ChangeMasterState::NewPassword => Some(&mut self.new_password),
+            ChangeMasterState::ConfirmPassword => Some(&mut self.confirm_password),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) {
+        self.state = match self.state {
+            ChangeMasterState::CurrentPassword => ChangeMasterState::NewPassword,
+            ChangeMasterState::NewPassword => ChangeMasterState::ConfirmPassword,
+            ChangeMasterState::ConfirmPassword => ChangeMasterState::Quit,
+            ChangeMasterState::Quit => ChangeMasterState::Confirm,
+            ChangeMasterState::Confirm => ChangeMasterState::CurrentPassword,
+        };
+    }
+
+    fn previous(&mut self) {
+        self.state = match self.state {
+            ChangeMasterState::CurrentPassword => ChangeMasterState::Confirm,
+            ChangeMasterState::NewPassword => ChangeMasterState::CurrentPassword,
+            ChangeMasterState::ConfirmPassword => ChangeMasterState::NewPassword,
+            ChangeMasterState::Quit | ChangeMasterState::Confirm => {
+                ChangeMasterState::ConfirmPassword
+            }
+        };
+    }
+
+    // back to the home screen with the user read under the new password
+    fn change(&mut self, app: &mut Application) {
+        if self.new_password != self.confirm_password {
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::new(
+                    "New passwords do not match".to_string(),
+                )));
+            return;
+        }
+
+        let mut user = self.home.user.clone();
+        let res = user
+            .change_master_password(&self.current_password, &self.new_password)
+            .and_then(|_| {
+                User::from(
+                    &app.immutable_app_state.db_path,
+                    &user.username(),
+                    &self.new_password,
+                )
+            });
+        match res {
+            Ok(user) => {
+                app.state = ScreenState::Home(Home::new(user, Position::default(), self.home.area));
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(
+                        "Master password changed".to_string(),
+                    )));
+            }
+            Err(e) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(e)));
+                app.state = ScreenState::ChangeMaster(self.clone());
+            }
+        }
+    }
+}
+
+impl Drop for ChangeMaster {
+    fn drop(&mut self) {
+        self.current_password.zeroize();
+        self.new_password.zeroize();
+        self.confirm_password.zeroize();
+    }
+}
+
+impl State for ChangeMaster {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let rect = centered_rect(rect, 50, 40);
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(5),
+                Constraint::Length(5),
+                Constraint::Length(5),
+                Constraint::Length(5),
+            ])
+            .split(rect);
+        let border = |state: ChangeMasterState| {
+            Style::default().fg(if self.state == state {
+                theme.text
+            } else {
+                theme.inactive
+            })
+        };
+        let masked = |pwd: &str| vec![Line::from(Span::raw("•".repeat(pwd.chars().count())))];
+
+        let current_p = Paragraph::new(masked(&self.current_password)).block(
+            Block::bordered()
+                .title("Current Master Password")
+                .border_style(border(ChangeMasterState::CurrentPassword)),
+        );
+        let new_p = Paragraph::new(masked(&self.new_password)).block(
+            Block::bordered()
+                .title("New Master Password")
+                .border_style(border(ChangeMasterState::NewPassword)),
+        );
+        let confirm_p = Paragraph::new(masked(&self.confirm_password)).block(
+            Block::bordered()
+                .title("Confirm New Master Password")
+                .border_style(border(ChangeMasterState::ConfirmPassword)),
+        );
+
+        let inner_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(layout[3]);
+        let quit_p = Paragraph::new(Span::raw("Quit"))
+            .block(Block::bordered().border_style(border(ChangeMasterState::Quit)));
+        let change_p = Paragraph::new(Span::raw("Confirm"))
+            .block(Block::bordered().border_style(border(ChangeMasterState::Confirm)));
+
+        f.render_widget(current_p, layout[0]);
+        f.render_widget(new_p, layout[1]);
+        f.render_widget(confirm_p, layout[2]);
+        f.render_widget(quit_p, inner_layout[0]);
+        f.render_widget(change_p, inner_layout[1]);
+    }
+
+    fn handle_paste(&mut self, text: &str, app: &Application) -> Application {
+        let mut app = app.clone();
+        if let Some(field) = self.field() {
+            field.push_str(text);
+        }
+        app.state = ScreenState::ChangeMaster(self.clone());
+        app
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {
+        let mut app = app.clone();
+
+        match (key.code, self.state) {
+            (KeyCode::Enter, ChangeMasterState::Quit) | (KeyCode::Esc, _) => {
+                app.state = ScreenState::Home(self.home.clone());
+                return app;
+            }
+            (KeyCode::Enter, ChangeMasterState::Confirm) => {
+                self.change(&mut app);
+                return app;
+            }
+            (KeyCode::Left | KeyCode::Right, ChangeMasterState::Quit) => {
+                self.state = ChangeMasterState::Confirm;
+            }
+            (KeyCode::Left | KeyCode::Right, ChangeMasterState::Confirm) => {
+                self.state = ChangeMasterState::Quit;
+            }
+            (KeyCode::Enter | KeyCode::Tab | KeyCode::Down, _) => self.next(),
+            (KeyCode::Up, _) => self.previous(),
+            (KeyCode::Char(c), _) => {
+                if let Some(field) = self.field() {
+                    field.push(c);
+                }
+            }
+            (KeyCode::Backspace, _) => {
+                if let Some(field) = self.field() {
+                    pop_grapheme(field);
+                }
+            }
+            _ => {}
+        }
+
+        app.state = ScreenState::ChangeMaster(self.clone());
+        app
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    path::{Path, PathBuf},
+    thread,
+};
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::Rect,
+    prelude::{Constraint, Direction, Layout},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+use zeroize::Zeroize;
+
+use crate::{
+    crypto::{check_user, delete_user, user::User},
+    ui::{
+        centered_rect, pop_grapheme,
+        popups::{confirm_popup::ConfirmPopup, message_popup::MessagePopup, Popup},
+        states::{login_state::login_delay, startup_state::StartUp, ScreenState, State},
+    },
+    Application,
+};
+
+// how many times the users file is overwritten, the same as `krab delete`
+const DELETE_PASSES: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeleteAccountState {
+    Username,
+    MasterPassword,
+    Confirm,
+    Quit,
+}
+
+/// Deletes a user after checking the master password, reached from the start up screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteAccount {
+    pub username: String,
+    pub master_password: String,
+    pub state: DeleteAccountState,
+    pub path: PathBuf,
+    pub failed_attempts: u32,
+}
+
+impl DeleteAccount {
+    pub fn new(path: &Path) -> Self {
+        DeleteAccount {
+            username: String::new(),
+            master_password: String::new(),
+            state: DeleteAccountState::Username,
+            path: path.to_path_buf(),
+            failed_attempts: 0,
+        }
+    }
+
+    // a missing user and a wrong master password are reported apart
+    fn verify(&self) -> Result<(), String> {
+        if !check_user(&self.username, &self.path) {
+            return Err("User not found".to_string());
+        }
+        match User::from(&self.path, &self.username, &self.master_password) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Wrong master password".to_string()),
+        }
+    }
+}
+
+impl Drop for DeleteAccount {
+    fn drop(&mut self) {
+        self.master_password.zeroize();
+    }
+}
+
+impl State for DeleteAccount {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
+        let rect = centered_rect(rect, 50, 40);
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(5),
+                Constraint::Length(5),
+                Constraint::Length(5),
+            ])
+            .split(rect);
+        let border = |state: DeleteAccountState| {
+            Style::default().fg(if self.state == state {
+                theme.text
+            } else {
+                theme.inactive
+            })
+        };
+
+        let text = vec![Line::from(Span::raw(self.username.clone()))];
+        let username_p = Paragraph::new(text).block(
+            Block::bordered()
+                .title("Delete account: Username")
+                .border_style(border(DeleteAccountState::Username)),
+        );
+
+        let text = vec![Line::from(Span::raw(
+            "•".repeat(self.master_password.chars().count()),
+        ))];
+        let master_password_p = Paragraph::new(text).block(
+            Block::bordered()
+                .title("Master Password")
+                .border_style(border(DeleteAccountState::MasterPassword)),
+        );
+
+        let inner_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(layout[2]);
+        let quit_p = Paragraph::new(Span::raw("Quit"))
+            .block(Block::bordered().border_style(border(DeleteAccountState::Quit)));
+        let confirm_p = Paragraph::new(Span::raw("Delete"))
+            .block(Block::bordered().border_style(border(DeleteAccountState::Confirm)));
+
+        f.render_widget(username_p, layout[0]);
+        f.render_widget(master_password_p, layout[1]);
+        f.render_widget(quit_p, inner_layout[0]);
+        f.render_widget(confirm_p, inner_layout[1]);
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {
+        let mut app = app.clone();
+
+        match (key.code, self.state) {
+            (KeyCode::Enter, DeleteAccountState::Quit) | (KeyCode::Esc, _) => {
+                app.state = ScreenState::StartUp(StartUp::new(&app.immutable_app_state.db_path));
+                return app;
+            }
+            (KeyCode::Enter, DeleteAccountState::Confirm) => match self.verify() {
+                Ok(_) => {
+                    self.failed_attempts = 0;
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(ConfirmPopup::new(format!(
+                            "Delete the account {} and all its records? This can not be undone.",
+                            self.username
+                        ))));
+                }
+                Err(e) => {
+                    // slows down guessing the master password, like the login
+                    self.failed_attempts += 1;
+                    thread::sleep(login_delay(self.failed_attempts));
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(MessagePopup::new(e)));
+                }
+            },
+            (KeyCode::Enter | KeyCode::Tab | KeyCode::Down, DeleteAccountState::Username) => {
+                self.state = DeleteAccountState::MasterPassword;
+            }
+            (KeyCode::Enter | KeyCode::Tab | KeyCode::Down, DeleteAccountState::MasterPassword) => {
+                self.state = DeleteAccountState::Quit;
+            }
+            (KeyCode::Up, DeleteAccountState::MasterPassword) => {
+                self.state = DeleteAccountState::Username;
+            }
+            (KeyCode::Up, DeleteAccountState::Username) => {
+                self.state = DeleteAccountState::Confirm;
+            }
+            (KeyCode::Left | KeyCode::Right, DeleteAccountState::Quit) => {
+                self.state = DeleteAccountState::Confirm;
+            }
+            (KeyCode::Left | KeyCode::Right, DeleteAccountState::Confirm) => {
+                self.state = DeleteAccountState::Quit;
+            }
+            (KeyCode::Up, DeleteAccountState::Quit | DeleteAccountState::Confirm) => {
+                self.state = DeleteAccountState::MasterPassword;
+            }
+            (KeyCode::Down | KeyCode::Tab, DeleteAccountState::Quit) => {
+                self.state = DeleteAccountState::Confirm;
+            }
+            (KeyCode::Down | KeyCode::Tab, DeleteAccountState::Confirm) => {
+                self.state = DeleteAccountState::Username;
+            }
+            (KeyCode::Char(c), DeleteAccountState::Username) => self.username.push(c),
+            (KeyCode::Char(c), DeleteAccountState::MasterPassword) => self.master_password.push(c),
+            (KeyCode::Backspace, DeleteAccountState::Username) => {
+                pop_grapheme(&mut self.username);
+            }
+            (KeyCode::Backspace, DeleteAccountState::MasterPassword) => {
+                pop_grapheme(&mut self.master_password);
+            }
+            _ => {}
+        }
+
+        app.state = ScreenState::DeleteAccount(self.clone());
+        app
+    }
+
+    fn handle_paste(&mut self, text: &str, app: &Application) -> Application {
+        let mut app = app.clone();
+        match self.state {
+            DeleteAccountState::Username => self.username.push_str(text),
+            DeleteAccountState::MasterPassword => self.master_password.push_str(text),
+            _ => {}
+        }
+        app.state = ScreenState::DeleteAccount(self.clone());
+        app
+    }
+
+    fn handle_confirm_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let mut app = app.clone();
+        let popup = match popup.downcast::<ConfirmPopup>() {
+            Ok(popup) => popup,
+            Err(_) => unreachable!(),
+        };
+        if !popup.confirmed {
+            return app;
+        }
+
+        // the master password may have changed since it was checked
+        let message = match self
+            .verify()
+            .and_then(|_| delete_user(&self.path, &self.username, DELETE_PASSES))
+        {
+            Ok(_) => {
+                app.state = ScreenState::StartUp(StartUp::new(&app.immutable_app_state.db_path));
+                format!("Deleted the account {}", self.username)
+            }
+            Err(e) => e,
+        };
+        app.mutable_app_state
+            .popups
+            .push(Box::new(MessagePopup::new(message)));
+
+        app
+    }
+}
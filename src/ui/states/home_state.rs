@@ -1,46 +1,220 @@
 use ratatui::{
-    crossterm::event::{KeyCode, KeyEvent},
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind},
+    layout::Alignment,
     prelude::{Buffer, Rect},
-    style::{Color, Style},
+    style::Style,
     text::Text,
-    widgets::Widget,
+    widgets::{Block, Paragraph, Widget},
     Frame,
 };
+use std::{
+    env,
+    ops::Range,
+    time::{Duration, Instant},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::{
-    crypto::user::User,
+    crypto::{
+        generator::{generate_password, PasswordPolicy},
+        strength::{check_record_strength, min_record_bits},
+        user::{display_name, Record, RecordOperationConfig, User},
+    },
     ui::{
+        autotype, browser, clipboard,
         components::scrollable_view::ScrollView,
-        states::{login_state::Login, State},
+        keymap::{key_name, Action, Keymap},
+        popups::{
+            confirm_popup::ConfirmPopup,
+            detail_popup::DetailPopup,
+            health_popup::HealthPopup,
+            insert_pwd_popup::{InsertPwd, InsertPwdExitState},
+            master_pwd_popup::MasterPwdPopup,
+            message_popup::MessagePopup,
+            qr_popup::QrPopup,
+            rename_popup::RenamePopup,
+            reused_pwd_popup::ReusedPwdPopup,
+            settings_popup::SettingsPopup,
+            tag_popup::TagPopup,
+            Popup,
+        },
+        states::{change_master_state::ChangeMaster, login_state::Login, State},
+        theme::Theme,
+        view_state::ViewState,
     },
     Application, ScreenState,
 };
 
-const SELECTED_DOMAIN_PWD_BG_COLOR: Color = Color::Rgb(202, 220, 252);
-const SELECTED_DOMAIN_PWD_FG_COLOR: Color = Color::Rgb(0, 36, 107);
 const DOMAIN_PWD_LIST_ITEM_HEIGHT: u16 = 4;
 const RIGHT_MARGIN: u16 = 6;
 const LEFT_PADDING: u16 = 2;
 const MAX_ENTRY_LENGTH: u16 = 32;
 const DOMAIN_PWD_MIDDLE_WIDTH: u16 = 3;
+const CURSOR_OFFSET: u16 = 4;
+// after the master password was entered to reveal a secret, further secrets are
+// revealed without asking again for this long
+const REVEAL_GRACE: Duration = Duration::from_secs(60);
+// the offsets into the list are u16, records past this many are not listed
+const MAX_LISTED_SECRETS: usize = (u16::MAX as usize - 1) / DOMAIN_PWD_LIST_ITEM_HEIGHT as usize;
 
-fn hidden_value(domain: String) -> String {
-    assert!(domain.len() <= MAX_ENTRY_LENGTH as usize);
+// `text` cut to at most `width` columns on a grapheme boundary, a cut is marked with `…`
+fn fit_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut fitted = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        used += grapheme.width();
+        if used + 1 > width {
+            break;
+        }
+        fitted.push_str(grapheme);
+    }
+    fitted.push('…');
 
-    let mut hidden_value = "  ".to_string() + &domain.clone();
+    fitted
+}
+
+// a record as a single line to hand it to someone
+fn share_line(domain: &str, pwd: &str) -> Zeroizing<String> {
+    Zeroizing::new(format!("domain: {} password: {}", domain, pwd))
+}
+
+fn hidden_value(domain: String) -> String {
+    let mut hidden_value = "  ".to_string() + &fit_width(&domain, MAX_ENTRY_LENGTH as usize);
     hidden_value.push_str(" : ");
     for _ in 0..MAX_ENTRY_LENGTH {
-        hidden_value.push_str("•");
+        hidden_value.push('•');
     }
 
     hidden_value
 }
 
+// like `hidden_value` but the domain is masked too, with a fixed length so it is not leaked
+fn private_value() -> String {
+    let hidden = "•".repeat(MAX_ENTRY_LENGTH as usize);
+    format!("  {} : {}", hidden, hidden)
+}
+
+/// Privacy mode is on by default if `KRAB_PRIVACY=1`
+fn privacy_from_env() -> bool {
+    env::var("KRAB_PRIVACY").is_ok_and(|v| v == "1")
+}
+
+/// Revealed secrets are hidden when a popup opens unless `KRAB_HIDE_ON_POPUP=0`
+fn hide_on_popup_from_env() -> bool {
+    env::var("KRAB_HIDE_ON_POPUP").map_or(true, |v| v != "0")
+}
+
+/// Scrollbars are drawn where the list does not fit unless `KRAB_SCROLLBARS=0`
+fn scrollbars_from_env() -> bool {
+    env::var("KRAB_SCROLLBARS").map_or(true, |v| v != "0")
+}
+
+/// Revealing a secret asks for the master password if `KRAB_REVEAL_REQUIRES_MASTER=1`
+pub fn reveal_requires_master_from_env() -> bool {
+    env::var("KRAB_REVEAL_REQUIRES_MASTER").is_ok_and(|v| v == "1")
+}
+
+/// Changes are only written with the save key if `KRAB_DEFER_WRITES=1`
+fn defer_writes_from_env() -> bool {
+    env::var("KRAB_DEFER_WRITES").is_ok_and(|v| v == "1")
+}
+
+/// How long a revealed secret stays shown, set with `KRAB_REVEAL_SECS` (unset or 0 keeps it shown)
+pub fn reveal_timeout() -> Option<Duration> {
+    match env::var("KRAB_REVEAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+    {
+        Some(0) | None => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+    }
+}
+
+// drops the secrets revealed at least `timeout` before `now`
+fn hide_expired(shown: &mut Vec<(usize, Instant)>, timeout: Duration, now: Instant) {
+    shown.retain(|(_, revealed_at)| now.duration_since(*revealed_at) < timeout);
+}
+
+// index `delta` entries away from `selected`, clamped to the list
+fn jump_target(selected: usize, delta: isize, len: usize) -> usize {
+    let last = len.saturating_sub(1) as isize;
+    (selected as isize + delta).clamp(0, last) as usize
+}
+
+/// Passwords not changed for this long are marked in the list, set with
+/// `KRAB_STALE_DAYS` (unset or 0 marks none)
+pub fn stale_after() -> Option<Duration> {
+    match env::var("KRAB_STALE_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        Some(0) | None => None,
+        Some(days) => Some(Duration::from_secs(days.saturating_mul(24 * 60 * 60))),
+    }
+}
+
+// whether each listed secret is stale, none are without a threshold
+fn stale_flags(
+    user: &User,
+    secrets: &[(String, String)],
+    labels: &[String],
+    max_age: Option<Duration>,
+) -> Vec<bool> {
+    let stale = match max_age {
+        Some(max_age) => user.stale_records(max_age),
+        None => return vec![false; secrets.len()],
+    };
+    secrets
+        .iter()
+        .zip(labels)
+        .map(|((domain, _), label)| stale.contains(&display_name(domain, label)))
+        .collect()
+}
+
+// next entry after `selected` whose domain starts with `letter`, ignoring case,
+// wrapping around to the top of the list
+fn next_with_initial(domains: &[&str], selected: usize, letter: char) -> Option<usize> {
+    let starts_with = |domain: &str| {
+        domain
+            .chars()
+            .next()
+            .is_some_and(|c| c.to_lowercase().eq(letter.to_lowercase()))
+    };
+    (1..=domains.len())
+        .map(|step| (selected + step) % domains.len())
+        .find(|&i| starts_with(domains[i]))
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Secrets {
     pub secrets: Vec<(String, String)>,
     pub selected_secret: usize,
-    pub shown_secrets: Vec<usize>,
+    /// Revealed entries and when they were revealed
+    pub shown_secrets: Vec<(usize, Instant)>,
+}
+
+impl Secrets {
+    fn wipe(&mut self) {
+        for (domain, pwd) in self.secrets.iter_mut() {
+            domain.zeroize();
+            pwd.zeroize();
+        }
+        self.secrets.clear();
+        self.shown_secrets.clear();
+        self.selected_secret = 0;
+    }
+}
+
+// copies of the home screen are made on every key, each one wipes its secrets
+impl Drop for Secrets {
+    fn drop(&mut self) {
+        self.wipe();
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -55,15 +229,51 @@ pub struct Home {
     pub secrets: Secrets,
     pub position: Position,
     pub area: Rect,
+    /// The most recently deleted record, kept for undo
+    pub deleted: Option<Record>,
+    /// A first `g` was pressed, a second one jumps to the top
+    pub pending_g: bool,
+    /// Domains are masked too until their entry is revealed
+    pub privacy: bool,
+    /// Only records with this tag are listed
+    pub tag: Option<String>,
+    /// Action waiting for the answer of a confirm popup
+    pub confirming: Option<Action>,
+    /// Domain, label, password and tags of a record waiting for the master password to be added
+    pub adding: Option<(String, String, String, Vec<String>)>,
+    /// Revealed secrets are hidden when a popup opens, so none is left behind it
+    pub hide_on_popup: bool,
+    /// Scrollbars are drawn where the list does not fit, off for narrow terminals
+    pub scrollbars: bool,
+    /// Revealing a secret asks for the master password first
+    pub reveal_requires_master: bool,
+    /// When the master password was last entered to reveal a secret
+    pub reveal_unlocked_at: Option<Instant>,
+    /// Passwords not changed for this long are marked, `None` marks none
+    pub stale_after: Option<Duration>,
+    /// Whether the password of each listed secret is stale
+    pub stale: Vec<bool>,
+    /// Label of each listed secret, empty for none
+    pub labels: Vec<String>,
 }
 
 impl Home {
-    pub fn new(user: User, position: Position, area: Rect) -> Self {
+    pub fn new(mut user: User, position: Position, area: Rect) -> Self {
+        if defer_writes_from_env() {
+            user.defer_writes();
+        }
         let secrets = Secrets {
             secrets: user.records().iter().map(|x| x.secret()).collect(),
             selected_secret: 0,
             shown_secrets: vec![],
         };
+        let labels: Vec<String> = user
+            .records()
+            .iter()
+            .map(|x| x.label().to_string())
+            .collect();
+        let stale_after = stale_after();
+        let stale = stale_flags(&user, &secrets.secrets, &labels, stale_after);
         Self {
             user,
             secrets,
@@ -72,7 +282,444 @@ impl Home {
                 offset_y: position.offset_y,
             },
             area,
+            deleted: None,
+            pending_g: false,
+            privacy: privacy_from_env(),
+            tag: None,
+            confirming: None,
+            adding: None,
+            hide_on_popup: hide_on_popup_from_env(),
+            scrollbars: scrollbars_from_env(),
+            reveal_requires_master: reveal_requires_master_from_env(),
+            reveal_unlocked_at: None,
+            stale_after,
+            stale,
+            labels,
+        }
+    }
+
+    /// Keys of the home screen as currently bound
+    pub fn legend(keymap: &Keymap) -> String {
+        let key = |action| key_name(keymap.key(action));
+        format!(
+            " {}/{} select | {}{}/{} top/bottom | {}/{} scroll | {}/{} move | {} reveal | {} details | {} type | {} copy and quit | {} copy domain and password and quit | {} copy a generated password and quit | {} add | {} duplicate | {} delete | {} undo | {} rename | {} reused passwords | {} health | {} privacy | {} filter by tag | {} qr code | {} open in browser | {} save | {} settings | {} change master password | {} logout ",
+            key(Action::Up),
+            key(Action::Down),
+            key(Action::Top),
+            key(Action::Top),
+            key(Action::Bottom),
+            key(Action::Left),
+            key(Action::Right),
+            key(Action::MoveUp),
+            key(Action::MoveDown),
+            key(Action::Reveal),
+            key(Action::Details),
+            key(Action::AutoType),
+            key(Action::CopyAndQuit),
+            key(Action::Share),
+            key(Action::Generate),
+            key(Action::Add),
+            key(Action::Duplicate),
+            key(Action::Delete),
+            key(Action::Undo),
+            key(Action::Rename),
+            key(Action::ReusedPasswords),
+            key(Action::Health),
+            key(Action::Privacy),
+            key(Action::Tags),
+            key(Action::Qr),
+            key(Action::OpenUrl),
+            key(Action::Save),
+            key(Action::Settings),
+            key(Action::ChangeMaster),
+            key(Action::Quit),
+        )
+    }
+
+    /// Logged in user, number of records and the tag they are filtered by
+    ///
+    /// The username is marked with `*` while there are unsaved changes.
+    pub fn status(&self) -> String {
+        let dirty = if self.user.is_dirty() { "*" } else { "" };
+        // the list stops at `MAX_LISTED_SECRETS`, the count says how many there are
+        let count = |n: usize| {
+            if n > MAX_LISTED_SECRETS {
+                format!("{} (first {} listed)", n, MAX_LISTED_SECRETS)
+            } else {
+                n.to_string()
+            }
+        };
+        let listed = self.secrets.secrets.len();
+        match &self.tag {
+            Some(tag) => {
+                let total = self.user.record_count();
+                format!(
+                    " user: {}{} | records: {} | filtered: {}/{} | tag: {} ",
+                    self.user.username(),
+                    dirty,
+                    total,
+                    count(listed),
+                    total,
+                    tag
+                )
+            }
+            // without a filter every record is listed
+            None => format!(
+                " user: {}{} | records: {} ",
+                self.user.username(),
+                dirty,
+                count(listed)
+            ),
+        }
+    }
+
+    // number of entries in the list, records past `MAX_LISTED_SECRETS` are not listed
+    fn listed_count(&self) -> usize {
+        self.secrets.secrets.len().min(MAX_LISTED_SECRETS)
+    }
+
+    /// The selected record and the scroll position, `None` without records
+    ///
+    /// The record is stored by its domain and label, the tag filter is not kept
+    /// after logout.
+    pub fn view_state(&self) -> Option<ViewState> {
+        let (domain, _) = self.secrets.secrets.get(self.secrets.selected_secret)?;
+        Some(ViewState::new(
+            domain,
+            self.label(self.secrets.selected_secret),
+            self.position.offset_y,
+        ))
+    }
+
+    /// Select the record a view state was left on and scroll back to it, the
+    /// list is scrolled to the top if the record is gone
+    pub fn restore(&mut self, view: &ViewState) {
+        let selected = match self.listed_position(&view.domain, &view.label) {
+            Some(selected) => selected,
+            None => return self.scroll_to_top(),
+        };
+        let (_, inner_buffer_height) = ScrollView::inner_buffer_bounding_box(self.area);
+        let row = selected as u16 * DOMAIN_PWD_LIST_ITEM_HEIGHT;
+        // the terminal may be smaller than when the view was left, the
+        // selected entry is kept in view
+        let lowest = (row + DOMAIN_PWD_LIST_ITEM_HEIGHT + 1).saturating_sub(inner_buffer_height);
+        self.secrets.selected_secret = selected;
+        self.position.offset_y = view.offset_y.min(row).max(lowest);
+    }
+
+    /// Hide all revealed secrets
+    pub fn hide_secrets(&mut self) {
+        self.secrets.shown_secrets.clear();
+    }
+
+    /// Hide the secrets that were revealed for longer than `timeout`
+    pub fn hide_expired_secrets(&mut self, timeout: Duration) {
+        hide_expired(&mut self.secrets.shown_secrets, timeout, Instant::now());
+    }
+
+    // QR code of the selected password, or a message if it does not fit in one
+    fn qr_popup(&self) -> Box<dyn Popup> {
+        let (_, pwd) = &self.secrets.secrets[self.secrets.selected_secret];
+        match QrPopup::new(self.listed_name(self.secrets.selected_secret), pwd) {
+            Ok(popup) => Box::new(popup),
+            Err(e) => Box::new(MessagePopup::new(e)),
+        }
+    }
+
+    // label of a listed secret, empty for none
+    fn label(&self, index: usize) -> &str {
+        self.labels.get(index).map_or("", String::as_str)
+    }
+
+    // name of a listed secret as it is shown, see `display_name`
+    fn listed_name(&self, index: usize) -> String {
+        display_name(&self.secrets.secrets[index].0, self.label(index))
+    }
+
+    // name of a record in prompts and messages, masked in privacy mode like the list
+    fn shown_name(&self, name: &str, masked: &str) -> String {
+        if self.privacy {
+            masked.to_string()
+        } else {
+            name.to_string()
+        }
+    }
+
+    // name of the selected record in prompts, see `shown_name`
+    fn selected_name(&self) -> String {
+        self.shown_name(
+            &self.listed_name(self.secrets.selected_secret),
+            "the selected record",
+        )
+    }
+
+    // asks before an action that would lose the unsaved changes
+    fn confirm_discard(&mut self, action: Action, app: &mut Application) {
+        self.confirming = Some(action);
+        app.mutable_app_state
+            .popups
+            .push(Box::new(ConfirmPopup::new(
+                "There are unsaved changes, discard them?".to_string(),
+            )));
+    }
+
+    fn copy_and_quit(&self, app: &mut Application) {
+        let (_, pwd) = &self.secrets.secrets[self.secrets.selected_secret];
+        Home::quit_with_clipboard(Zeroizing::new(pwd.clone()), app);
+    }
+
+    // copies the selected record as a line to hand it to someone, see `share_line`
+    fn share_and_quit(&self, app: &mut Application) {
+        let (_, pwd) = &self.secrets.secrets[self.secrets.selected_secret];
+        let name = self.listed_name(self.secrets.selected_secret);
+        Home::quit_with_clipboard(share_line(&name, pwd), app);
+    }
+
+    /// Copy a generated password and quit, nothing is stored
+    ///
+    /// The password follows the default policy and is held on the clipboard after
+    /// exiting like a copied record. Also used by the start up screen, no login is needed.
+    pub fn generate_and_quit(app: &mut Application) {
+        if app.immutable_app_state.clipboard_disabled {
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::new(
+                    "Clipboard disabled".to_string(),
+                )));
+            return;
+        }
+        let pwd = Zeroizing::new(generate_password(&PasswordPolicy::from_env()));
+        Home::quit_with_clipboard(pwd, app);
+    }
+
+    /// Wipe the decrypted secrets and go back to the login screen
+    ///
+    /// The listed secrets, a record waiting to be added and the records of the
+    /// user are wiped right away instead of whenever the screen is dropped.
+    pub fn logout(&mut self, app: &mut Application) {
+        self.wipe_secrets();
+        app.state = ScreenState::Login(Login::new(&app.immutable_app_state.db_path));
+    }
+
+    /// Wipe the decrypted secrets while the screen is locked, see `unlock`
+    ///
+    /// Unsaved changes are written first, as the records are read again from
+    /// the file on unlock. If they can not be written the secrets are only hidden.
+    pub fn lock(&mut self) {
+        self.hide_secrets();
+        if self.user.flush().is_err() {
+            return;
+        }
+        self.wipe_secrets();
+    }
+
+    /// Read the secrets again after `lock`
+    ///
+    /// # Arguments
+    ///
+    /// * `master_pwd` - The master password of the user
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Nothing or an error message
+    pub fn unlock(&mut self, master_pwd: &str) -> Result<(), String> {
+        if !self.user.check_master_pwd(master_pwd) {
+            return Err("Wrong master password".to_string());
+        }
+        // the secrets were kept if they could not be saved
+        if self.user.record_count() == 0 {
+            self.user.reload(master_pwd)?;
+            self.reload_secrets();
+        }
+
+        Ok(())
+    }
+
+    // wipes the listed secrets, a record waiting to be added, the undo buffer and
+    // the records of the user
+    fn wipe_secrets(&mut self) {
+        self.secrets.wipe();
+        self.stale.clear();
+        self.labels.clear();
+        if let Some((domain, label, pwd, tags)) = &mut self.adding {
+            domain.zeroize();
+            label.zeroize();
+            pwd.zeroize();
+            tags.zeroize();
         }
+        self.adding = None;
+        self.deleted = None;
+        self.user.wipe();
+    }
+
+    // the text is held on the clipboard after exiting, like every copied secret
+    fn quit_with_clipboard(text: Zeroizing<String>, app: &mut Application) {
+        match clipboard::copy(&text) {
+            Ok(_) => {
+                app.mutable_app_state.clipboard_on_exit = Some(text);
+                app.mutable_app_state.running = false;
+            }
+            Err(e) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(e)));
+            }
+        }
+    }
+
+    fn is_shown(&self, index: usize) -> bool {
+        self.secrets.shown_secrets.iter().any(|(i, _)| *i == index)
+    }
+
+    // reload the secrets after the records of the user or the tag filter changed
+    fn reload_secrets(&mut self) {
+        let records = match &self.tag {
+            Some(tag) => self.user.records_with_tag(tag),
+            None => self.user.records(),
+        };
+        self.secrets.secrets = records.iter().map(|x| x.secret()).collect();
+        self.labels = records.iter().map(|x| x.label().to_string()).collect();
+        self.stale = stale_flags(
+            &self.user,
+            &self.secrets.secrets,
+            &self.labels,
+            self.stale_after,
+        );
+        self.secrets.shown_secrets.clear();
+        if self.secrets.selected_secret >= self.listed_count() {
+            self.secrets.selected_secret = self.listed_count().saturating_sub(1);
+        }
+    }
+
+    fn record_config(
+        &self,
+        master_pwd: &str,
+        domain: &str,
+        label: &str,
+        pwd: &str,
+        app: &Application,
+    ) -> RecordOperationConfig {
+        RecordOperationConfig {
+            label: label.to_string(),
+            ..RecordOperationConfig::new(
+                &self.user.username(),
+                master_pwd,
+                domain,
+                pwd,
+                &app.immutable_app_state.db_path,
+            )
+        }
+    }
+
+    fn delete_selected(&mut self, master_pwd: &str, app: &Application) -> Result<String, String> {
+        let (domain, _) = &self.secrets.secrets[self.secrets.selected_secret];
+        let domain = domain.clone();
+        let label = self.label(self.secrets.selected_secret).to_string();
+        let name = self.listed_name(self.secrets.selected_secret);
+        let record = self
+            .user
+            .records()
+            .into_iter()
+            .find(|r| r.secret().0 == domain && r.label() == label);
+        let config = self.record_config(master_pwd, &domain, &label, "", app);
+        self.user.remove_record(config)?;
+        self.deleted = record;
+        self.reload_secrets();
+
+        Ok(format!("Deleted {}", self.shown_name(&name, "the record")))
+    }
+
+    // domain and label of the selected record and the place in the file of the
+    // listed record it is swapped with, `None` at the top or bottom of the list
+    fn move_target(&self, action: Action) -> Option<(String, String, usize)> {
+        let selected = self.secrets.selected_secret;
+        let neighbour = match action {
+            Action::MoveUp => selected.checked_sub(1)?,
+            _ => selected + 1,
+        };
+        let (neighbour_domain, _) = self.secrets.secrets.get(neighbour)?;
+        let neighbour_label = self.label(neighbour);
+        let (domain, _) = &self.secrets.secrets[selected];
+        // with a tag filter the neighbour in the list is not the one in the file
+        let index = self
+            .user
+            .records()
+            .iter()
+            .position(|r| r.secret().0 == *neighbour_domain && r.label() == neighbour_label)?;
+
+        Some((domain.clone(), self.label(selected).to_string(), index))
+    }
+
+    // moves the selected record up or down, the selection follows it
+    fn move_selected(
+        &mut self,
+        action: Action,
+        master_pwd: &str,
+        app: &Application,
+    ) -> Result<String, String> {
+        let (domain, label, index) = match self.move_target(action) {
+            Some(target) => target,
+            None => return Err("The record can not be moved further".to_string()),
+        };
+        self.user.move_record(&domain, &label, index, master_pwd)?;
+        self.reload_secrets();
+        if let Some(moved) = self.listed_position(&domain, &label) {
+            let area = app.immutable_app_state.rect.unwrap_or(self.area);
+            self.jump(moved as isize - self.secrets.selected_secret as isize, area);
+        }
+
+        let name = display_name(&domain, &label);
+        Ok(format!("Moved {}", self.shown_name(&name, "the record")))
+    }
+
+    // place of a record in the list, `None` if it is not listed
+    fn listed_position(&self, domain: &str, label: &str) -> Option<usize> {
+        (0..self.listed_count())
+            .find(|&index| self.secrets.secrets[index].0 == domain && self.label(index) == label)
+    }
+
+    // adds the record typed into the insert popup
+    fn add_pending(&mut self, master_pwd: &str, app: &Application) -> Result<String, String> {
+        let (domain, label, pwd, tags) = match self.adding.take() {
+            Some(adding) => adding,
+            None => return Err("Nothing to add".to_string()),
+        };
+        let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+        let config = RecordOperationConfig::builder()
+            .username(&self.user.username())
+            .master_pwd(master_pwd)
+            .domain(&domain)
+            .label(&label)
+            .pwd(&pwd)
+            .tags(&tags)
+            .path(&app.immutable_app_state.db_path)
+            .build()?;
+        self.user.add_record(config)?;
+        self.reload_secrets();
+        if let Some(added) = self.listed_position(&domain, &label) {
+            let area = app.immutable_app_state.rect.unwrap_or(self.area);
+            self.jump(added as isize - self.secrets.selected_secret as isize, area);
+        }
+
+        let name = display_name(&domain, &label);
+        Ok(format!("Added {}", self.shown_name(&name, "the record")))
+    }
+
+    fn undo_delete(&mut self, master_pwd: &str, app: &Application) -> Result<String, String> {
+        let deleted = match &self.deleted {
+            Some(deleted) => deleted.clone(),
+            None => return Err("Nothing to undo".to_string()),
+        };
+        let (domain, pwd) = deleted.secret();
+        let config = RecordOperationConfig {
+            tags: deleted.tags().to_vec(),
+            ..self.record_config(master_pwd, &domain, deleted.label(), &pwd, app)
+        };
+        self.user.add_record(config)?;
+        self.deleted = None;
+        self.reload_secrets();
+
+        let name = display_name(&domain, deleted.label());
+        Ok(format!("Restored {}", self.shown_name(&name, "the record")))
     }
 
     fn up(&mut self, area: Rect) {
@@ -86,13 +733,56 @@ impl Home {
         )
     }
 
+    // number of entries that fit in the view
+    fn page_size(area: Rect) -> usize {
+        let (_, inner_buffer_height) = ScrollView::inner_buffer_bounding_box(area);
+        ((inner_buffer_height / DOMAIN_PWD_LIST_ITEM_HEIGHT) as usize).max(1)
+    }
+
+    // moves the selection by `delta` entries, one at a time so the view follows it
+    fn jump(&mut self, delta: isize, area: Rect) {
+        let target = jump_target(self.secrets.selected_secret, delta, self.listed_count());
+        if target == 0 {
+            return self.scroll_to_top();
+        }
+        if target + 1 >= self.listed_count() {
+            return self.scroll_to_bottom(area);
+        }
+        while self.secrets.selected_secret != target {
+            let previous = self.secrets.selected_secret;
+            let next = if target > previous {
+                previous + 1
+            } else {
+                previous - 1
+            };
+            self.set_selected_secret(next, previous, area);
+        }
+    }
+
+    // selects the next entry whose domain starts with `letter`, if there is one
+    fn jump_to_initial(&mut self, letter: char, area: Rect) {
+        if !letter.is_alphabetic() {
+            return;
+        }
+        let domains: Vec<&str> = self.secrets.secrets[..self.listed_count()]
+            .iter()
+            .map(|(domain, _)| domain.as_str())
+            .collect();
+        if let Some(target) = next_with_initial(&domains, self.secrets.selected_secret, letter) {
+            self.jump(
+                target as isize - self.secrets.selected_secret as isize,
+                area,
+            );
+        }
+    }
+
     fn scroll_to_top(&mut self) {
         self.secrets.selected_secret = 0;
         self.position.offset_y = 0;
     }
 
     fn down(&mut self, area: Rect) {
-        if self.secrets.selected_secret == self.secrets.secrets.len() - 1 {
+        if self.secrets.selected_secret + 1 >= self.listed_count() {
             self.scroll_to_bottom(area);
             return;
         }
@@ -105,11 +795,10 @@ impl Home {
 
     fn scroll_to_bottom(&mut self, area: Rect) {
         let (_, inner_buffer_height) = ScrollView::inner_buffer_bounding_box(area);
-        let max_offset_y =
-            self.buffer_to_render().area().height as i32 - inner_buffer_height as i32 + 1;
+        let max_offset_y = self.buffer_area().height as i32 - inner_buffer_height as i32 + 1;
         let max_offset_y = if max_offset_y < 0 { 0 } else { max_offset_y };
         let max_offset_y = max_offset_y as u16;
-        self.secrets.selected_secret = self.secrets.secrets.len() - 1;
+        self.secrets.selected_secret = self.listed_count().saturating_sub(1);
         self.position.offset_y = max_offset_y;
     }
 
@@ -119,59 +808,79 @@ impl Home {
         previous_selected_secret: usize,
         area: Rect,
     ) {
-        assert!(selected_secret < self.secrets.secrets.len());
+        assert!(selected_secret < self.listed_count());
         let (_, inner_buffer_height) = ScrollView::inner_buffer_bounding_box(area);
         let mut position = self.position.clone();
+        let selected_y = selected_secret as u32 * DOMAIN_PWD_LIST_ITEM_HEIGHT as u32;
         if selected_secret > previous_selected_secret {
-            if selected_secret as u16 * DOMAIN_PWD_LIST_ITEM_HEIGHT + 1
-                >= inner_buffer_height + position.offset_y
-            {
+            if selected_y + 1 >= inner_buffer_height as u32 + position.offset_y as u32 {
                 position.offset_y += DOMAIN_PWD_LIST_ITEM_HEIGHT;
             }
-        } else {
-            if selected_secret as u16 * DOMAIN_PWD_LIST_ITEM_HEIGHT + 1 <= position.offset_y {
-                position.offset_y -= DOMAIN_PWD_LIST_ITEM_HEIGHT;
-            }
+        } else if selected_y < position.offset_y as u32 {
+            position.offset_y -= DOMAIN_PWD_LIST_ITEM_HEIGHT;
         }
         self.secrets.selected_secret = selected_secret;
         self.position = position;
     }
 
+    // hiding never asks, revealing only outside the grace period
+    fn reveal_needs_master(&self, now: Instant) -> bool {
+        self.reveal_requires_master
+            && !self.is_shown(self.secrets.selected_secret)
+            && self
+                .reveal_unlocked_at
+                .is_none_or(|at| now.duration_since(at) >= REVEAL_GRACE)
+    }
+
+    // reveals the selected secret if the master password is right
+    fn reveal_with_master(&mut self, master_pwd: &str) -> Result<(), String> {
+        if !self.user.check_master_pwd(master_pwd) {
+            return Err("Wrong master password".to_string());
+        }
+        self.reveal_unlocked_at = Some(Instant::now());
+        if !self.is_shown(self.secrets.selected_secret) {
+            self.toggle_shown_secret();
+        }
+
+        Ok(())
+    }
+
     fn toggle_shown_secret(&mut self) {
         assert!(self.secrets.selected_secret < self.secrets.secrets.len());
 
         let selected_secret = self.secrets.selected_secret;
-        let mut shown_secrets = self.secrets.shown_secrets.clone();
-        if shown_secrets.contains(&selected_secret) {
-            shown_secrets.retain(|&x| x != selected_secret);
+        if self.is_shown(selected_secret) {
+            self.secrets
+                .shown_secrets
+                .retain(|(i, _)| *i != selected_secret);
         } else {
-            shown_secrets.push(selected_secret);
+            self.secrets
+                .shown_secrets
+                .push((selected_secret, Instant::now()));
         }
-
-        self.secrets.shown_secrets = shown_secrets;
     }
 
-    fn separator(&self, width: u16) -> Text {
+    fn separator(&self, width: u16, theme: &Theme) -> Text<'_> {
         let mut separator = String::new();
         for _ in 0..width {
-            separator.push_str("╍");
+            separator.push('╍');
         }
-        Text::styled(separator, Style::default().fg(Color::White))
+        Text::styled(separator, Style::default().fg(theme.text))
     }
 
-    fn current_secret_cursor(&self, height: u16, width: u16, index: u16, style: Style) -> Text {
+    fn current_secret_cursor(&self, height: u16, width: u16, index: u16, style: Style) -> Text<'_> {
         let mut cursor = String::new();
         for _ in 0..height {
             if self.secrets.selected_secret == index as usize {
                 for _ in 0..width - 1 {
-                    cursor.push_str(">");
+                    cursor.push('>');
                 }
-                cursor.push_str("\n");
+                cursor.push('\n');
             } else {
                 for _ in 0..width - 1 {
-                    cursor.push_str(" ");
+                    cursor.push(' ');
                 }
-                cursor.push_str("\n");
+                cursor.push('\n');
             }
         }
         Text::styled(cursor, style)
@@ -188,110 +897,1152 @@ impl Home {
         }
     }
 
-    fn render_secrets(&self, buffer: &mut Buffer, cursor_offset: u16) {
-        let mut y = 0;
-        let mut index = 0;
-        for (key, value) in self.secrets.secrets.iter() {
+    // renders `count` entries from `first` on, at their rows in the whole list
+    fn render_secrets(
+        &self,
+        buffer: &mut Buffer,
+        cursor_offset: u16,
+        first: usize,
+        count: usize,
+        visible: Range<u16>,
+        theme: &Theme,
+    ) {
+        let top = buffer.area().y;
+        let mut y = top;
+        for (index, (key, value)) in self
+            .secrets
+            .secrets
+            .iter()
+            .enumerate()
+            .take(self.listed_count())
+            .skip(first)
+            .take(count)
+        {
             let style = if self.secrets.selected_secret == index {
-                Style::default()
-                    .bg(SELECTED_DOMAIN_PWD_BG_COLOR)
-                    .fg(SELECTED_DOMAIN_PWD_FG_COLOR)
+                Style::default().bg(theme.selected_bg).fg(theme.selected_fg)
             } else {
                 Style::default()
             };
             let cursor = self.current_secret_cursor(3, cursor_offset, index as u16, style);
             let width = self.width();
-            if y == 0 {
+            if y == top {
                 cursor.render(Rect::new(0, y + 1, cursor_offset, 3), buffer);
-                let separator = self.separator(buffer.area().width);
+                let separator = self.separator(buffer.area().width, theme);
                 separator.render(Rect::new(cursor_offset, y, width, 1), buffer);
                 y += 1;
             } else {
                 cursor.render(Rect::new(0, y, cursor_offset, 3), buffer);
             }
-            let text = if self.secrets.shown_secrets.contains(&index) {
-                format!("\n  {} : {}", key, value)
+            // a revealed password is masked on rows scrolled out of the view, the
+            // rendered buffer is larger than the view and could hold it otherwise
+            let name = display_name(key, self.label(index));
+            let text = if self.is_shown(index) && visible.contains(&(y + 1)) {
+                format!("\n  {} : {}", name, value)
+            } else if self.privacy {
+                "\n".to_string() + &private_value()
             } else {
-                "\n".to_string() + &hidden_value(key.to_string())
+                "\n".to_string() + &hidden_value(name)
             };
             let text = Text::styled(text, style);
             text.render(Rect::new(cursor_offset, y, width, 3), buffer);
+            // in the first of the two spaces before the domain
+            if self.stale.get(index).copied().unwrap_or(false) {
+                Text::styled("!", style.fg(theme.error))
+                    .render(Rect::new(cursor_offset, y + 1, 1, 1), buffer);
+            }
             y += 3;
-            let separator = self.separator(buffer.area().width);
+            let separator = self.separator(buffer.area().width, theme);
             separator.render(Rect::new(cursor_offset, y, width, 1), buffer);
             y += 1;
-            index += 1;
         }
     }
 
-    fn buffer_to_render(&self) -> Buffer {
-        let cursor_offset = 4;
-        let secrets_count = self.secrets.secrets.len();
-        let rect = Rect::new(
-            0,
-            0,
-            self.width() + cursor_offset,
-            (secrets_count as u16 * DOMAIN_PWD_LIST_ITEM_HEIGHT) + 1,
+    // area of the whole list, not made with `Rect::new` as that shrinks rects
+    // of more than `u16::MAX` cells
+    fn buffer_area(&self) -> Rect {
+        let height = self.listed_count() as u32 * DOMAIN_PWD_LIST_ITEM_HEIGHT as u32 + 1;
+        Rect {
+            x: 0,
+            y: 0,
+            width: self.width() + CURSOR_OFFSET,
+            height: height.min(u16::MAX as u32) as u16,
+        }
+    }
+
+    // a buffer holds at most `u16::MAX` cells, so only the entries in a view of
+    // `view_height` rows are rendered, into a buffer placed at their rows
+    fn buffer_to_render(&self, theme: &Theme, view_height: u16) -> Buffer {
+        let area = self.buffer_area();
+        let first = self.position.offset_y / DOMAIN_PWD_LIST_ITEM_HEIGHT;
+        let count = view_height / DOMAIN_PWD_LIST_ITEM_HEIGHT + 2;
+        let top = first * DOMAIN_PWD_LIST_ITEM_HEIGHT;
+        let height = (count * DOMAIN_PWD_LIST_ITEM_HEIGHT + 1).min(area.height.saturating_sub(top));
+        let mut buffer = Buffer::empty(Rect::new(0, top, area.width, height));
+        self.render_secrets(
+            &mut buffer,
+            CURSOR_OFFSET,
+            first as usize,
+            count as usize,
+            self.position.offset_y..self.position.offset_y.saturating_add(view_height),
+            theme,
         );
-        let mut buffer = Buffer::empty(rect);
-        self.render_secrets(&mut buffer, cursor_offset);
 
         buffer
     }
+
+    // the list with its border and scrollbars
+    fn render_list(&self, buffer: &mut Buffer, area: Rect, theme: &Theme) {
+        let (_, view_height) = ScrollView::inner_buffer_bounding_box(area);
+        let buffer_to_render = self.buffer_to_render(theme, view_height);
+        ScrollView::render(
+            buffer,
+            &self.position,
+            area,
+            self.buffer_area(),
+            &buffer_to_render,
+            theme,
+            self.scrollbars,
+        );
+    }
+
+    // a message in place of the list when no record is listed
+    fn render_empty(&self, buffer: &mut Buffer, area: Rect, theme: &Theme, add_key: &str) {
+        let block = Block::bordered().border_style(Style::default().fg(theme.text));
+        let inner = block.inner(area);
+        block.render(area, buffer);
+        let message = Paragraph::new(format!("No records — press '{}' to add one", add_key))
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.inactive));
+        message.render(
+            Rect {
+                y: inner.y + inner.height / 2,
+                height: inner.height.min(1),
+                ..inner
+            },
+            buffer,
+        );
+    }
+}
+
+// actions on the selected record, there is none to act on in an empty list
+fn needs_selection(action: Action) -> bool {
+    matches!(
+        action,
+        Action::MoveUp
+            | Action::MoveDown
+            | Action::Reveal
+            | Action::Details
+            | Action::AutoType
+            | Action::CopyAndQuit
+            | Action::Share
+            | Action::Duplicate
+            | Action::Delete
+            | Action::Rename
+            | Action::Qr
+            | Action::OpenUrl
+    )
 }
 
 impl State for Home {
     fn render(&self, f: &mut Frame, app: &Application, area: Rect) {
-        match app.immutable_app_state.rect {
-            Some(_) => {
-                let mut buffer = f.buffer_mut();
-                let buffer_to_render = self.buffer_to_render();
-                ScrollView::render(&mut buffer, &self.position, area, &buffer_to_render);
-            }
-            None => {}
+        if app.immutable_app_state.rect.is_none() {
+            return;
+        }
+        let theme = &app.immutable_app_state.theme;
+        if self.listed_count() == 0 {
+            let add_key = key_name(app.immutable_app_state.keymap.key(Action::Add));
+            self.render_empty(f.buffer_mut(), area, theme, &add_key);
+        } else {
+            self.render_list(f.buffer_mut(), area, theme);
         }
     }
 
     fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {
         let mut app = app.clone();
         let mut change_state = false;
+        let area = app.immutable_app_state.rect.unwrap();
+        let popups = app.mutable_app_state.popups.len();
 
-        // TODO: rework this
-        if key.code == KeyCode::Char('q') {
-            app.state = ScreenState::Login(Login::new(&app.immutable_app_state.db_path));
-            change_state = true;
-        }
-        if key.code == KeyCode::Char('j') {
-            self.down(app.immutable_app_state.rect.unwrap());
+        // the top key has to be pressed twice (vim style gg), any other key cancels it
+        let pending_g = std::mem::take(&mut self.pending_g);
+
+        // half pages are fixed to ctrl+d and ctrl+u, the keymap has no modifiers
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            let half_page = (Home::page_size(area) / 2).max(1) as isize;
+            match key.code {
+                KeyCode::Char('d') => self.jump(half_page, area),
+                KeyCode::Char('u') => self.jump(-half_page, area),
+                _ => {}
+            }
+            app.state = ScreenState::Home(self.clone());
+            return app;
         }
-        if key.code == KeyCode::Char('k') {
-            self.up(app.immutable_app_state.rect.unwrap());
+
+        let action = app.immutable_app_state.keymap.action(key.code);
+        if self.listed_count() == 0 && action.is_some_and(needs_selection) {
+            app.state = ScreenState::Home(self.clone());
+            return app;
         }
-        if key.code == KeyCode::Char('h') {
-            if self.position.offset_x != 0 {
+        match action {
+            Some(Action::Quit) if self.user.is_dirty() => {
+                self.confirm_discard(Action::Quit, &mut app);
+            }
+            Some(Action::Quit) => {
+                self.logout(&mut app);
+                change_state = true;
+            }
+            Some(Action::Down) => {
+                self.down(app.immutable_app_state.rect.unwrap());
+            }
+            Some(Action::Up) => {
+                self.up(app.immutable_app_state.rect.unwrap());
+            }
+            Some(Action::PageDown) => {
+                self.jump(Home::page_size(area) as isize, area);
+            }
+            Some(Action::PageUp) => {
+                self.jump(-(Home::page_size(area) as isize), area);
+            }
+            Some(Action::Top) if pending_g => {
+                self.scroll_to_top();
+            }
+            Some(Action::Top) => {
+                self.pending_g = true;
+            }
+            Some(Action::Bottom) => {
+                self.scroll_to_bottom(area);
+            }
+            Some(Action::Left) if self.position.offset_x != 0 => {
                 self.position.offset_x -= 1;
             }
-        }
-        if key.code == KeyCode::Char('l') {
-            if !ScrollView::check_if_width_out_of_bounds(
-                &self.position,
-                &self.buffer_to_render(),
-                self.area,
-            ) {
+            Some(Action::Right)
+                if !ScrollView::check_if_width_out_of_bounds(
+                    &self.position,
+                    self.buffer_area(),
+                    self.area,
+                ) =>
+            {
                 self.position.offset_x += 1;
             }
+            Some(Action::Reveal) if self.reveal_needs_master(Instant::now()) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MasterPwdPopup::new(
+                        Action::Reveal,
+                        format!("Reveal {}", self.selected_name()),
+                    )));
+            }
+            Some(Action::Reveal) => {
+                self.toggle_shown_secret();
+            }
+            Some(Action::ChangeMaster) => {
+                self.hide_secrets();
+                app.state = ScreenState::ChangeMaster(ChangeMaster::new(self.clone()));
+                change_state = true;
+            }
+            Some(Action::Privacy) => {
+                self.privacy = !self.privacy;
+            }
+            // a hidden password is only shown as a QR code after a confirmation
+            Some(Action::Qr) if self.is_shown(self.secrets.selected_secret) => {
+                app.mutable_app_state.popups.push(self.qr_popup());
+            }
+            Some(Action::Qr) => {
+                self.confirming = Some(Action::Qr);
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(ConfirmPopup::new(format!(
+                        "Show the password of {} as a QR code?",
+                        self.selected_name()
+                    ))));
+            }
+            Some(Action::OpenUrl) => {
+                let (domain, _) = &self.secrets.secrets[self.secrets.selected_secret];
+                let message = match browser::open_domain(domain) {
+                    Ok(_) if self.privacy => {
+                        "Opened the selected record in the browser".to_string()
+                    }
+                    Ok(url) => format!("Opened {} in the browser", url),
+                    Err(e) => e,
+                };
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(message)));
+            }
+            Some(Action::Tags) => {
+                let tags = self.user.tags();
+                if tags.is_empty() {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(MessagePopup::new(
+                            "No record has tags".to_string(),
+                        )));
+                } else {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(TagPopup::new(tags, self.tag.as_deref())));
+                }
+            }
+            Some(Action::Details) => {
+                let (_, pwd) = self.secrets.secrets[self.secrets.selected_secret].clone();
+                let name = self.listed_name(self.secrets.selected_secret);
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(DetailPopup::new(name, pwd)));
+            }
+            Some(Action::AutoType) => {
+                let (_, pwd) = &self.secrets.secrets[self.secrets.selected_secret];
+                let message = match autotype::type_password(pwd) {
+                    Ok(_) => format!(
+                        "Focus the target window, typing the password of {}",
+                        self.selected_name()
+                    ),
+                    Err(e) => e,
+                };
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(message)));
+            }
+            // without the clipboard the password is typed or revealed in place
+            Some(Action::CopyAndQuit) if app.immutable_app_state.clipboard_disabled => {
+                let (_, pwd) = &self.secrets.secrets[self.secrets.selected_secret];
+                let message = if autotype::enabled() {
+                    Some(match autotype::type_password(pwd) {
+                        Ok(_) => format!(
+                            "Clipboard disabled, focus the target window, typing the password of {}",
+                            self.selected_name()
+                        ),
+                        Err(e) => e,
+                    })
+                } else if !self.privacy {
+                    if !self.is_shown(self.secrets.selected_secret) {
+                        self.toggle_shown_secret();
+                    }
+                    None
+                } else {
+                    Some("Clipboard disabled".to_string())
+                };
+                if let Some(message) = message {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(MessagePopup::new(message)));
+                }
+            }
+            Some(Action::CopyAndQuit) if self.user.is_dirty() => {
+                self.confirm_discard(Action::CopyAndQuit, &mut app);
+            }
+            Some(Action::CopyAndQuit) => self.copy_and_quit(&mut app),
+            Some(Action::Share) if app.immutable_app_state.clipboard_disabled => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(
+                        "Clipboard disabled".to_string(),
+                    )));
+            }
+            // in privacy mode the domain and password are only copied together
+            // after a confirmation, which also covers discarding unsaved changes
+            Some(Action::Share) if self.privacy => {
+                self.confirming = Some(Action::Share);
+                let discard = if self.user.is_dirty() {
+                    " There are unsaved changes, they are discarded."
+                } else {
+                    ""
+                };
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(ConfirmPopup::new(format!(
+                        "Copy the domain and password of the selected record and quit?{}",
+                        discard
+                    ))));
+            }
+            Some(Action::Share) if self.user.is_dirty() => {
+                self.confirm_discard(Action::Share, &mut app);
+            }
+            Some(Action::Share) => self.share_and_quit(&mut app),
+            Some(Action::Generate) if self.user.is_dirty() => {
+                self.confirm_discard(Action::Generate, &mut app);
+            }
+            Some(Action::Generate) => Home::generate_and_quit(&mut app),
+            Some(Action::Save) => {
+                let message = match self.user.flush() {
+                    Ok(_) => "Saved".to_string(),
+                    Err(e) => e,
+                };
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(message)));
+            }
+            Some(Action::Settings) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(SettingsPopup::new(
+                        self.privacy,
+                        self.hide_on_popup,
+                        app.immutable_app_state.clipboard_disabled,
+                    )));
+            }
+            Some(Action::Add) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(InsertPwd::new()));
+            }
+            Some(Action::Duplicate) => {
+                let (domain, pwd) = self.secrets.secrets[self.secrets.selected_secret].clone();
+                let label = self.label(self.secrets.selected_secret);
+                let tags = self
+                    .user
+                    .records()
+                    .iter()
+                    .find(|r| r.secret().0 == domain && r.label() == label)
+                    .map_or(vec![], |r| r.tags().to_vec());
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(InsertPwd::duplicate_of(pwd, tags)));
+            }
+            Some(action @ (Action::MoveUp | Action::MoveDown)) => match self.move_target(action) {
+                Some(_) => {
+                    let direction = match action {
+                        Action::MoveUp => "up",
+                        _ => "down",
+                    };
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(MasterPwdPopup::new(
+                            action,
+                            format!("Move {} {}", self.selected_name(), direction),
+                        )));
+                }
+                None => app
+                    .mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(
+                        "The record can not be moved further".to_string(),
+                    ))),
+            },
+            Some(Action::Delete) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MasterPwdPopup::new(
+                        Action::Delete,
+                        format!("Delete {}", self.selected_name()),
+                    )));
+            }
+            Some(Action::Undo) => match &self.deleted {
+                Some(record) => {
+                    let name = display_name(&record.secret().0, record.label());
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(MasterPwdPopup::new(
+                            Action::Undo,
+                            format!("Restore {}", self.shown_name(&name, "the deleted record")),
+                        )));
+                }
+                None => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(MessagePopup::new("Nothing to undo".to_string())));
+                }
+            },
+            Some(Action::Rename) => {
+                let (domain, _) = &self.secrets.secrets[self.secrets.selected_secret];
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(RenamePopup::new(domain.clone())));
+            }
+            Some(Action::Health) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(HealthPopup::new(&self.user)));
+            }
+            Some(Action::ReusedPasswords) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(ReusedPwdPopup::new(self.user.reused_passwords())));
+            }
+            // a letter no action is bound to jumps to the next domain starting with it
+            None => {
+                if let KeyCode::Char(letter) = key.code {
+                    self.jump_to_initial(letter, area);
+                }
+            }
+            _ => {}
         }
-        if key.code == KeyCode::Enter {
-            self.toggle_shown_secret();
+
+        // nothing is shown again when the popup closes
+        if self.hide_on_popup && app.mutable_app_state.popups.len() > popups {
+            self.hide_secrets();
         }
-        if key.code == KeyCode::Char('a') {
-            //TODO: add new record
+        if !change_state {
+            app.state = ScreenState::Home(self.clone());
         }
 
-        if !change_state {
+        app
+    }
+
+    fn handle_master_pwd_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let mut app = app.clone();
+        let popup = match popup.downcast::<MasterPwdPopup>() {
+            Ok(popup) => popup,
+            Err(_) => unreachable!(),
+        };
+        if !popup.confirmed {
+            // a cancelled record is not kept around
+            if self.adding.take().is_some() {
+                app.state = ScreenState::Home(self.clone());
+            }
+            return app;
+        }
+        if popup.action == Action::Reveal {
+            if let Err(e) = self.reveal_with_master(&popup.master_pwd) {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(e)));
+            }
             app.state = ScreenState::Home(self.clone());
+            return app;
+        }
+
+        let res = match popup.action {
+            Action::Delete => self.delete_selected(&popup.master_pwd, &app),
+            Action::Undo => self.undo_delete(&popup.master_pwd, &app),
+            Action::Add => self.add_pending(&popup.master_pwd, &app),
+            Action::MoveUp | Action::MoveDown => {
+                self.move_selected(popup.action, &popup.master_pwd, &app)
+            }
+            _ => unreachable!("No master password is asked for this action"),
+        };
+        let message = match res {
+            Ok(message) => message,
+            Err(e) => e,
+        };
+        app.mutable_app_state
+            .popups
+            .push(Box::new(MessagePopup::new(message)));
+        app.state = ScreenState::Home(self.clone());
+
+        app
+    }
+
+    fn handle_insert_record_popup(
+        &mut self,
+        app: Application,
+        popup: Box<dyn Popup>,
+    ) -> Application {
+        let mut app = app.clone();
+        let popup = match popup.downcast::<InsertPwd>() {
+            Ok(popup) => popup,
+            Err(_) => unreachable!(),
+        };
+        if popup.exit_state != Some(InsertPwdExitState::Confirm) {
+            return app;
+        }
+
+        // the popup is opened again so the domain can be changed
+        let error = if popup.domain.is_empty() {
+            Some("Domain can not be empty".to_string())
+        } else if self
+            .user
+            .records()
+            .iter()
+            .any(|r| r.secret().0 == popup.domain && r.label() == popup.label)
+        {
+            Some("Record already exists".to_string())
+        } else {
+            check_record_strength(&popup.pwd, min_record_bits())
+                .err()
+                .map(|e| format!("{}, Ctrl+G in the password field generates one", e))
+        };
+        if let Some(error) = error {
+            let mut reopened = popup.clone();
+            reopened.exit_state = None;
+            app.mutable_app_state.popups.push(reopened);
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::new(error)));
+            return app;
+        }
+
+        self.adding = Some((
+            popup.domain.clone(),
+            popup.label.clone(),
+            popup.pwd.clone(),
+            popup.tags.clone(),
+        ));
+        app.mutable_app_state
+            .popups
+            .push(Box::new(MasterPwdPopup::new(
+                Action::Add,
+                format!("Add {}", display_name(&popup.domain, &popup.label)),
+            )));
+        app.state = ScreenState::Home(self.clone());
+
+        app
+    }
+
+    fn handle_rename_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let mut app = app.clone();
+        let popup = match popup.downcast::<RenamePopup>() {
+            Ok(popup) => popup,
+            Err(_) => unreachable!(),
+        };
+        if !popup.confirmed || popup.domain == popup.old_domain {
+            return app;
+        }
+
+        // the selection does not change while the popup is open
+        let label = self.label(self.secrets.selected_secret).to_string();
+        let res =
+            self.user
+                .rename_domain(&popup.old_domain, &label, &popup.domain, &popup.master_pwd);
+        match res {
+            Ok(_) => {
+                let selected_secret = self.secrets.selected_secret;
+                self.reload_secrets();
+                self.secrets.selected_secret = selected_secret;
+            }
+            Err(e) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(e)));
+            }
+        }
+        app.state = ScreenState::Home(self.clone());
+
+        app
+    }
+
+    fn handle_confirm_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let mut app = app.clone();
+        let popup = match popup.downcast::<ConfirmPopup>() {
+            Ok(popup) => popup,
+            Err(_) => unreachable!(),
+        };
+        let action = self.confirming.take();
+        if popup.confirmed {
+            match action {
+                Some(Action::Quit) => {
+                    self.logout(&mut app);
+                    return app;
+                }
+                Some(Action::CopyAndQuit) => self.copy_and_quit(&mut app),
+                Some(Action::Share) => self.share_and_quit(&mut app),
+                Some(Action::Generate) => Home::generate_and_quit(&mut app),
+                Some(Action::Qr) => app.mutable_app_state.popups.push(self.qr_popup()),
+                _ => {}
+            }
+        }
+        app.state = ScreenState::Home(self.clone());
+
+        app
+    }
+
+    fn handle_settings_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let mut app = app.clone();
+        let popup = match popup.downcast::<SettingsPopup>() {
+            Ok(popup) => popup,
+            Err(_) => unreachable!(),
+        };
+        self.privacy = popup.privacy.unwrap_or(self.privacy);
+        self.hide_on_popup = popup.hide_on_popup.unwrap_or(self.hide_on_popup);
+        app.state = ScreenState::Home(self.clone());
+
+        app
+    }
+
+    fn handle_tag_popup(&mut self, app: Application, popup: Box<dyn Popup>) -> Application {
+        let mut app = app.clone();
+        let popup = match popup.downcast::<TagPopup>() {
+            Ok(popup) => popup,
+            Err(_) => unreachable!(),
+        };
+        if !popup.confirmed {
+            return app;
+        }
+
+        self.tag = popup.tag();
+        self.reload_secrets();
+        self.scroll_to_top();
+        app.state = ScreenState::Home(self.clone());
+
+        app
+    }
+
+    fn handle_mouse(&mut self, mouse: &MouseEvent, app: &Application) -> Application {
+        let mut app = app.clone();
+
+        match mouse.kind {
+            MouseEventKind::ScrollDown => {
+                self.down(app.immutable_app_state.rect.unwrap());
+            }
+            MouseEventKind::ScrollUp => {
+                self.up(app.immutable_app_state.rect.unwrap());
+            }
+            _ => {}
         }
 
+        app.state = ScreenState::Home(self.clone());
+
         app
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use dotenv::dotenv;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+    use crate::crypto::delete_user;
+
+    #[test]
+    fn test_jump_target() {
+        assert_eq!(jump_target(5, 3, 20), 8);
+        assert_eq!(jump_target(5, -3, 20), 2);
+    }
+
+    #[test]
+    fn test_next_with_initial() {
+        let domains = [
+            "amazon.com",
+            "Bank",
+            "bitbucket.org",
+            "github.com",
+            "bing.com",
+        ];
+
+        assert_eq!(next_with_initial(&domains, 0, 'b'), Some(1));
+        assert_eq!(next_with_initial(&domains, 1, 'B'), Some(2));
+        assert_eq!(next_with_initial(&domains, 2, 'b'), Some(4));
+        // cycles back to the first match
+        assert_eq!(next_with_initial(&domains, 4, 'b'), Some(1));
+        assert_eq!(next_with_initial(&domains, 0, 'a'), Some(0));
+        assert_eq!(next_with_initial(&domains, 0, 'z'), None);
+        assert_eq!(next_with_initial(&[], 0, 'a'), None);
+    }
+
+    #[test]
+    fn test_share_line() {
+        assert_eq!(
+            *share_line("example.com", "p4ss word"),
+            "domain: example.com password: p4ss word"
+        );
+    }
+
+    #[test]
+    fn test_fit_width() {
+        assert_eq!(fit_width("münchen.de", 10), "münchen.de");
+        assert_eq!(fit_width("münchen.de", 8), "münchen…");
+        // emoji take two columns
+        assert_eq!(fit_width("🦀🦀🦀.rs", 6), "🦀🦀…");
+        assert_eq!(fit_width("🦀🦀🦀.rs", 4), "🦀…");
+        assert!(fit_width(&"é".repeat(40), 32).width() <= 32);
+    }
+
+    #[test]
+    fn test_hidden_value_long_domain() {
+        let hidden = hidden_value("ex\u{0301}".repeat(20));
+
+        assert!(hidden.starts_with(&format!("  {}e… : ", "ex\u{0301}".repeat(15))));
+    }
+
+    #[test]
+    fn test_hide_expired() {
+        let start = Instant::now();
+        let mut shown = vec![
+            (0, start),
+            (3, start + Duration::from_secs(35)),
+            (7, start + Duration::from_secs(10)),
+        ];
+        hide_expired(
+            &mut shown,
+            Duration::from_secs(30),
+            start + Duration::from_secs(40),
+        );
+
+        assert_eq!(shown, vec![(3, start + Duration::from_secs(35))]);
+    }
+
+    // home screen listing `count` records, the users file is removed right away
+    fn home_with_records(name: &str, count: usize) -> Home {
+        dotenv().ok();
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let username = format!("keeper-crabby-{}-{}", name, std::process::id());
+        let config =
+            RecordOperationConfig::new(&username, "password", "example.com", "password", &path);
+        let user = User::new(&config).unwrap();
+        delete_user(&path, &username, 1).unwrap();
+
+        let mut home = Home::new(user, Position::default(), Rect::new(0, 0, 120, 40));
+        home.secrets.secrets = (0..count)
+            .map(|i| (format!("example{}.com", i), "password".to_string()))
+            .collect();
+        home.labels = vec![String::new(); count];
+        home
+    }
+
+    #[test]
+    fn test_buffer_to_render_many_records() {
+        let mut home = home_with_records("buffer", 20_000);
+        home.scroll_to_bottom(home.area);
+        let buffer = home.buffer_to_render(&Theme::default(), 36);
+
+        assert_eq!(home.secrets.selected_secret, MAX_LISTED_SECRETS - 1);
+        assert_eq!(
+            home.buffer_area().height as usize,
+            MAX_LISTED_SECRETS * DOMAIN_PWD_LIST_ITEM_HEIGHT as usize + 1
+        );
+        // the last listed entry is rendered at its row
+        assert_eq!(buffer.area().bottom(), home.buffer_area().height);
+        let last_row = buffer.area().bottom() - 3;
+        let line: String = (0..buffer.area().width)
+            .map(|x| buffer[(x, last_row)].symbol().to_string())
+            .collect();
+        assert!(line.contains("example16382.com"));
+        assert!(home
+            .status()
+            .contains("records: 20000 (first 16383 listed)"));
+    }
+
+    #[test]
+    fn test_empty_home() {
+        let mut home = home_with_records("empty", 0);
+        let mut app = Application::create(
+            PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap()),
+            home.area,
+        )
+        .into_inner();
+        for key in ['j', 'k', 'j', 'J', 'K', 'G'] {
+            app = home.handle_key(&KeyEvent::from(KeyCode::Char(key)), &app);
+        }
+        for action in [
+            Action::PageDown,
+            Action::PageUp,
+            Action::Reveal,
+            Action::Delete,
+        ] {
+            let key = app.immutable_app_state.keymap.key(action);
+            app = home.handle_key(&KeyEvent::from(key), &app);
+        }
+
+        assert_eq!(home.secrets.selected_secret, 0);
+        assert_eq!(home.position.offset_y, 0);
+        assert!(app.mutable_app_state.popups.is_empty());
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|f| home.render(f, &app, f.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        let line: String = (0..120)
+            .map(|x| buffer[(x, 20)].symbol().to_string())
+            .collect();
+        assert!(line.contains("No records — press 'a' to add one"));
+    }
+
+    #[test]
+    fn test_top_and_bottom_keys() {
+        let mut home = home_with_records("top-bottom", 30);
+        let mut app = Application::create(
+            PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap()),
+            home.area,
+        )
+        .into_inner();
+        let mut press = |home: &mut Home, key| {
+            app = home.handle_key(&KeyEvent::from(KeyCode::Char(key)), &app);
+        };
+
+        press(&mut home, 'G');
+        assert_eq!(home.secrets.selected_secret, 29);
+
+        // another key between the two g cancels the jump
+        for key in ['g', 'k', 'g'] {
+            press(&mut home, key);
+        }
+        assert_eq!(home.secrets.selected_secret, 28);
+        assert!(home.pending_g);
+
+        press(&mut home, 'g');
+        assert_eq!(home.secrets.selected_secret, 0);
+        assert_eq!(home.position.offset_y, 0);
+        assert!(!home.pending_g);
+
+        assert!(Home::legend(&Keymap::default()).contains(" gg/G top/bottom "));
+    }
+
+    #[test]
+    fn test_confirm_without_action() {
+        let mut home = home_with_records("confirm-none", 3);
+        let app = Application::create(
+            PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap()),
+            home.area,
+        )
+        .into_inner();
+        let mut popup = ConfirmPopup::new("Confirm?".to_string());
+        popup.confirmed = true;
+
+        let app = home.handle_confirm_popup(app, Box::new(popup));
+
+        assert!(app.mutable_app_state.popups.is_empty());
+        assert_eq!(home.confirming, None);
+    }
+
+    #[test]
+    fn test_logout() {
+        let mut home = home_with_records("logout", 3);
+        let mut app = Application::create(
+            PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap()),
+            home.area,
+        )
+        .into_inner();
+        home.toggle_shown_secret();
+        home.adding = Some((
+            "example.org".to_string(),
+            String::new(),
+            "password".to_string(),
+            vec![],
+        ));
+        home.logout(&mut app);
+
+        assert!(home.secrets.secrets.is_empty());
+        assert!(home.secrets.shown_secrets.is_empty());
+        assert_eq!(home.adding, None);
+        assert_eq!(home.user.record_count(), 0);
+        assert!(matches!(app.state, ScreenState::Login(_)));
+    }
+
+    #[test]
+    fn test_shown_name() {
+        let mut home = home_with_records("shown-name", 1);
+        home.labels = vec!["work".to_string()];
+        let (domain, _) = home.secrets.secrets[0].clone();
+        home.privacy = false;
+        assert_eq!(home.selected_name(), format!("{} (work)", domain));
+
+        home.privacy = true;
+        assert_eq!(home.selected_name(), "the selected record");
+    }
+
+    #[test]
+    fn test_lock_and_unlock() {
+        dotenv().ok();
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let username = format!("keeper-crabby-lock-{}", std::process::id());
+        let mut config =
+            RecordOperationConfig::new(&username, "password", "example.com", "password", &path);
+        let mut user = User::new(&config).unwrap();
+        config.domain = "example2.com".to_string();
+        user.add_record(config).unwrap();
+        let mut home = Home::new(user, Position::default(), Rect::new(0, 0, 120, 40));
+        home.toggle_shown_secret();
+
+        home.lock();
+        let locked = (home.secrets.secrets.len(), home.user.record_count());
+        let wrong = home.unlock("wrong");
+        let still_locked = home.secrets.secrets.len();
+        let unlocked = home.unlock("password");
+
+        delete_user(&path, &username, 1).unwrap();
+
+        assert_eq!(locked, (0, 0));
+        assert!(wrong.is_err());
+        assert_eq!(still_locked, 0);
+        assert!(unlocked.is_ok());
+        assert_eq!(
+            home.secrets.secrets,
+            vec![
+                ("example.com".to_string(), "password".to_string()),
+                ("example2.com".to_string(), "password".to_string()),
+            ]
+        );
+        assert!(!home.is_shown(0));
+    }
+
+    #[test]
+    fn test_delete_last_record() {
+        dotenv().ok();
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let username = format!("keeper-crabby-delete-last-{}", std::process::id());
+        let config =
+            RecordOperationConfig::new(&username, "password", "example.com", "password", &path);
+        let user = User::new(&config).unwrap();
+        let mut home = Home::new(user, Position::default(), Rect::new(0, 0, 120, 40));
+        let app = Application::create(path.clone(), home.area).into_inner();
+
+        let deleted = home.delete_selected("password", &app);
+        let reloaded = User::from(&path, &username, "password");
+
+        delete_user(&path, &username, 1).unwrap();
+
+        assert_eq!(deleted, Ok("Deleted example.com".to_string()));
+        assert!(home.secrets.secrets.is_empty());
+        assert_eq!(reloaded.unwrap().record_count(), 0);
+    }
+
+    #[test]
+    fn test_status_with_tag() {
+        dotenv().ok();
+        let path = PathBuf::from(env::var("KEEPER_CRABBY_TEMP_DIR").unwrap());
+        let username = format!("keeper-crabby-status-tag-{}", std::process::id());
+        let config =
+            RecordOperationConfig::new(&username, "password", "example.com", "password", &path);
+        let mut user = User::new(&config).unwrap();
+        user.add_record(RecordOperationConfig {
+            domain: "example2.com".to_string(),
+            tags: vec!["work".to_string()],
+            ..config.clone()
+        })
+        .unwrap();
+        user.add_record(RecordOperationConfig {
+            domain: "example3.com".to_string(),
+            ..config.clone()
+        })
+        .unwrap();
+        let mut home = Home::new(user, Position::default(), Rect::new(0, 0, 120, 40));
+        let unfiltered = home.status();
+        home.tag = Some("work".to_string());
+        home.reload_secrets();
+        let filtered = home.status();
+
+        delete_user(&path, &username, 1).unwrap();
+
+        assert_eq!(unfiltered, format!(" user: {} | records: 3 ", username));
+        assert_eq!(
+            filtered,
+            format!(
+                " user: {} | records: 3 | filtered: 1/3 | tag: work ",
+                username
+            )
+        );
+    }
+
+    #[test]
+    fn test_reveal_needs_master() {
+        let mut home = home_with_records("reveal", 3);
+        let now = Instant::now();
+        home.reveal_requires_master = false;
+        assert!(!home.reveal_needs_master(now));
+
+        home.reveal_requires_master = true;
+        assert!(home.reveal_needs_master(now));
+        assert_eq!(
+            home.reveal_with_master("wrong"),
+            Err("Wrong master password".to_string())
+        );
+        assert!(!home.is_shown(0));
+
+        // hiding does not ask
+        home.toggle_shown_secret();
+        assert!(!home.reveal_needs_master(now));
+        home.toggle_shown_secret();
+
+        home.reveal_unlocked_at = Some(now);
+        assert!(!home.reveal_needs_master(now + Duration::from_secs(59)));
+        assert!(home.reveal_needs_master(now + REVEAL_GRACE));
+    }
+
+    #[test]
+    fn test_buffer_to_render_marks_stale_secrets() {
+        let mut home = home_with_records("stale", 3);
+        // a new user has nothing stale
+        assert_eq!(
+            stale_flags(
+                &home.user,
+                &home.secrets.secrets,
+                &home.labels,
+                Some(Duration::from_secs(60))
+            ),
+            vec![false; 3]
+        );
+        home.stale = vec![false, true];
+        let buffer = home.buffer_to_render(&Theme::default(), 12);
+        let row = |y: u16| -> String {
+            (0..buffer.area().width)
+                .map(|x| buffer[(x, y)].symbol().to_string())
+                .collect()
+        };
+
+        assert!(!row(2).contains('!'));
+        assert!(row(6).contains("! example1.com"));
+        assert!(!row(10).contains('!'));
+    }
+
+    #[test]
+    fn test_buffer_to_render_masks_offscreen_secrets() {
+        let mut home = home_with_records("offscreen", 10);
+        // the first two entries fit in a view of 8 rows, the buffer holds more
+        home.secrets.shown_secrets = (0..4).map(|i| (i, Instant::now())).collect();
+        let buffer = home.buffer_to_render(&Theme::default(), 8);
+        let row = |y: u16| -> String {
+            (0..buffer.area().width)
+                .map(|x| buffer[(x, y)].symbol().to_string())
+                .collect()
+        };
+
+        assert!(buffer.area().bottom() > 4 * DOMAIN_PWD_LIST_ITEM_HEIGHT);
+        assert!(row(2).contains("example0.com : password"));
+        assert!(row(6).contains("example1.com : password"));
+        assert!(row(10).contains("example2.com"));
+        assert!(!row(10).contains("password"));
+        assert!(!row(14).contains("password"));
+    }
+
+    #[test]
+    fn test_scrollbars_only_when_needed() {
+        let area = Rect::new(0, 0, 100, 30);
+        let rendered = |home: &Home| {
+            let mut buffer = Buffer::empty(area);
+            home.render_list(&mut buffer, area, &Theme::default());
+            buffer
+        };
+        let column = |buffer: &Buffer, x: u16| -> String {
+            (1..area.height - 1)
+                .map(|y| buffer[(x, y)].symbol().to_string())
+                .collect()
+        };
+
+        let single = rendered(&home_with_records("scrollbars_single", 1));
+        let mut many = home_with_records("scrollbars_many", 100);
+        let scrolling = rendered(&many);
+        many.scrollbars = false;
+        let disabled = rendered(&many);
+
+        // nothing is drawn next to the right border, the view reaches it
+        assert_eq!(column(&single, area.width - 2).trim(), "");
+        assert_eq!(column(&single, area.width - 3).trim(), "");
+        assert!(!column(&single, 1).trim().is_empty());
+        assert!(column(&scrolling, area.width - 3).contains('▕'));
+        assert!(!column(&disabled, area.width - 3).contains('▕'));
+    }
+
+    #[test]
+    fn test_restore_view_state() {
+        let mut home = home_with_records("view", 100);
+        home.jump(60, home.area);
+        let view = home.view_state().unwrap();
+        assert_eq!(view.domain, "example60.com");
+
+        let mut restored = home_with_records("view-restored", 100);
+        restored.restore(&view);
+        assert_eq!(restored.secrets.selected_secret, 60);
+        assert_eq!(restored.position, home.position);
+
+        // the record is found by its domain after the list changed
+        let mut restored = home_with_records("view-moved", 100);
+        restored.secrets.secrets.reverse();
+        restored.restore(&view);
+        assert_eq!(restored.secrets.selected_secret, 39);
+
+        // a record that is gone keeps the top of the list
+        let mut restored = home_with_records("view-gone", 10);
+        restored.restore(&view);
+        assert_eq!(restored.secrets.selected_secret, 0);
+        assert_eq!(restored.position, Position::default());
+    }
+
+    #[test]
+    fn test_jump_target_past_top_and_bottom() {
+        assert_eq!(jump_target(2, -10, 20), 0);
+        assert_eq!(jump_target(15, 10, 20), 19);
+        assert_eq!(jump_target(0, 10, 1), 0);
+    }
+}
@@ -1,10 +1,16 @@
-use std::path::PathBuf;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 
 use ratatui::{
     crossterm::event::{KeyCode, KeyEvent},
     layout::Rect,
     prelude::{Constraint, Direction, Layout},
-    style::{Color, Style},
+    style::Style,
     text::{Line, Span},
     widgets::{Block, Paragraph},
     Frame,
@@ -13,17 +19,57 @@ use ratatui::{
 use crate::{
     crypto::{check_user, user::User},
     ui::{
-        centered_rect,
+        centered_rect, pop_grapheme,
         popups::message_popup::MessagePopup,
         states::{
             home_state::{Home, Position},
             startup_state::StartUp,
             ScreenState, State,
         },
+        view_state::ViewState,
     },
     Application,
 };
 
+// delay after the first failed login, doubled for every further failure
+const LOGIN_DELAY_MS: u64 = 500;
+const MAX_LOGIN_DELAY_MS: u64 = 8000;
+
+/// Delay after a failed login, the base can be set with `KRAB_LOGIN_DELAY_MS`
+pub fn login_delay(failed_attempts: u32) -> Duration {
+    let base = env::var("KRAB_LOGIN_DELAY_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(LOGIN_DELAY_MS);
+    let factor = 1u64 << failed_attempts.saturating_sub(1).min(16);
+    Duration::from_millis(base.saturating_mul(factor).min(MAX_LOGIN_DELAY_MS))
+}
+
+/// Username the login and register screens start with, set with `KRAB_DEFAULT_USER`
+pub fn default_username() -> String {
+    default_username_from(env::var("KRAB_DEFAULT_USER").ok())
+}
+
+// a blank value is the same as none
+fn default_username_from(value: Option<String>) -> String {
+    value.map(|v| v.trim().to_string()).unwrap_or_default()
+}
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// A login running on a worker thread, the result is `None` until it is done
+#[derive(Debug, Clone)]
+pub struct PendingLogin {
+    result: Arc<Mutex<Option<Result<User, String>>>>,
+    started: Instant,
+}
+
+impl PartialEq for PendingLogin {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.result, &other.result)
+    }
+}
+
 // TODO: change to private (LoginInnerState)
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LoginState {
@@ -39,6 +85,12 @@ pub struct Login {
     pub master_password: String,
     pub state: LoginState,
     pub path: PathBuf,
+    /// Logins since the last successful one, counted when they are started
+    pub failed_attempts: u32,
+    /// The next login does not start before this, cancelling does not lift it
+    pub retry_at: Option<Instant>,
+    /// Set while the user is read, which can take a while for large vaults
+    pub pending: Option<PendingLogin>,
 }
 
 impl Login {
@@ -51,19 +103,22 @@ impl Login {
     }
 
     pub fn username_pop(&mut self) {
-        self.username.pop();
+        pop_grapheme(&mut self.username);
     }
 
     pub fn master_password_pop(&mut self) {
-        self.master_password.pop();
+        pop_grapheme(&mut self.master_password);
     }
 
-    pub fn new(path: &PathBuf) -> Self {
+    pub fn new(path: &Path) -> Self {
         Login {
-            username: String::new(),
+            username: default_username(),
             master_password: String::new(),
             state: LoginState::Username,
-            path: path.clone(),
+            path: path.to_path_buf(),
+            failed_attempts: 0,
+            retry_at: None,
+            pending: None,
         }
     }
 
@@ -71,7 +126,7 @@ impl Login {
     // this function should return a vector of cipher configs and a master pwd
     // or does it?
     pub fn login(&self) -> Result<User, String> {
-        let user_exists = check_user(&self.username, self.path.clone());
+        let user_exists = check_user(&self.username, &self.path);
         if !user_exists {
             return Err("Cannot login".to_string());
         }
@@ -83,11 +138,112 @@ impl Login {
             Err(_) => Err("Cannot login".to_string()),
         }
     }
+
+    // reads the user on a worker thread, so the screen keeps redrawing and can be cancelled
+    //
+    // The attempt is counted as failed until it succeeds, so cancelling it does
+    // not reset the delay before the next one.
+    fn start_login(&mut self) {
+        let now = Instant::now();
+        let pending = PendingLogin {
+            result: Arc::new(Mutex::new(None)),
+            started: now,
+        };
+        let result = Arc::clone(&pending.result);
+        let login = self.clone();
+        let wait = self.retry_at.map_or(Duration::ZERO, |retry_at| {
+            retry_at.saturating_duration_since(now)
+        });
+        self.failed_attempts += 1;
+        let delay = login_delay(self.failed_attempts);
+        self.retry_at = Some(now + wait + delay);
+        thread::spawn(move || {
+            // the delay of an earlier attempt that was cancelled
+            thread::sleep(wait);
+            let user = login.login();
+            if user.is_err() {
+                // slows down guessing the master password
+                thread::sleep(delay);
+            }
+            *result.lock().unwrap() = Some(user);
+        });
+        self.pending = Some(pending);
+    }
+
+    /// Move on to the home screen once the login started with Confirm is done
+    ///
+    /// Called on every tick of the main loop while the login screen is shown.
+    pub fn poll_login(&mut self, app: &mut Application) {
+        let result = match &self.pending {
+            Some(pending) => pending.result.lock().unwrap().take(),
+            None => return,
+        };
+        let result = match result {
+            Some(result) => result,
+            None => return,
+        };
+        self.pending = None;
+
+        match result {
+            Ok(user) => {
+                self.failed_attempts = 0;
+                self.retry_at = None;
+                let view = ViewState::load(&app.immutable_app_state.db_path, &user.username());
+                let read_only = user.is_read_only();
+                let unreadable = user.unreadable();
+                let mut home = Home::new(
+                    user,
+                    Position::default(),
+                    app.immutable_app_state.rect.unwrap(),
+                );
+                if let Some(view) = view {
+                    home.restore(&view);
+                }
+                app.state = ScreenState::Home(home);
+                if read_only {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(MessagePopup::new(
+                            "The vault is read-only, changes can not be saved".to_string(),
+                        )));
+                }
+                if unreadable > 0 {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(MessagePopup::new(format!(
+                            "{} record(s) could not be read and are not listed, see krab verify",
+                            unreadable
+                        ))));
+                }
+            }
+            Err(e) => {
+                app.mutable_app_state
+                    .popups
+                    .push(Box::new(MessagePopup::new(e)));
+                app.state = ScreenState::Login(self.clone());
+            }
+        }
+    }
 }
 
 impl State for Login {
-    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
         let rect = centered_rect(rect, 50, 40);
+        if let Some(pending) = &self.pending {
+            let frame = pending.started.elapsed().as_millis() / 100;
+            let text = format!(
+                "{} Decrypting the vault... (esc to cancel)",
+                SPINNER[frame as usize % SPINNER.len()]
+            );
+            let progress_p = Paragraph::new(text).block(
+                Block::bordered()
+                    .title("Login")
+                    .border_style(Style::default().fg(theme.text)),
+            );
+            f.render_widget(progress_p, Rect::new(rect.x, rect.y, rect.width, 3));
+            return;
+        }
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
@@ -101,8 +257,8 @@ impl State for Login {
         let username_p =
             Paragraph::new(text).block(Block::bordered().title("Username").border_style(
                 Style::default().fg(match self.state {
-                    LoginState::Username => Color::White,
-                    _ => Color::DarkGray,
+                    LoginState::Username => theme.text,
+                    _ => theme.inactive,
                 }),
             ));
 
@@ -110,8 +266,8 @@ impl State for Login {
         let master_password_p =
             Paragraph::new(text).block(Block::bordered().title("Master Password").border_style(
                 Style::default().fg(match self.state {
-                    LoginState::MasterPassword => Color::White,
-                    _ => Color::DarkGray,
+                    LoginState::MasterPassword => theme.text,
+                    _ => theme.inactive,
                 }),
             ));
 
@@ -122,15 +278,15 @@ impl State for Login {
 
         let quit_p = Paragraph::new(Span::raw("Quit")).block(Block::bordered().border_style(
             Style::default().fg(match self.state {
-                LoginState::Quit => Color::White,
-                _ => Color::DarkGray,
+                LoginState::Quit => theme.text,
+                _ => theme.inactive,
             }),
         ));
 
         let confirm_p = Paragraph::new(Span::raw("Confirm")).block(Block::bordered().border_style(
             Style::default().fg(match self.state {
-                LoginState::Confirm => Color::White,
-                _ => Color::DarkGray,
+                LoginState::Confirm => theme.text,
+                _ => theme.inactive,
             }),
         ));
 
@@ -140,10 +296,33 @@ impl State for Login {
         f.render_widget(confirm_p, inner_layout[1]);
     }
 
+    fn handle_paste(&mut self, text: &str, app: &Application) -> Application {
+        let mut app = app.clone();
+        if self.pending.is_none() {
+            match self.state {
+                LoginState::Username => self.username.push_str(text),
+                LoginState::MasterPassword => self.master_password.push_str(text),
+                _ => {}
+            }
+        }
+        app.state = ScreenState::Login(self.clone());
+        app
+    }
+
     fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {
         let mut app = app.clone();
         let mut change_state = false;
 
+        // only cancelling is possible while the user is read, the worker's result is
+        // dropped but the attempt stays counted
+        if self.pending.is_some() {
+            if key.code == KeyCode::Esc {
+                self.pending = None;
+            }
+            app.state = ScreenState::Login(self.clone());
+            return app;
+        }
+
         match self.state {
             LoginState::Username => match key.code {
                 KeyCode::Char(c) => {
@@ -177,7 +356,8 @@ impl State for Login {
             },
             LoginState::Quit => match key.code {
                 KeyCode::Enter => {
-                    app.state = ScreenState::StartUp(StartUp::new());
+                    app.state =
+                        ScreenState::StartUp(StartUp::new(&app.immutable_app_state.db_path));
                     change_state = true;
                 }
                 KeyCode::Right | KeyCode::Left | KeyCode::Tab => {
@@ -193,22 +373,7 @@ impl State for Login {
             },
             LoginState::Confirm => match key.code {
                 KeyCode::Enter => {
-                    let data = self.login();
-                    match data {
-                        Ok(d) => {
-                            app.state = ScreenState::Home(Home::new(
-                                d,
-                                Position::default(),
-                                app.immutable_app_state.rect.unwrap(),
-                            ));
-                            change_state = true;
-                        }
-                        Err(_) => {
-                            app.mutable_app_state
-                                .popups
-                                .push(Box::new(MessagePopup::new("Cannot login".to_string())));
-                        }
-                    }
+                    self.start_login();
                 }
                 KeyCode::Right | KeyCode::Left => {
                     self.state = LoginState::Quit;
@@ -230,3 +395,36 @@ impl State for Login {
         app
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_delay() {
+        assert_eq!(login_delay(1), Duration::from_millis(LOGIN_DELAY_MS));
+        assert_eq!(login_delay(2), Duration::from_millis(2 * LOGIN_DELAY_MS));
+        assert_eq!(login_delay(100), Duration::from_millis(MAX_LOGIN_DELAY_MS));
+    }
+
+    #[test]
+    fn test_cancelled_login_is_counted() {
+        let mut login = Login::new(Path::new("/nonexistent"));
+        login.start_login();
+        let first = login.retry_at.unwrap();
+        // cancelled with esc
+        login.pending = None;
+        login.start_login();
+        let second = login.retry_at.unwrap();
+
+        assert_eq!(login.failed_attempts, 2);
+        assert!(second >= first + login_delay(2));
+    }
+
+    #[test]
+    fn test_default_username_from() {
+        assert_eq!(default_username_from(Some(" alice ".to_string())), "alice");
+        assert_eq!(default_username_from(Some("  ".to_string())), "");
+        assert_eq!(default_username_from(None), "");
+    }
+}
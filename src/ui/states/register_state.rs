@@ -1,17 +1,21 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use ratatui::{
     crossterm::event::{KeyCode, KeyEvent},
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::Style,
     text::{Line, Span},
     widgets::{Block, Paragraph},
     Frame,
 };
 
 use crate::{
-    crypto::user::{RecordOperationConfig, User},
+    crypto::{
+        check_user,
+        user::{RecordOperationConfig, User},
+    },
     ui::{
+        pop_grapheme,
         popups::{
             insert_pwd_popup::{InsertPwd, InsertPwdExitState},
             message_popup::MessagePopup,
@@ -19,7 +23,7 @@ use crate::{
         },
         {
             centered_rect,
-            states::{startup_state::StartUp, ScreenState},
+            states::{login_state::default_username, startup_state::StartUp, ScreenState},
             State,
         },
     },
@@ -47,15 +51,15 @@ pub struct Register {
 }
 
 impl Register {
-    pub fn new(path: &PathBuf) -> Self {
+    pub fn new(path: &Path) -> Self {
         Register {
-            username: String::new(),
+            username: default_username(),
             master_password: String::new(),
             confirm_master_password: String::new(),
             state: RegisterState::Username,
             domain: String::new(),
             pwd: String::new(),
-            path: path.clone(),
+            path: path.to_path_buf(),
         }
     }
 
@@ -72,20 +76,40 @@ impl Register {
     }
 
     pub fn username_pop(&mut self) {
-        self.username.pop();
+        pop_grapheme(&mut self.username);
     }
 
     pub fn master_password_pop(&mut self) {
-        self.master_password.pop();
+        pop_grapheme(&mut self.master_password);
     }
 
     pub fn confirm_master_password_pop(&mut self) {
-        self.confirm_master_password.pop();
+        pop_grapheme(&mut self.confirm_master_password);
+    }
+
+    // the confirmation is typed and differs from the master password
+    fn passwords_differ(&self) -> bool {
+        !self.confirm_master_password.is_empty()
+            && self.master_password != self.confirm_master_password
+    }
+
+    // reported right away, so no master password and first record are typed in vain
+    fn check_username(&self, app: &mut Application) -> bool {
+        if check_user(&self.username, &self.path) {
+            app.mutable_app_state
+                .popups
+                .push(Box::new(MessagePopup::new(
+                    "Username already exists".to_string(),
+                )));
+            return false;
+        }
+        true
     }
 }
 
 impl State for Register {
-    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
         // need to create input widget
         // this is a temporary solution
         let rect = centered_rect(rect, 50, 40);
@@ -103,8 +127,8 @@ impl State for Register {
         let username_p =
             Paragraph::new(text).block(Block::bordered().title("Username").border_style(
                 Style::default().fg(match self.state {
-                    RegisterState::Username => Color::White,
-                    _ => Color::DarkGray,
+                    RegisterState::Username => theme.text,
+                    _ => theme.inactive,
                 }),
             ));
 
@@ -112,22 +136,30 @@ impl State for Register {
         let master_password_p =
             Paragraph::new(text).block(Block::bordered().title("Master Password").border_style(
                 Style::default().fg(match self.state {
-                    RegisterState::MasterPassword => Color::White,
-                    _ => Color::DarkGray,
+                    RegisterState::MasterPassword => theme.text,
+                    _ => theme.inactive,
                 }),
             ));
 
         let text = vec![Line::from(vec![Span::raw(
             self.confirm_master_password.clone(),
         )])];
-        let confirm_master_password_p = Paragraph::new(text).block(
-            Block::bordered()
-                .title("Confirm Master Password")
-                .border_style(Style::default().fg(match self.state {
-                    RegisterState::ConfirmMasterPassword => Color::White,
-                    _ => Color::DarkGray,
-                })),
-        );
+        let confirm_master_password_p = if self.passwords_differ() {
+            Paragraph::new(text).block(
+                Block::bordered()
+                    .title("Confirm Master Password (does not match)")
+                    .border_style(Style::default().fg(theme.error)),
+            )
+        } else {
+            Paragraph::new(text).block(
+                Block::bordered()
+                    .title("Confirm Master Password")
+                    .border_style(Style::default().fg(match self.state {
+                        RegisterState::ConfirmMasterPassword => theme.text,
+                        _ => theme.inactive,
+                    })),
+            )
+        };
 
         let inner_layout = Layout::default()
             .direction(Direction::Horizontal)
@@ -136,15 +168,16 @@ impl State for Register {
 
         let quit_p = Paragraph::new(Span::raw("Quit")).block(Block::bordered().border_style(
             Style::default().fg(match self.state {
-                RegisterState::Quit => Color::White,
-                _ => Color::DarkGray,
+                RegisterState::Quit => theme.text,
+                _ => theme.inactive,
             }),
         ));
 
+        // the button is inactive while the master passwords differ
         let register_p = Paragraph::new(Span::raw("Confirm")).block(
             Block::bordered().border_style(Style::default().fg(match self.state {
-                RegisterState::Confirm => Color::White,
-                _ => Color::DarkGray,
+                RegisterState::Confirm if !self.passwords_differ() => theme.text,
+                _ => theme.inactive,
             })),
         );
 
@@ -155,6 +188,18 @@ impl State for Register {
         f.render_widget(register_p, inner_layout[1]);
     }
 
+    fn handle_paste(&mut self, text: &str, app: &Application) -> Application {
+        let mut app = app.clone();
+        match self.state {
+            RegisterState::Username => self.username.push_str(text),
+            RegisterState::MasterPassword => self.master_password.push_str(text),
+            RegisterState::ConfirmMasterPassword => self.confirm_master_password.push_str(text),
+            _ => {}
+        }
+        app.state = ScreenState::Register(self.clone());
+        app
+    }
+
     fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {
         let mut app = app.clone();
         let mut change_state = false;
@@ -167,10 +212,10 @@ impl State for Register {
                 KeyCode::Backspace => {
                     self.username_pop();
                 }
-                KeyCode::Enter | KeyCode::Tab | KeyCode::Down => {
+                KeyCode::Enter | KeyCode::Tab | KeyCode::Down if self.check_username(&mut app) => {
                     self.state = RegisterState::MasterPassword;
                 }
-                KeyCode::Up => {
+                KeyCode::Up if self.check_username(&mut app) => {
                     self.state = RegisterState::Confirm;
                 }
                 _ => {}
@@ -207,7 +252,8 @@ impl State for Register {
             },
             RegisterState::Quit => match key.code {
                 KeyCode::Enter => {
-                    app.state = ScreenState::StartUp(StartUp::new());
+                    app.state =
+                        ScreenState::StartUp(StartUp::new(&app.immutable_app_state.db_path));
                     change_state = true;
                 }
                 KeyCode::Right | KeyCode::Left | KeyCode::Tab => {
@@ -222,11 +268,22 @@ impl State for Register {
                 _ => {}
             },
             RegisterState::Confirm => match key.code {
-                KeyCode::Enter => {
+                KeyCode::Enter if self.master_password != self.confirm_master_password => {
+                    self.state = RegisterState::ConfirmMasterPassword;
                     app.mutable_app_state
                         .popups
-                        .push(Box::new(InsertPwd::new()));
-                    change_state = true;
+                        .push(Box::new(MessagePopup::new(
+                            "Master passwords do not match".to_string(),
+                        )));
+                }
+                KeyCode::Enter => {
+                    if self.check_username(&mut app) {
+                        app.mutable_app_state
+                            .popups
+                            .push(Box::new(InsertPwd::new()));
+                    } else {
+                        self.state = RegisterState::Username;
+                    }
                 }
                 KeyCode::Right | KeyCode::Left => {
                     self.state = RegisterState::Quit;
@@ -282,29 +339,27 @@ impl State for Register {
 
         let mut app = app.clone();
 
-        let config = RecordOperationConfig::new(
-            &self.username,
-            &self.master_password,
-            &domain,
-            &pwd,
-            &self.path,
-        );
-
-        // first need to validate config
-        // match config.validate() ...
+        let config = RecordOperationConfig::builder()
+            .username(&self.username)
+            .master_pwd(&self.master_password)
+            .domain(&domain)
+            .pwd(&pwd)
+            .path(&self.path)
+            .build();
 
-        let res = User::new(&config);
+        let res = match config {
+            Ok(config) => User::new(&config).map_err(|_| "Could not create user.".to_string()),
+            Err(e) => Err(e),
+        };
 
         match res {
             Ok(_) => {
-                app.state = ScreenState::StartUp(StartUp::new());
+                app.state = ScreenState::StartUp(StartUp::new(&app.immutable_app_state.db_path));
             }
-            Err(_) => {
+            Err(e) => {
                 app.mutable_app_state
                     .popups
-                    .push(Box::new(MessagePopup::new(
-                        "Could not create user.".to_string(),
-                    )));
+                    .push(Box::new(MessagePopup::new(e)));
             }
         }
 
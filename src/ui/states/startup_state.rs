@@ -1,16 +1,23 @@
 use ratatui::{
     crossterm::event::{KeyCode, KeyEvent},
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style, Stylize},
+    style::{Style, Stylize},
     text::Line,
     widgets::{Block, Padding, Paragraph},
     Frame,
 };
 
+use std::path::Path;
+
 use crate::{
+    db::count_user_files,
     ui::{
         centered_rect,
-        states::{login_state::Login, register_state::Register, ScreenState, State},
+        popups::{about_popup::AboutPopup, settings_popup::SettingsPopup},
+        states::{
+            delete_account_state::DeleteAccount, home_state::Home, login_state::Login,
+            register_state::Register, ScreenState, State,
+        },
     },
     Application,
 };
@@ -19,31 +26,43 @@ use crate::{
 pub enum StartUpState {
     Login,
     Register,
+    DeleteAccount,
+    Generate,
+    Settings,
+    About,
     Quit,
 }
 
 #[derive(Clone)]
 pub struct StartUp {
     pub state: StartUpState,
+    /// Users in the data directory, counted when the screen is entered
+    pub user_count: usize,
 }
 
 impl StartUp {
-    pub fn new() -> Self {
+    pub fn new(db_path: &Path) -> Self {
         StartUp {
             state: StartUpState::Login,
+            user_count: count_user_files(db_path),
         }
     }
 }
 
 impl State for StartUp {
-    fn render(&self, f: &mut Frame, _app: &Application, rect: Rect) {
+    fn render(&self, f: &mut Frame, app: &Application, rect: Rect) {
+        let theme = app.immutable_app_state.theme;
         let rect = centered_rect(rect, 50, 40);
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
-                Constraint::Length(5),
-                Constraint::Length(5),
-                Constraint::Length(5),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
             ])
             .split(rect);
 
@@ -52,8 +71,8 @@ impl State for StartUp {
             .block(
                 Block::bordered()
                     .border_style(Style::default().fg(match self.state {
-                        StartUpState::Login => Color::White,
-                        _ => Color::DarkGray,
+                        StartUpState::Login => theme.text,
+                        _ => theme.inactive,
                     }))
                     .padding(Padding::new(1, 0, layout[0].height / 4, 0)),
             )
@@ -65,30 +84,86 @@ impl State for StartUp {
             .block(
                 Block::bordered()
                     .border_style(Style::default().fg(match self.state {
-                        StartUpState::Register => Color::White,
-                        _ => Color::DarkGray,
+                        StartUpState::Register => theme.text,
+                        _ => theme.inactive,
                     }))
                     .padding(Padding::new(1, 0, layout[1].height / 4, 0)),
             )
             .style(Style::new().white())
             .alignment(Alignment::Left);
 
+        let text = vec![Line::from(vec!["Delete account".into()])];
+        let delete_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .border_style(Style::default().fg(match self.state {
+                        StartUpState::DeleteAccount => theme.text,
+                        _ => theme.inactive,
+                    }))
+                    .padding(Padding::new(1, 0, layout[2].height / 4, 0)),
+            )
+            .style(Style::new().white())
+            .alignment(Alignment::Left);
+
+        let text = vec![Line::from(vec!["Copy a generated password".into()])];
+        let generate_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .border_style(Style::default().fg(match self.state {
+                        StartUpState::Generate => theme.text,
+                        _ => theme.inactive,
+                    }))
+                    .padding(Padding::new(1, 0, layout[3].height / 4, 0)),
+            )
+            .style(Style::new().white())
+            .alignment(Alignment::Left);
+
+        let text = vec![Line::from(vec!["Settings".into()])];
+        let settings_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .border_style(Style::default().fg(match self.state {
+                        StartUpState::Settings => theme.text,
+                        _ => theme.inactive,
+                    }))
+                    .padding(Padding::new(1, 0, layout[4].height / 4, 0)),
+            )
+            .style(Style::new().white())
+            .alignment(Alignment::Left);
+
+        let text = vec![Line::from(vec!["About".into()])];
+        let about_p = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .border_style(Style::default().fg(match self.state {
+                        StartUpState::About => theme.text,
+                        _ => theme.inactive,
+                    }))
+                    .padding(Padding::new(1, 0, layout[5].height / 4, 0)),
+            )
+            .style(Style::new().white())
+            .alignment(Alignment::Left);
+
         let text = vec![Line::from(vec!["Quit".into()])];
         let quit_p = Paragraph::new(text)
             .block(
                 Block::bordered()
                     .border_style(Style::default().fg(match self.state {
-                        StartUpState::Quit => Color::White,
-                        _ => Color::DarkGray,
+                        StartUpState::Quit => theme.text,
+                        _ => theme.inactive,
                     }))
-                    .padding(Padding::new(1, 0, layout[2].height / 4, 0)),
+                    .padding(Padding::new(1, 0, layout[6].height / 4, 0)),
             )
             .style(Style::new().white())
             .alignment(Alignment::Left);
 
         f.render_widget(login_p, layout[0]);
         f.render_widget(register_p, layout[1]);
-        f.render_widget(quit_p, layout[2]);
+        f.render_widget(delete_p, layout[2]);
+        f.render_widget(generate_p, layout[3]);
+        f.render_widget(settings_p, layout[4]);
+        f.render_widget(about_p, layout[5]);
+        f.render_widget(quit_p, layout[6]);
     }
 
     fn handle_key(&mut self, key: &KeyEvent, app: &Application) -> Application {
@@ -121,13 +196,66 @@ impl State for StartUp {
                     change_state = true;
                 }
                 KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
-                    self.state = StartUpState::Quit;
+                    self.state = StartUpState::DeleteAccount;
                 }
                 KeyCode::Up | KeyCode::Char('k') => {
                     self.state = StartUpState::Login;
                 }
                 _ => {}
             },
+            StartUpState::DeleteAccount => match key.code {
+                KeyCode::Enter => {
+                    app.state = ScreenState::DeleteAccount(DeleteAccount::new(
+                        &app.immutable_app_state.db_path,
+                    ));
+                    change_state = true;
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                    self.state = StartUpState::Generate;
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state = StartUpState::Register;
+                }
+                _ => {}
+            },
+            StartUpState::Generate => match key.code {
+                KeyCode::Enter => Home::generate_and_quit(&mut app),
+                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                    self.state = StartUpState::Settings;
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state = StartUpState::DeleteAccount;
+                }
+                _ => {}
+            },
+            StartUpState::Settings => match key.code {
+                KeyCode::Enter => {
+                    app.mutable_app_state
+                        .popups
+                        .push(Box::new(SettingsPopup::read_only(
+                            app.immutable_app_state.clipboard_disabled,
+                        )));
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                    self.state = StartUpState::About;
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state = StartUpState::Generate;
+                }
+                _ => {}
+            },
+            StartUpState::About => match key.code {
+                KeyCode::Enter => {
+                    app.mutable_app_state.popups.push(Box::new(AboutPopup));
+                }
+                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                    self.state = StartUpState::Quit;
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state = StartUpState::Settings;
+                }
+                _ => {}
+            },
             StartUpState::Quit => match key.code {
                 KeyCode::Enter => {
                     app.mutable_app_state.running = false;
@@ -136,7 +264,7 @@ impl State for StartUp {
                     self.state = StartUpState::Login;
                 }
                 KeyCode::Up | KeyCode::Char('k') => {
-                    self.state = StartUpState::Register;
+                    self.state = StartUpState::About;
                 }
                 _ => {}
             },
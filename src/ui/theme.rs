@@ -0,0 +1,181 @@
+use directories::ProjectDirs;
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::{env, fs, path::PathBuf};
+
+// named colors that can be used instead of a hex value
+const NAMED_COLORS: [(&str, Color); 16] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+/// Parse a color from `#rrggbb`, `#rgb` or a name like `red` or `lightcyan`
+///
+/// # Arguments
+///
+/// * `value` - The color, names are case insensitive
+///
+/// # Returns
+/// * `Result<Color, String>` - The color or an error message
+pub fn from(value: &str) -> Result<Color, String> {
+    let value = value.trim();
+    let hex = match value.strip_prefix('#') {
+        Some(hex) => hex,
+        None => {
+            return NAMED_COLORS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(value))
+                .map(|(_, color)| *color)
+                .ok_or(format!("Unknown color name: {}", value))
+        }
+    };
+
+    let invalid = || format!("Invalid hex color: {}", value);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).map_err(|_| invalid());
+    match hex.len() {
+        // each digit is doubled, #f0c is #ff00cc
+        3 => Ok(Color::Rgb(
+            channel(&hex[0..1].repeat(2))?,
+            channel(&hex[1..2].repeat(2))?,
+            channel(&hex[2..3].repeat(2))?,
+        )),
+        6 => Ok(Color::Rgb(
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        _ => Err(invalid()),
+    }
+}
+
+/// Colors of the TUI
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Text, borders and the focused field
+    pub text: Color,
+    /// Fields and buttons that are not focused
+    pub inactive: Color,
+    /// Scrollbar thumbs
+    pub accent: Color,
+    /// Errors and the most severe findings
+    pub error: Color,
+    /// Findings that should be looked at
+    pub warning: Color,
+    /// Findings of low severity
+    pub info: Color,
+    /// Background of the selected record
+    pub selected_bg: Color,
+    /// Foreground of the selected record
+    pub selected_fg: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            text: Color::White,
+            inactive: Color::DarkGray,
+            accent: Color::Yellow,
+            error: Color::Red,
+            warning: Color::Rgb(255, 165, 0),
+            info: Color::Cyan,
+            selected_bg: Color::Rgb(202, 220, 252),
+            selected_fg: Color::Rgb(0, 36, 107),
+        }
+    }
+}
+
+// every field is optional, missing ones keep the built-in color
+#[derive(Debug, Default, Deserialize)]
+struct ThemeFile {
+    text: Option<String>,
+    inactive: Option<String>,
+    accent: Option<String>,
+    error: Option<String>,
+    warning: Option<String>,
+    info: Option<String>,
+    selected_bg: Option<String>,
+    selected_fg: Option<String>,
+}
+
+impl Theme {
+    /// Load the theme from `KRAB_THEME` or `theme.toml` in the config directory
+    ///
+    /// Falls back to the built-in colors if the file is missing or can not be
+    /// parsed, and per field if a color is invalid.
+    pub fn load() -> Self {
+        let path = match env::var("KRAB_THEME") {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => ProjectDirs::from("", "", "keeper-crabby")
+                .map(|dirs| dirs.config_dir().join("theme.toml")),
+        };
+        match path.and_then(|path| fs::read_to_string(path).ok()) {
+            Some(content) => Theme::from_toml(&content),
+            None => Theme::default(),
+        }
+    }
+
+    fn from_toml(content: &str) -> Self {
+        let file: ThemeFile = toml::from_str(content).unwrap_or_default();
+        let default = Theme::default();
+        let color = |value: Option<String>, default: Color| {
+            value.and_then(|v| from(&v).ok()).unwrap_or(default)
+        };
+
+        Theme {
+            text: color(file.text, default.text),
+            inactive: color(file.inactive, default.inactive),
+            accent: color(file.accent, default.accent),
+            error: color(file.error, default.error),
+            warning: color(file.warning, default.warning),
+            info: color(file.info, default.info),
+            selected_bg: color(file.selected_bg, default.selected_bg),
+            selected_fg: color(file.selected_fg, default.selected_fg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_success() {
+        assert_eq!(from("#f0c"), Ok(Color::Rgb(0xff, 0x00, 0xcc)));
+        assert_eq!(from("#F0ECC9"), Ok(Color::Rgb(0xf0, 0xec, 0xc9)));
+        assert_eq!(from("Cyan"), Ok(Color::Cyan));
+    }
+
+    #[test]
+    fn test_from_fail() {
+        assert!(from("#zz").is_err());
+        assert!(from("#12345").is_err());
+        assert!(from("purple-ish").is_err());
+    }
+
+    #[test]
+    fn test_theme_from_toml() {
+        let theme = Theme::from_toml("text = \"#f0c\"\nerror = \"cyan\"\naccent = \"#zz\"\n");
+
+        assert_eq!(theme.text, Color::Rgb(0xff, 0x00, 0xcc));
+        assert_eq!(theme.error, Color::Cyan);
+        assert_eq!(theme.accent, Theme::default().accent);
+        assert_eq!(Theme::from_toml("not toml ="), Theme::default());
+    }
+}
@@ -0,0 +1,105 @@
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::crypto::{hash, normalize_username};
+
+/// Where a user left the home screen, restored on their next login
+///
+/// Kept unencrypted in the config directory, in a file named after the hash of
+/// the data directory and the username, so users of the same name in different
+/// vaults keep their own. The domain and label of the selected record are UI
+/// state like the scroll position, no password is stored.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ViewState {
+    /// Domain of the selected record
+    pub domain: String,
+    /// Label of the selected record, empty for none
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    pub offset_y: u16,
+}
+
+// file of a user, `None` if there is no config directory
+fn path(data_dir: &Path, username: &str) -> Option<PathBuf> {
+    ProjectDirs::from("", "", "keeper-crabby").map(|dirs| {
+        dirs.config_dir().join("view_state").join(hash(format!(
+            "{}\n{}",
+            data_dir.display(),
+            normalize_username(username)
+        )))
+    })
+}
+
+impl ViewState {
+    /// View state with the record of `domain` and `label` selected
+    pub fn new(domain: &str, label: &str, offset_y: u16) -> Self {
+        ViewState {
+            domain: domain.to_string(),
+            label: label.to_string(),
+            offset_y,
+        }
+    }
+
+    /// The view state a user left the home screen with, `None` if there is none
+    pub fn load(data_dir: &Path, username: &str) -> Option<Self> {
+        let content = fs::read_to_string(path(data_dir, username)?).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    /// Store the view state of a user
+    ///
+    /// # Arguments
+    ///
+    /// * `data_dir` - The data directory the user is in
+    /// * `username` - The username of the user
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Nothing or an error message
+    pub fn save(&self, data_dir: &Path, username: &str) -> Result<(), String> {
+        let path = match path(data_dir, username) {
+            Some(path) => path,
+            None => return Err("Could not get the config directory".to_string()),
+        };
+        let content = match toml::to_string(self) {
+            Ok(content) => content,
+            Err(_) => return Err("Could not serialize the view state".to_string()),
+        };
+        if let Some(dir) = path.parent() {
+            if fs::create_dir_all(dir).is_err() {
+                return Err("Could not create the view state directory".to_string());
+            }
+        }
+
+        fs::write(path, content).map_err(|_| "Could not write the view state".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_state_toml() {
+        let view = ViewState::new("example.com", "", 12);
+        let content = toml::to_string(&view).unwrap();
+
+        assert_eq!(content, "domain = \"example.com\"\noffset_y = 12\n");
+        assert_eq!(toml::from_str::<ViewState>(&content).unwrap(), view);
+
+        let view = ViewState::new("bank.com", "pin", 0);
+        let content = toml::to_string(&view).unwrap();
+        assert_eq!(toml::from_str::<ViewState>(&content).unwrap(), view);
+    }
+
+    #[test]
+    fn test_path_per_data_dir() {
+        let first = path(Path::new("/srv/first"), "crabby");
+
+        assert_eq!(first, path(Path::new("/srv/first"), "Crabby"));
+        assert_ne!(first, path(Path::new("/srv/second"), "crabby"));
+    }
+}